@@ -1,10 +1,18 @@
-use std::path::Path;
+use std::{fmt::Write, path::Path};
+
+#[allow(dead_code)]
+#[path = "src/graphics/preprocessor.rs"]
+mod preprocessor;
+
+use preprocessor::WgslPreprocessor;
 
 const SRC_DIR: &str = "src/graphics/shaders/wgsl";
 
 fn main() {
     println!("cargo:rerun-if-changed={}", SRC_DIR);
 
+    let mut shader_paths = Vec::new();
+
     for entry in std::fs::read_dir(SRC_DIR).expect("Shaders directory should exist") {
         let entry = entry.unwrap();
         let path = entry.path();
@@ -12,16 +20,45 @@ fn main() {
         if let Some(extension) = path.extension().and_then(|os_str| os_str.to_str()) {
             if extension.to_ascii_lowercase().as_str() == "wgsl" {
                 println!("cargo:rerun-if-changed={}", path.to_string_lossy());
-                compile_shader(path);
+                shader_paths.push(path);
             }
         }
     }
+
+    // Keep the generated layouts in a stable order.
+    shader_paths.sort();
+
+    // Any shader can be included by the others.
+    let preprocessor = shader_paths.iter().fold(WgslPreprocessor::new(), |preprocessor, path| {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let source = std::fs::read_to_string(path).expect("Shader source should be available");
+        preprocessor.include(name, source)
+    });
+
+    let mut layouts = String::from("pub const SHADER_LAYOUTS: &[ShaderLayout] = &[\n");
+
+    for path in shader_paths {
+        let module = compile_shader(&preprocessor, &path);
+        let file = path.file_name().unwrap().to_string_lossy();
+        write_shader_layout(&mut layouts, &file, &module);
+    }
+
+    layouts.push_str("];\n");
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR should be set by cargo");
+    std::fs::write(Path::new(&out_dir).join("shader_layouts.rs"), layouts)
+        .expect("Failed to write shader layouts");
 }
 
-fn compile_shader<P: AsRef<Path>>(path: P) {
+fn compile_shader<P: AsRef<Path>>(preprocessor: &WgslPreprocessor, path: P) -> naga::Module {
     let path = path.as_ref();
     let shader_source = std::fs::read_to_string(path).expect("Shader source should be available");
 
+    let shader_source = preprocessor
+        .process(&shader_source)
+        .inspect_err(|e| println!("{}: {e}", path.display()))
+        .expect("Shader preprocessing failed");
+
     let module = naga::front::wgsl::parse_str(&shader_source)
         .inspect_err(|e| {
             let msg = e.emit_to_string(&shader_source);
@@ -29,10 +66,115 @@ fn compile_shader<P: AsRef<Path>>(path: P) {
         })
         .expect("Shader compilation failed");
 
+    // Must match `validate_wgsl` in src/graphics/hot_reload.rs, which validates the
+    // reloaded versions of these shaders.
     let _info = naga::valid::Validator::new(
         naga::valid::ValidationFlags::all(),
         naga::valid::Capabilities::empty(),
     )
     .validate(&module)
     .expect("Shader validation failed");
+
+    module
+}
+
+/// Writes the structs and vertex inputs of a shader as a `ShaderLayout` expression,
+/// see src/graphics/layouts.rs.
+fn write_shader_layout(output: &mut String, file: &str, module: &naga::Module) {
+    writeln!(output, "    ShaderLayout {{\n        file: {:?},\n        structs: &[", file)
+        .unwrap();
+
+    for (_, ty) in module.types.iter() {
+        let (Some(name), naga::TypeInner::Struct { members, span }) = (&ty.name, &ty.inner) else {
+            continue;
+        };
+
+        writeln!(
+            output,
+            "            StructLayout {{ name: {:?}, size: {}, members: &[",
+            name, span
+        )
+        .unwrap();
+
+        for member in members {
+            let size = module.types[member.ty].inner.size(module.to_ctx());
+
+            writeln!(
+                output,
+                "                MemberLayout {{ name: {:?}, offset: {}, size: {} }},",
+                member.name.as_deref().unwrap_or_default(),
+                member.offset,
+                size
+            )
+            .unwrap();
+        }
+
+        output.push_str("            ] },\n");
+    }
+
+    output.push_str("        ],\n        vertex_inputs: &[\n");
+
+    for entry_point in &module.entry_points {
+        if entry_point.stage != naga::ShaderStage::Vertex {
+            continue;
+        }
+
+        for argument in &entry_point.function.arguments {
+            let ty = &module.types[argument.ty];
+
+            // Arguments are either bound themselves, or structs with bound members.
+            let inputs: Vec<_> = match (&argument.binding, &ty.inner) {
+                (Some(binding), _) => vec![(argument.name.as_deref(), binding, argument.ty)],
+                (None, naga::TypeInner::Struct { members, .. }) => members
+                    .iter()
+                    .filter_map(|member| {
+                        Some((member.name.as_deref(), member.binding.as_ref()?, member.ty))
+                    })
+                    .collect(),
+                (None, _) => Vec::new(),
+            };
+
+            for (name, binding, ty) in inputs {
+                let naga::Binding::Location { location, .. } = binding else {
+                    continue;
+                };
+
+                let format = match vertex_format(&module.types[ty].inner) {
+                    Some(format) => format!("Some(wgpu::VertexFormat::{})", format),
+                    None => "None".to_string(),
+                };
+
+                writeln!(
+                    output,
+                    "            VertexInputLayout {{ entry_point: {:?}, name: {:?}, location: {}, \
+                     format: {} }},",
+                    entry_point.name,
+                    name.unwrap_or_default(),
+                    location,
+                    format
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    output.push_str("        ],\n    },\n");
+}
+
+/// The `wgpu::VertexFormat` variant matching a vertex input's type, if there is one.
+fn vertex_format(inner: &naga::TypeInner) -> Option<String> {
+    let (scalar, size) = match *inner {
+        naga::TypeInner::Scalar(scalar) => (scalar, 1),
+        naga::TypeInner::Vector { size, scalar } => (scalar, size as u8),
+        _ => return None,
+    };
+
+    let base = match (scalar.kind, scalar.width) {
+        (naga::ScalarKind::Float, 4) => "Float32",
+        (naga::ScalarKind::Sint, 4) => "Sint32",
+        (naga::ScalarKind::Uint, 4) => "Uint32",
+        _ => return None,
+    };
+
+    Some(if size == 1 { base.to_string() } else { format!("{}x{}", base, size) })
 }
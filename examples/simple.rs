@@ -4,10 +4,11 @@ use simple_game::{
         text::{AxisAlign, StyledText, TextAlignment, TextSystem},
         DebugDrawer, FullscreenQuad, GraphicsDevice, Image, ImageDrawer, LineDrawer2d, LineVertex,
     },
+    input::Input,
     util::FPSCounter,
-    GameApp,
+    GameApp, GameConfig,
 };
-use winit::window::Window;
+use winit::keyboard::KeyCode;
 
 struct SimpleGame {
     fullscreen_quad: FullscreenQuad,
@@ -98,10 +99,20 @@ impl GameApp for SimpleGame {
         self.text_system.resize(width, height);
     }
 
-    fn tick(&mut self, _dt: f32) {}
+    fn tick(&mut self, _dt: f32, _input: &Input) {}
 
-    fn render(&mut self, graphics_device: &mut GraphicsDevice, _window: &Window) {
-        let mut frame_encoder = graphics_device.begin_frame();
+    fn render(&mut self, graphics_device: &mut GraphicsDevice, _alpha: f32) {
+        // Picks up edited shaders, a no-op unless hot reloading is enabled.
+        self.fullscreen_quad.reload_shaders(graphics_device);
+        self.text_system.reload_shaders(graphics_device);
+        self.debug_drawer.reload_shaders(graphics_device);
+        self.image_drawer.reload_shaders(graphics_device);
+        self.line_drawer.reload_shaders(graphics_device);
+
+        let Some(mut frame_encoder) = graphics_device.begin_frame().expect("Failed to begin frame")
+        else {
+            return;
+        };
 
         let mut render_pass =
             frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -147,15 +158,19 @@ impl GameApp for SimpleGame {
 
         drop(render_pass);
 
-        graphics_device.queue().submit(Some(frame_encoder.encoder.finish()));
-        frame_encoder.frame.present();
+        graphics_device.end_frame(frame_encoder);
 
         self.fps_counter.tick();
     }
 }
 
 fn main() -> Result<(), simple_game::Error> {
-    simple_game::run_game_app::<SimpleGame>()?;
+    simple_game::run_game_app::<SimpleGame>(
+        GameConfig::new()
+            .title("Simple Game")
+            .screenshot_key(KeyCode::F12)
+            .shader_hot_reload(cfg!(debug_assertions)),
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,92 @@
+use crate::{
+    audio::{SharedMixer, OUTPUT_CHANNELS},
+    Error,
+};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Where mixed audio goes.
+///
+/// Real-time backends pull audio from the mixer themselves from the moment they're
+/// started, usually on an audio thread. Offline backends are handed blocks of mixed
+/// audio by `Audio::render` instead, so a game or test decides how much audio is mixed.
+pub trait AudioBackend {
+    /// The sample rate the mixer should produce.
+    fn sample_rate(&self) -> u32;
+
+    /// Called once by `Audio::new`.
+    fn start(&mut self, _mixer: SharedMixer) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Receives the interleaved stereo audio mixed by `Audio::render`.
+    fn write(&mut self, _samples: &[f32]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called by `Audio::finish`, flushes any buffered output.
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Discards all audio. Voices only advance when `Audio::render` is called.
+#[derive(Debug, Copy, Clone)]
+pub struct NullBackend {
+    sample_rate: u32,
+}
+
+impl NullBackend {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate }
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+/// Writes the audio mixed by `Audio::render` to a 32-bit float stereo WAV file.
+pub struct WavFileBackend {
+    sample_rate: u32,
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+}
+
+impl WavFileBackend {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<Self, Error> {
+        let spec = hound::WavSpec {
+            channels: OUTPUT_CHANNELS as u16,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        Ok(Self { sample_rate, writer: Some(hound::WavWriter::create(path, spec)?) })
+    }
+}
+
+impl AudioBackend for WavFileBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), Error> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+
+        for &sample in samples {
+            writer.write_sample(sample)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        match self.writer.take() {
+            Some(writer) => Ok(writer.finalize()?),
+            None => Ok(()),
+        }
+    }
+}
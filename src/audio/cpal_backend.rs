@@ -0,0 +1,87 @@
+use crate::{
+    audio::{AudioBackend, SharedMixer, OUTPUT_CHANNELS},
+    Error,
+};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SizedSample,
+};
+
+/// Plays audio on the system's default output device. The mixer runs on cpal's audio
+/// thread, which locks it for every block of audio it requests.
+pub struct CpalBackend {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    stream: Option<cpal::Stream>,
+}
+
+impl CpalBackend {
+    pub fn new() -> Result<Self, Error> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| Error::AudioBackendError("No audio output device".to_string()))?;
+
+        let config = device
+            .default_output_config()
+            .map_err(|err| Error::AudioBackendError(err.to_string()))?;
+
+        Ok(Self { device, config, stream: None })
+    }
+
+    fn build_stream<T>(&self, mixer: SharedMixer) -> Result<cpal::Stream, Error>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let channels = self.config.channels() as usize;
+        let mut buffer = Vec::new();
+
+        self.device
+            .build_output_stream(
+                &self.config.config(),
+                move |output: &mut [T], _| {
+                    let frames = output.len() / channels;
+                    buffer.resize(frames * OUTPUT_CHANNELS, 0.0);
+                    mixer.lock().unwrap().mix(&mut buffer);
+
+                    // Mono devices get the left channel, extra channels stay silent.
+                    for (out, frame) in
+                        output.chunks_exact_mut(channels).zip(buffer.chunks_exact(OUTPUT_CHANNELS))
+                    {
+                        for (channel, sample) in out.iter_mut().enumerate() {
+                            let value = frame.get(channel).copied().unwrap_or(0.0);
+                            *sample = T::from_sample(value);
+                        }
+                    }
+                },
+                |err| println!("Audio output error: {}", err),
+                None,
+            )
+            .map_err(|err| Error::AudioBackendError(err.to_string()))
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate().0
+    }
+
+    fn start(&mut self, mixer: SharedMixer) -> Result<(), Error> {
+        let stream = match self.config.sample_format() {
+            cpal::SampleFormat::F32 => self.build_stream::<f32>(mixer)?,
+            cpal::SampleFormat::I16 => self.build_stream::<i16>(mixer)?,
+            cpal::SampleFormat::U16 => self.build_stream::<u16>(mixer)?,
+            cpal::SampleFormat::I32 => self.build_stream::<i32>(mixer)?,
+            format => {
+                return Err(Error::AudioBackendError(format!(
+                    "Unsupported sample format {:?}",
+                    format
+                )))
+            },
+        };
+
+        stream.play().map_err(|err| Error::AudioBackendError(err.to_string()))?;
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+}
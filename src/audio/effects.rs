@@ -0,0 +1,405 @@
+use crate::audio::OUTPUT_CHANNELS;
+use std::{
+    f32::consts::{FRAC_1_SQRT_2, TAU},
+    time::Duration,
+};
+
+/// Processes the audio of a bus, see `Mixer::add_effect`.
+///
+/// Effects are plain state machines without any randomness or timing of their own, so
+/// rendering the same voices always produces the same output.
+pub trait Effect: std::any::Any + Send {
+    /// Processes interleaved stereo in place.
+    fn process(&mut self, buffer: &mut [f32], sample_rate: u32);
+
+    /// Clears delay lines and filter state.
+    fn reset(&mut self) {}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterKind {
+    LowPass,
+    HighPass,
+}
+
+/// A second order filter, with a 12 dB per octave slope.
+///
+/// The parameters can be changed between mixes, for example to muffle everything
+/// while the player is underwater.
+#[derive(Debug, Clone)]
+pub struct BiquadFilter {
+    pub kind: FilterKind,
+    /// In Hz, clamped to just below half the sample rate.
+    pub cutoff: f32,
+    /// Resonance, the default of 1/√2 is as flat as possible without a peak.
+    pub q: f32,
+    /// The coefficients and the parameters they were computed for.
+    coefficients: Option<(FilterKind, f32, f32, u32, [f32; 5])>,
+    /// Transposed direct form II state per channel.
+    state: [[f32; 2]; OUTPUT_CHANNELS],
+}
+
+impl BiquadFilter {
+    pub fn low_pass(cutoff: f32) -> Self {
+        Self::new(FilterKind::LowPass, cutoff)
+    }
+
+    pub fn high_pass(cutoff: f32) -> Self {
+        Self::new(FilterKind::HighPass, cutoff)
+    }
+
+    fn new(kind: FilterKind, cutoff: f32) -> Self {
+        Self { kind, cutoff, q: FRAC_1_SQRT_2, coefficients: None, state: [[0.0; 2]; 2] }
+    }
+
+    /// `[b0, b1, b2, a1, a2]`, normalized by a0, from the Audio EQ Cookbook.
+    fn coefficients(&mut self, sample_rate: u32) -> [f32; 5] {
+        let key = (self.kind, self.cutoff, self.q, sample_rate);
+
+        match self.coefficients {
+            Some((kind, cutoff, q, rate, coefficients)) if (kind, cutoff, q, rate) == key => {
+                return coefficients;
+            },
+            _ => {},
+        }
+
+        let cutoff = self.cutoff.clamp(10.0, sample_rate as f32 * 0.49);
+        let omega = TAU * cutoff / sample_rate as f32;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * self.q.max(0.01));
+
+        let (b0, b1) = match self.kind {
+            FilterKind::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos),
+            FilterKind::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos)),
+        };
+
+        let a0 = 1.0 + alpha;
+        let coefficients = [b0 / a0, b1 / a0, b0 / a0, -2.0 * cos / a0, (1.0 - alpha) / a0];
+        self.coefficients = Some((self.kind, self.cutoff, self.q, sample_rate, coefficients));
+
+        coefficients
+    }
+}
+
+impl Effect for BiquadFilter {
+    fn process(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let [b0, b1, b2, a1, a2] = self.coefficients(sample_rate);
+
+        for frame in buffer.chunks_exact_mut(OUTPUT_CHANNELS) {
+            for (sample, state) in frame.iter_mut().zip(&mut self.state) {
+                let input = *sample;
+                let output = b0 * input + state[0];
+
+                state[0] = b1 * input - a1 * output + state[1];
+                state[1] = b2 * input - a2 * output;
+                *sample = output;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = [[0.0; 2]; 2];
+    }
+}
+
+/// An echo which repeats the input after `time`, each repeat `feedback` times as loud.
+///
+/// The delay line is allocated once for `max_time`, changing `time` only moves where the
+/// echoes are read from.
+#[derive(Debug, Clone)]
+pub struct Delay {
+    /// Clamped to `max_time`.
+    pub time: Duration,
+    /// From 0.0 to below 1.0, how much of each echo is repeated.
+    pub feedback: f32,
+    /// From 0.0 for only the input to 1.0 for only the echoes.
+    pub mix: f32,
+    max_time: Duration,
+    /// Interleaved stereo, one frame longer than `max_time`, and the rate it was built for.
+    buffer: Vec<f32>,
+    sample_rate: u32,
+    /// Where the next frame is written.
+    position: usize,
+}
+
+impl Delay {
+    /// The longest delay by default, unless `time` is longer already.
+    pub const DEFAULT_MAX_TIME: Duration = Duration::from_secs(1);
+
+    pub fn new(time: Duration, feedback: f32, mix: f32) -> Self {
+        Self {
+            time,
+            feedback,
+            mix,
+            max_time: time.max(Self::DEFAULT_MAX_TIME),
+            buffer: Vec::new(),
+            sample_rate: 0,
+            position: 0,
+        }
+    }
+
+    /// Sets the longest `time` this delay can be automated to.
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.max_time = max_time;
+        self.buffer = Vec::new();
+        self
+    }
+
+    pub fn max_time(&self) -> Duration {
+        self.max_time
+    }
+}
+
+impl Effect for Delay {
+    fn process(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let to_frames = |time: Duration| (time.as_secs_f64() * sample_rate as f64).round() as usize;
+        let max_frames = to_frames(self.max_time).max(1);
+
+        if self.buffer.is_empty() || self.sample_rate != sample_rate {
+            self.buffer = vec![0.0; (max_frames + 1) * OUTPUT_CHANNELS];
+            self.sample_rate = sample_rate;
+            self.position = 0;
+        }
+
+        let offset = to_frames(self.time).clamp(1, max_frames) * OUTPUT_CHANNELS;
+
+        for frame in buffer.chunks_exact_mut(OUTPUT_CHANNELS) {
+            let read = (self.position + self.buffer.len() - offset) % self.buffer.len();
+
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let input = *sample;
+                let delayed = self.buffer[read + channel];
+
+                *sample = input * (1.0 - self.mix) + delayed * self.mix;
+                self.buffer[self.position + channel] = input + delayed * self.feedback;
+            }
+
+            self.position = (self.position + OUTPUT_CHANNELS) % self.buffer.len();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+/// Comb filter lengths of Freeverb at 44.1 kHz, the right channel's are a little longer.
+const COMB_LENGTHS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_LENGTHS: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+
+/// A small Schroeder reverb after Freeverb, four damped comb filters followed by two
+/// allpass filters per channel.
+#[derive(Debug, Clone)]
+pub struct Reverb {
+    /// From 0.0 to 1.0, longer tails for larger rooms.
+    pub room_size: f32,
+    /// From 0.0 to 1.0, how quickly high frequencies die out.
+    pub damping: f32,
+    /// From 0.0 for only the input to 1.0 for only the reverb.
+    pub mix: f32,
+    /// Built for this sample rate.
+    sample_rate: u32,
+    channels: Vec<ReverbChannel>,
+}
+
+#[derive(Debug, Clone)]
+struct ReverbChannel {
+    combs: Vec<(DelayLine, f32)>,
+    allpasses: Vec<DelayLine>,
+}
+
+#[derive(Debug, Clone)]
+struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(length: usize) -> Self {
+        Self { buffer: vec![0.0; length.max(1)], position: 0 }
+    }
+
+    /// Returns the oldest sample and replaces it with `input`.
+    fn push(&mut self, input: impl FnOnce(f32) -> f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.buffer[self.position] = input(output);
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+impl Reverb {
+    pub fn new(room_size: f32, damping: f32, mix: f32) -> Self {
+        Self { room_size, damping, mix, sample_rate: 0, channels: Vec::new() }
+    }
+
+    fn build(&mut self, sample_rate: u32) {
+        let scale = |length: usize| length * sample_rate as usize / 44_100;
+
+        self.sample_rate = sample_rate;
+        self.channels = (0..OUTPUT_CHANNELS)
+            .map(|channel| {
+                let spread = channel * STEREO_SPREAD;
+
+                ReverbChannel {
+                    combs: COMB_LENGTHS
+                        .iter()
+                        .map(|length| (DelayLine::new(scale(length + spread)), 0.0))
+                        .collect(),
+                    allpasses: ALLPASS_LENGTHS
+                        .iter()
+                        .map(|length| DelayLine::new(scale(length + spread)))
+                        .collect(),
+                }
+            })
+            .collect();
+    }
+}
+
+impl Effect for Reverb {
+    fn process(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        if self.sample_rate != sample_rate {
+            self.build(sample_rate);
+        }
+
+        let feedback = 0.7 + 0.28 * self.room_size.clamp(0.0, 1.0);
+        let damping = self.damping.clamp(0.0, 1.0) * 0.4;
+
+        for frame in buffer.chunks_exact_mut(OUTPUT_CHANNELS) {
+            // Both channels feed the same mono input into differently tuned filters.
+            let input = (frame[0] + frame[1]) * 0.03;
+
+            for (sample, channel) in frame.iter_mut().zip(&mut self.channels) {
+                let mut wet = 0.0;
+
+                for (comb, filtered) in &mut channel.combs {
+                    wet += comb.push(|output| {
+                        *filtered = output * (1.0 - damping) + *filtered * damping;
+                        input + *filtered * feedback
+                    });
+                }
+
+                for allpass in &mut channel.allpasses {
+                    let input = wet;
+                    wet = allpass.push(|output| input + output * 0.5) - input;
+                }
+
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.sample_rate = 0;
+    }
+}
+
+/// Turns down everything louder than the threshold, with the two channels linked so
+/// the stereo image stays put.
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    pub threshold_db: f32,
+    /// 4.0 turns 4 dB above the threshold into 1 dB, infinity limits.
+    pub ratio: f32,
+    /// How quickly the gain drops once the input gets too loud.
+    pub attack: Duration,
+    /// How quickly the gain recovers once the input gets quieter.
+    pub release: Duration,
+    /// Applied after compressing.
+    pub makeup_gain_db: f32,
+    envelope: f32,
+}
+
+impl Compressor {
+    pub fn new(threshold_db: f32, ratio: f32) -> Self {
+        Self {
+            threshold_db,
+            ratio,
+            attack: Duration::from_millis(10),
+            release: Duration::from_millis(100),
+            makeup_gain_db: 0.0,
+            envelope: 0.0,
+        }
+    }
+
+    /// Instantly turns down peaks so nothing gets louder than the threshold, meant for
+    /// the master bus.
+    pub fn limiter(threshold_db: f32) -> Self {
+        Self {
+            attack: Duration::ZERO,
+            release: Duration::from_millis(50),
+            ..Self::new(threshold_db, f32::INFINITY)
+        }
+    }
+
+    pub fn attack(mut self, attack: Duration) -> Self {
+        self.attack = attack;
+        self
+    }
+
+    pub fn release(mut self, release: Duration) -> Self {
+        self.release = release;
+        self
+    }
+
+    pub fn makeup_gain_db(mut self, gain: f32) -> Self {
+        self.makeup_gain_db = gain;
+        self
+    }
+
+    /// The gain reduction right now, in dB.
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.reduction_db(self.envelope)
+    }
+
+    fn reduction_db(&self, envelope: f32) -> f32 {
+        let over = to_db(envelope) - self.threshold_db;
+        let slope = 1.0 - 1.0 / self.ratio.max(1.0);
+
+        if over > 0.0 {
+            -over * slope
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Effect for Compressor {
+    fn process(&mut self, buffer: &mut [f32], sample_rate: u32) {
+        let coefficient = |time: Duration| {
+            let frames = time.as_secs_f32() * sample_rate as f32;
+            if frames > 0.0 {
+                (-1.0 / frames).exp()
+            } else {
+                0.0
+            }
+        };
+
+        let attack = coefficient(self.attack);
+        let release = coefficient(self.release);
+
+        for frame in buffer.chunks_exact_mut(OUTPUT_CHANNELS) {
+            let peak = frame[0].abs().max(frame[1].abs());
+            let coefficient = if peak > self.envelope { attack } else { release };
+
+            self.envelope = coefficient * self.envelope + (1.0 - coefficient) * peak;
+
+            let gain = from_db(self.reduction_db(self.envelope) + self.makeup_gain_db);
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+fn to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-6).log10()
+}
+
+fn from_db(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
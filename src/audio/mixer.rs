@@ -0,0 +1,628 @@
+use crate::audio::{
+    Attenuation, Effect, Listener, MusicTrack, Sound, Spatialization, StreamSource,
+};
+use glam::Vec2;
+use std::{f32::consts::FRAC_PI_4, time::Duration};
+
+/// The default speed of sound, in meters per second.
+const SPEED_OF_SOUND: f32 = 343.0;
+
+/// The mixer always produces interleaved stereo.
+pub const OUTPUT_CHANNELS: usize = 2;
+
+/// Identifies a bus of a `Mixer`. Every voice plays into a bus, and every bus except
+/// the master bus feeds into a parent bus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BusId(usize);
+
+impl BusId {
+    /// The bus every other bus ends up in, which is written to the output.
+    pub const MASTER: BusId = BusId(0);
+}
+
+/// Identifies a playing voice. Handles stay valid after the voice finished, the
+/// mixer just ignores them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceHandle(u64);
+
+/// Identifies an effect added to a bus with `Mixer::add_effect`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EffectId {
+    bus: BusId,
+    index: usize,
+}
+
+/// How a sound is played, passed to `Mixer::play`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlaybackParams {
+    pub(crate) volume: f32,
+    pub(crate) pitch: f32,
+    pub(crate) pan: f32,
+    pub(crate) looping: bool,
+    pub(crate) bus: BusId,
+    pub(crate) position: Option<Vec2>,
+    pub(crate) velocity: Vec2,
+    pub(crate) attenuation: Attenuation,
+    pub(crate) doppler: f32,
+}
+
+impl Default for PlaybackParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaybackParams {
+    pub fn new() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            looping: false,
+            bus: BusId::MASTER,
+            position: None,
+            velocity: Vec2::ZERO,
+            attenuation: Attenuation::default(),
+            doppler: 0.0,
+        }
+    }
+
+    /// Linear gain, 1.0 plays the sound unchanged.
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Playback speed, 2.0 plays the sound an octave higher and twice as fast.
+    pub fn pitch(mut self, pitch: f32) -> Self {
+        assert!(pitch > 0.0, "expecting a positive pitch");
+        self.pitch = pitch;
+        self
+    }
+
+    /// -1.0 is fully left, 1.0 fully right. Added to the pan of positioned voices.
+    pub fn pan(mut self, pan: f32) -> Self {
+        self.pan = pan.clamp(-1.0, 1.0);
+        self
+    }
+
+    /// Restarts the sound when it ends until the voice is stopped.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn bus(mut self, bus: BusId) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    /// Plays the voice at a world position, panned and attenuated relative to the
+    /// mixer's `Listener`.
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// In world units per second, for Doppler shifts.
+    pub fn velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Scales the Doppler shift of a positioned voice, 0.0 disables it and 1.0 is
+    /// physically accurate given `Mixer::set_speed_of_sound`.
+    pub fn doppler(mut self, doppler: f32) -> Self {
+        self.doppler = doppler.max(0.0);
+        self
+    }
+}
+
+struct Voice {
+    handle: VoiceHandle,
+    source: VoiceSource,
+    params: PlaybackParams,
+    fade: Option<Fade>,
+}
+
+enum VoiceSource {
+    Sound {
+        sound: Sound,
+        /// Position in the sound, in frames of the sound's sample rate.
+        position: f64,
+    },
+    Stream(Box<StreamSource>),
+}
+
+struct Bus {
+    name: String,
+    parent: Option<BusId>,
+    volume: f32,
+    fade: Option<Fade>,
+    effects: Vec<Box<dyn Effect>>,
+    /// Interleaved stereo, reused between `mix` calls.
+    buffer: Vec<f32>,
+}
+
+/// A linear volume ramp, advanced once per frame.
+#[derive(Debug, Copy, Clone)]
+struct Fade {
+    target: f32,
+    step: f32,
+    remaining: u64,
+    /// Stops the voice once the fade is over.
+    stop: bool,
+}
+
+/// Mixes playing voices into buses, and buses into an interleaved stereo f32 buffer.
+///
+/// Voices are resampled to the mixer's sample rate with linear interpolation, which also
+/// takes care of their pitch. Mono voices are panned with equal power panning, so a
+/// centered mono voice plays at -3 dB on each side. Stereo voices are balanced instead.
+pub struct Mixer {
+    sample_rate: u32,
+    buses: Vec<Bus>,
+    voices: Vec<Voice>,
+    next_handle: u64,
+    listener: Listener,
+    speed_of_sound: f32,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        assert!(sample_rate > 0, "expecting a positive sample rate");
+
+        let master = Bus {
+            name: "master".to_string(),
+            parent: None,
+            volume: 1.0,
+            fade: None,
+            effects: Vec::new(),
+            buffer: Vec::new(),
+        };
+
+        Self {
+            sample_rate,
+            buses: vec![master],
+            voices: Vec::new(),
+            next_handle: 0,
+            listener: Listener::default(),
+            speed_of_sound: SPEED_OF_SOUND,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Adds a bus which feeds into `parent`, for example "sfx" and "music" buses with
+    /// separate volume controls.
+    pub fn add_bus<S: Into<String>>(&mut self, name: S, parent: BusId) -> BusId {
+        assert!(parent.0 < self.buses.len(), "unknown parent bus");
+
+        self.buses.push(Bus {
+            name: name.into(),
+            parent: Some(parent),
+            volume: 1.0,
+            fade: None,
+            effects: Vec::new(),
+            buffer: Vec::new(),
+        });
+
+        BusId(self.buses.len() - 1)
+    }
+
+    /// The bus named `name`, the master bus is named "master".
+    pub fn bus(&self, name: &str) -> Option<BusId> {
+        self.buses.iter().position(|bus| bus.name == name).map(BusId)
+    }
+
+    /// The current volume of `bus`, which changes during a fade.
+    pub fn bus_volume(&self, bus: BusId) -> f32 {
+        self.buses[bus.0].volume
+    }
+
+    /// The volume `bus` ends up with once its fade is over.
+    pub fn bus_target_volume(&self, bus: BusId) -> f32 {
+        let bus = &self.buses[bus.0];
+        bus.fade.map_or(bus.volume, |fade| fade.target)
+    }
+
+    /// Linear gain applied to everything playing into `bus`, including its child buses.
+    /// Cancels a fade of the bus.
+    pub fn set_bus_volume(&mut self, bus: BusId, volume: f32) {
+        let bus = &mut self.buses[bus.0];
+        bus.volume = volume;
+        bus.fade = None;
+    }
+
+    /// Ramps the volume of `bus` to `volume` over `duration`, starting with the next mixed
+    /// frame.
+    pub fn fade_bus_volume(&mut self, bus: BusId, volume: f32, duration: Duration) {
+        let sample_rate = self.sample_rate;
+        let bus = &mut self.buses[bus.0];
+        bus.fade = Fade::new(bus.volume, volume, duration, sample_rate, false);
+
+        if bus.fade.is_none() {
+            bus.volume = volume;
+        }
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    /// Positioned voices are heard from `listener`, usually set from the camera every
+    /// tick.
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+    }
+
+    /// In world units per second, 343 by default for worlds measured in meters.
+    pub fn set_speed_of_sound(&mut self, speed_of_sound: f32) {
+        assert!(speed_of_sound > 0.0, "expecting a positive speed of sound");
+        self.speed_of_sound = speed_of_sound;
+    }
+
+    /// Appends `effect` to the effect chain of `bus`. Effects process everything mixed
+    /// into the bus, including its child buses, before the bus volume is applied.
+    pub fn add_effect<E: Effect>(&mut self, bus: BusId, effect: E) -> EffectId {
+        let effects = &mut self.buses[bus.0].effects;
+        effects.push(Box::new(effect));
+
+        EffectId { bus, index: effects.len() - 1 }
+    }
+
+    /// The effect added as `id`, to change its parameters between mixes. `None` if it
+    /// isn't an `E`.
+    pub fn effect_mut<E: Effect>(&mut self, id: EffectId) -> Option<&mut E> {
+        let effect: &mut dyn std::any::Any = self.buses[id.bus.0].effects[id.index].as_mut();
+        effect.downcast_mut()
+    }
+
+    /// Removes all effects from `bus`, which invalidates their `EffectId`s.
+    pub fn clear_effects(&mut self, bus: BusId) {
+        self.buses[bus.0].effects.clear();
+    }
+
+    /// The number of voices playing directly into `bus`, not counting its child buses.
+    pub fn voices_on_bus(&self, bus: BusId) -> usize {
+        self.voices.iter().filter(|voice| voice.params.bus == bus).count()
+    }
+
+    pub fn play(&mut self, sound: &Sound, params: PlaybackParams) -> VoiceHandle {
+        let source = VoiceSource::Sound { sound: sound.clone(), position: 0.0 };
+        self.add_voice(source, params)
+    }
+
+    /// Streams `track` from disk. The file is opened and decoded on a background thread,
+    /// the voice stays silent until the first samples arrive. Looping is up to the track,
+    /// `PlaybackParams::looping` is ignored.
+    pub fn play_stream(&mut self, track: &MusicTrack, params: PlaybackParams) -> VoiceHandle {
+        let source = VoiceSource::Stream(Box::new(track.spawn()));
+        self.add_voice(source, params)
+    }
+
+    pub fn stop(&mut self, handle: VoiceHandle) {
+        self.voices.retain(|voice| voice.handle != handle);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    pub fn is_playing(&self, handle: VoiceHandle) -> bool {
+        self.voice(handle).is_some()
+    }
+
+    /// Whether a stream voice is still waiting for its first decoded samples. Always false
+    /// for sounds and voices which stopped.
+    pub fn is_buffering(&self, handle: VoiceHandle) -> bool {
+        self.voice(handle).is_some_and(|voice| match &voice.source {
+            VoiceSource::Sound { .. } => false,
+            VoiceSource::Stream(stream) => stream.is_buffering(),
+        })
+    }
+
+    /// The number of voices currently playing.
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// The current volume of a voice, which changes during a fade.
+    pub fn volume(&self, handle: VoiceHandle) -> Option<f32> {
+        self.voice(handle).map(|voice| voice.params.volume)
+    }
+
+    /// Cancels a fade of the voice.
+    pub fn set_volume(&mut self, handle: VoiceHandle, volume: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params.volume = volume;
+            voice.fade = None;
+        }
+    }
+
+    /// Ramps the volume of a voice to `volume` over `duration`, starting with the next
+    /// mixed frame.
+    pub fn fade_volume(&mut self, handle: VoiceHandle, volume: f32, duration: Duration) {
+        self.start_fade(handle, volume, duration, false);
+    }
+
+    /// Ramps the volume of a voice down to silence over `duration`, then stops it.
+    pub fn fade_out(&mut self, handle: VoiceHandle, duration: Duration) {
+        self.start_fade(handle, 0.0, duration, true);
+    }
+
+    pub fn set_pitch(&mut self, handle: VoiceHandle, pitch: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params = voice.params.pitch(pitch);
+        }
+    }
+
+    pub fn set_pan(&mut self, handle: VoiceHandle, pan: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params = voice.params.pan(pan);
+        }
+    }
+
+    pub fn set_looping(&mut self, handle: VoiceHandle, looping: bool) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params.looping = looping;
+        }
+    }
+
+    /// Moves a voice, which makes it positioned if it wasn't.
+    pub fn set_position(&mut self, handle: VoiceHandle, position: Vec2) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params.position = Some(position);
+        }
+    }
+
+    pub fn set_velocity(&mut self, handle: VoiceHandle, velocity: Vec2) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.params.velocity = velocity;
+        }
+    }
+
+    /// Mixes the next `output.len() / 2` frames into `output`, replacing its contents.
+    /// Voices which reach the end of their sound or fade out are removed.
+    pub fn mix(&mut self, output: &mut [f32]) {
+        assert_eq!(output.len() % OUTPUT_CHANNELS, 0, "expecting interleaved stereo");
+
+        for bus in &mut self.buses {
+            bus.buffer.clear();
+            bus.buffer.resize(output.len(), 0.0);
+        }
+
+        let sample_rate = self.sample_rate;
+        let buses = &mut self.buses;
+        let spatial = (&self.listener, self.speed_of_sound);
+
+        self.voices.retain_mut(|voice| {
+            voice.mix(sample_rate, spatial, &mut buses[voice.params.bus.0].buffer)
+        });
+
+        // Buses are only ever added after their parents, so walking them backwards mixes
+        // every bus into its parent after all of its children were mixed into it.
+        for index in (1..self.buses.len()).rev() {
+            let parent = self.buses[index].parent.expect("Only the master bus has no parent");
+            let (parents, children) = self.buses.split_at_mut(index);
+            let child = &mut children[0];
+            child.apply_effects(sample_rate);
+
+            let frames = child.buffer.chunks_exact(OUTPUT_CHANNELS);
+
+            for (out, samples) in
+                parents[parent.0].buffer.chunks_exact_mut(OUTPUT_CHANNELS).zip(frames)
+            {
+                let volume = Fade::advance(&mut child.volume, &mut child.fade);
+                out[0] += samples[0] * volume;
+                out[1] += samples[1] * volume;
+            }
+        }
+
+        let master = &mut self.buses[0];
+        master.apply_effects(sample_rate);
+
+        let frames = master.buffer.chunks_exact(OUTPUT_CHANNELS);
+
+        for (out, samples) in output.chunks_exact_mut(OUTPUT_CHANNELS).zip(frames) {
+            let volume = Fade::advance(&mut master.volume, &mut master.fade);
+            out[0] = samples[0] * volume;
+            out[1] = samples[1] * volume;
+        }
+    }
+
+    fn add_voice(&mut self, source: VoiceSource, params: PlaybackParams) -> VoiceHandle {
+        assert!(params.bus.0 < self.buses.len(), "unknown bus");
+
+        let handle = VoiceHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.voices.push(Voice { handle, source, params, fade: None });
+
+        handle
+    }
+
+    fn start_fade(&mut self, handle: VoiceHandle, volume: f32, duration: Duration, stop: bool) {
+        let sample_rate = self.sample_rate;
+
+        if let Some(index) = self.voices.iter().position(|voice| voice.handle == handle) {
+            let voice = &mut self.voices[index];
+            voice.fade = Fade::new(voice.params.volume, volume, duration, sample_rate, stop);
+
+            if voice.fade.is_none() {
+                voice.params.volume = volume;
+
+                if stop {
+                    self.voices.remove(index);
+                }
+            }
+        }
+    }
+
+    fn voice(&self, handle: VoiceHandle) -> Option<&Voice> {
+        self.voices.iter().find(|voice| voice.handle == handle)
+    }
+
+    fn voice_mut(&mut self, handle: VoiceHandle) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|voice| voice.handle == handle)
+    }
+}
+
+impl Bus {
+    fn apply_effects(&mut self, sample_rate: u32) {
+        for effect in &mut self.effects {
+            effect.process(&mut self.buffer, sample_rate);
+        }
+    }
+}
+
+impl Voice {
+    /// Adds the voice to `buffer`. Returns false once the voice finished. Positioned
+    /// voices are spatialized once per call.
+    fn mix(
+        &mut self,
+        sample_rate: u32,
+        (listener, speed_of_sound): (&Listener, f32),
+        buffer: &mut [f32],
+    ) -> bool {
+        let PlaybackParams { mut pitch, mut pan, looping, .. } = self.params;
+        let mut gain = 1.0;
+
+        if let Some(position) = self.params.position {
+            let spatial = Spatialization::new(&self.params, position, listener, speed_of_sound);
+            gain = spatial.gain;
+            pan = (pan + spatial.pan).clamp(-1.0, 1.0);
+            pitch *= spatial.pitch;
+        }
+
+        let (left_gain, right_gain) = self.pan_gains(pan);
+        let (left_gain, right_gain) = (left_gain * gain, right_gain * gain);
+
+        for frame in buffer.chunks_exact_mut(OUTPUT_CHANNELS) {
+            let Some((left, right)) = self.source.next_frame(sample_rate, pitch, looping) else {
+                return false;
+            };
+
+            let stopping = self.fade.is_some_and(|fade| fade.stop && fade.remaining == 1);
+            let volume = Fade::advance(&mut self.params.volume, &mut self.fade);
+
+            frame[0] += left * left_gain * volume;
+            frame[1] += right * right_gain * volume;
+
+            if stopping {
+                return false;
+            }
+        }
+
+        !self.source.is_finished(looping)
+    }
+
+    /// The gains at full volume.
+    fn pan_gains(&self, pan: f32) -> (f32, f32) {
+        if self.source.channels() == 1 {
+            let angle = (pan + 1.0) * FRAC_PI_4;
+            (angle.cos(), angle.sin())
+        } else {
+            ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+        }
+    }
+}
+
+impl VoiceSource {
+    /// The next frame at the output sample rate, `None` once the source ended.
+    fn next_frame(&mut self, sample_rate: u32, pitch: f32, looping: bool) -> Option<(f32, f32)> {
+        let (sound, position) = match self {
+            VoiceSource::Sound { sound, position } => (sound, position),
+            VoiceSource::Stream(stream) => return stream.next_frame(sample_rate, pitch),
+        };
+
+        let frame_count = sound.frame_count();
+
+        if frame_count == 0 {
+            return None;
+        }
+
+        if *position >= frame_count as f64 {
+            if !looping {
+                return None;
+            }
+
+            *position %= frame_count as f64;
+        }
+
+        let index = *position as usize;
+        let fraction = (*position - index as f64) as f32;
+
+        // Interpolate towards the next frame, which is the first one again when looping
+        // and silence otherwise.
+        let next = match index + 1 {
+            next if next < frame_count => sound.frame(next),
+            _ if looping => sound.frame(0),
+            _ => (0.0, 0.0),
+        };
+
+        let current = sound.frame(index);
+        *position += pitch as f64 * sound.sample_rate() as f64 / sample_rate as f64;
+
+        Some((
+            current.0 + (next.0 - current.0) * fraction,
+            current.1 + (next.1 - current.1) * fraction,
+        ))
+    }
+
+    /// Whether the source has nothing left to play, known in advance for sounds.
+    fn is_finished(&self, looping: bool) -> bool {
+        match self {
+            VoiceSource::Sound { sound, position } => {
+                !looping && *position >= sound.frame_count() as f64
+            },
+            VoiceSource::Stream(stream) => stream.is_finished(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            VoiceSource::Sound { sound, .. } => sound.channels(),
+            VoiceSource::Stream(_) => 2,
+        }
+    }
+}
+
+impl Fade {
+    /// A fade from `from` to `to`, `None` if it's shorter than a frame.
+    fn new(from: f32, to: f32, duration: Duration, sample_rate: u32, stop: bool) -> Option<Self> {
+        let frames = (duration.as_secs_f64() * sample_rate as f64).round() as u64;
+
+        (frames > 0).then(|| Self {
+            target: to,
+            step: (to - from) / frames as f32,
+            remaining: frames,
+            stop,
+        })
+    }
+
+    /// Returns the volume for the current frame and moves `volume` one frame along `fade`.
+    fn advance(volume: &mut f32, fade: &mut Option<Fade>) -> f32 {
+        let current = *volume;
+
+        if let Some(active) = fade {
+            active.remaining -= 1;
+
+            if active.remaining == 0 {
+                *volume = active.target;
+                *fade = None;
+            } else {
+                *volume += active.step;
+            }
+        }
+
+        current
+    }
+}
@@ -0,0 +1,95 @@
+use crate::Error;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+mod backend;
+#[cfg(feature = "cpal")]
+mod cpal_backend;
+mod effects;
+mod mixer;
+mod music;
+mod sound;
+mod spatial;
+mod stream;
+mod synth;
+
+pub use backend::*;
+#[cfg(feature = "cpal")]
+pub use cpal_backend::*;
+pub use effects::*;
+pub use mixer::*;
+pub use music::*;
+pub use sound::*;
+pub(crate) use spatial::Spatialization;
+pub use spatial::{Attenuation, Listener};
+pub use stream::MusicTrack;
+pub(crate) use stream::StreamSource;
+pub use synth::*;
+
+/// The mixer shared between the game and a backend's audio thread.
+pub type SharedMixer = Arc<Mutex<Mixer>>;
+
+/// A `Mixer` playing through an `AudioBackend`.
+///
+/// ```
+/// # use simple_game::audio::{Audio, NullBackend, PlaybackParams, Sound};
+/// let mut audio = Audio::new(NullBackend::new(48_000)).unwrap();
+/// let music = audio.mixer().add_bus("music", simple_game::audio::BusId::MASTER);
+///
+/// let sound = Sound::from_samples(48_000, 1, vec![0.5; 4800]);
+/// let voice = audio.play(&sound, PlaybackParams::new().bus(music).looping(true));
+///
+/// audio.render(1024).unwrap();
+/// assert!(audio.mixer().is_playing(voice));
+/// ```
+pub struct Audio {
+    mixer: SharedMixer,
+    backend: Box<dyn AudioBackend>,
+    buffer: Vec<f32>,
+}
+
+impl Audio {
+    pub fn new<B: AudioBackend + 'static>(mut backend: B) -> Result<Self, Error> {
+        let mixer = Arc::new(Mutex::new(Mixer::new(backend.sample_rate())));
+        backend.start(mixer.clone())?;
+
+        Ok(Self { mixer, backend: Box::new(backend), buffer: Vec::new() })
+    }
+
+    /// Plays audio on the default output device.
+    #[cfg(feature = "cpal")]
+    pub fn with_default_device() -> Result<Self, Error> {
+        Self::new(CpalBackend::new()?)
+    }
+
+    /// Locks the mixer. Real-time backends can't mix while it's locked, so don't hold on
+    /// to the guard for long.
+    pub fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().unwrap()
+    }
+
+    pub fn shared_mixer(&self) -> &SharedMixer {
+        &self.mixer
+    }
+
+    pub fn play(&self, sound: &Sound, params: PlaybackParams) -> VoiceHandle {
+        self.mixer().play(sound, params)
+    }
+
+    pub fn stop(&self, voice: VoiceHandle) {
+        self.mixer().stop(voice)
+    }
+
+    /// Mixes `frames` frames and hands them to the backend. Only meant for offline
+    /// backends like `NullBackend` and `WavFileBackend`, real-time backends mix on
+    /// their own.
+    pub fn render(&mut self, frames: usize) -> Result<(), Error> {
+        self.buffer.resize(frames * OUTPUT_CHANNELS, 0.0);
+        self.mixer.lock().unwrap().mix(&mut self.buffer);
+        self.backend.write(&self.buffer)
+    }
+
+    /// Flushes the backend, for example to finish writing a WAV file.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        self.backend.finish()
+    }
+}
@@ -0,0 +1,153 @@
+use crate::audio::{BusId, Mixer, MusicTrack, PlaybackParams, VoiceHandle};
+use std::time::Duration;
+
+/// Plays one streamed `MusicTrack` at a time on a music bus, crossfading between tracks,
+/// and ducks the bus while dialogue plays.
+///
+/// Nothing here blocks, so it's meant to be driven from `GameApp::tick`:
+///
+/// ```
+/// # use simple_game::audio::{Audio, BusId, MusicPlayer, MusicTrack, NullBackend};
+/// # use std::time::Duration;
+/// let audio = Audio::new(NullBackend::new(48_000)).unwrap();
+/// let music_bus = audio.mixer().add_bus("music", BusId::MASTER);
+/// let dialogue_bus = audio.mixer().add_bus("dialogue", BusId::MASTER);
+///
+/// let mut music = MusicPlayer::new(music_bus).ducking(dialogue_bus, 0.3, Duration::from_millis(250));
+/// music.play(&mut audio.mixer(), &MusicTrack::new("music/level1.ogg"), Duration::from_secs(2));
+///
+/// // Every tick.
+/// music.update(&mut audio.mixer());
+/// ```
+pub struct MusicPlayer {
+    bus: BusId,
+    current: Option<VoiceHandle>,
+    /// The crossfade to the current track, until its stream has samples to fade in.
+    crossfade: Option<Crossfade>,
+    ducking: Option<Ducking>,
+}
+
+struct Crossfade {
+    previous: Option<VoiceHandle>,
+    duration: Duration,
+}
+
+struct Ducking {
+    dialogue_bus: BusId,
+    volume: f32,
+    fade: Duration,
+    /// The music bus volume from before ducking it, while it is ducked.
+    restore_volume: Option<f32>,
+}
+
+impl MusicPlayer {
+    pub fn new(bus: BusId) -> Self {
+        Self { bus, current: None, crossfade: None, ducking: None }
+    }
+
+    /// Fades the music bus to `volume` times its volume while voices play on
+    /// `dialogue_bus`, and back once they're done. Changes to the music bus volume while
+    /// it's ducked are overwritten when it's restored.
+    pub fn ducking(mut self, dialogue_bus: BusId, volume: f32, fade: Duration) -> Self {
+        self.ducking = Some(Ducking { dialogue_bus, volume, fade, restore_volume: None });
+        self
+    }
+
+    pub fn bus(&self) -> BusId {
+        self.bus
+    }
+
+    /// The voice playing the current track, if any.
+    pub fn current(&self) -> Option<VoiceHandle> {
+        self.current
+    }
+
+    pub fn is_ducked(&self) -> bool {
+        self.ducking.as_ref().is_some_and(|ducking| ducking.restore_volume.is_some())
+    }
+
+    /// Starts streaming `track`, fading it in while the previous track fades out over
+    /// `crossfade`. The crossfade starts in `update` once the stream decoded its first
+    /// samples, the previous track keeps playing until then. A zero crossfade switches
+    /// tracks immediately.
+    pub fn play(
+        &mut self,
+        mixer: &mut Mixer,
+        track: &MusicTrack,
+        crossfade: Duration,
+    ) -> VoiceHandle {
+        let voice = mixer.play_stream(track, PlaybackParams::new().bus(self.bus).volume(0.0));
+
+        let previous = match (self.current.replace(voice), self.crossfade.take()) {
+            // The track which never faded in is dropped, the one before it still fades out.
+            (Some(silent), Some(crossfade)) => {
+                mixer.stop(silent);
+                crossfade.previous
+            },
+            (previous, _) => previous,
+        };
+
+        self.crossfade = Some(Crossfade { previous, duration: crossfade });
+        self.start_crossfade(mixer);
+
+        voice
+    }
+
+    /// Fades out the current track over `fade`.
+    pub fn stop(&mut self, mixer: &mut Mixer, fade: Duration) {
+        let previous = self.crossfade.take().and_then(|crossfade| crossfade.previous);
+
+        for voice in self.current.take().into_iter().chain(previous) {
+            mixer.fade_out(voice, fade);
+        }
+    }
+
+    /// Starts the pending crossfade once the current track has samples, and ducks or
+    /// restores the music bus depending on whether dialogue is playing.
+    pub fn update(&mut self, mixer: &mut Mixer) {
+        self.start_crossfade(mixer);
+
+        if self.current.is_some_and(|current| !mixer.is_playing(current)) {
+            self.current = None;
+        }
+
+        let Some(ducking) = &mut self.ducking else {
+            return;
+        };
+
+        let dialogue_playing = mixer.voices_on_bus(ducking.dialogue_bus) > 0;
+
+        match ducking.restore_volume {
+            None if dialogue_playing => {
+                let volume = mixer.bus_target_volume(self.bus);
+                ducking.restore_volume = Some(volume);
+                mixer.fade_bus_volume(self.bus, volume * ducking.volume, ducking.fade);
+            },
+            Some(volume) if !dialogue_playing => {
+                ducking.restore_volume = None;
+                mixer.fade_bus_volume(self.bus, volume, ducking.fade);
+            },
+            _ => {},
+        }
+    }
+
+    fn start_crossfade(&mut self, mixer: &mut Mixer) {
+        let (Some(current), Some(crossfade)) = (self.current, &self.crossfade) else {
+            return;
+        };
+
+        // Fading in a stream which is still opening its file would spend the start of the
+        // fade on silence.
+        if !crossfade.duration.is_zero() && mixer.is_buffering(current) {
+            return;
+        }
+
+        mixer.fade_volume(current, 1.0, crossfade.duration);
+
+        if let Some(previous) = crossfade.previous {
+            mixer.fade_out(previous, crossfade.duration);
+        }
+
+        self.crossfade = None;
+    }
+}
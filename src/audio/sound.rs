@@ -0,0 +1,149 @@
+use crate::Error;
+use std::{io::Cursor, path::Path, sync::Arc, time::Duration};
+
+struct SoundData {
+    sample_rate: u32,
+    channels: u16,
+    /// Interleaved samples in the range [-1.0, 1.0].
+    samples: Vec<f32>,
+}
+
+/// A decoded mono or stereo sound, ready to be played by the `Mixer`.
+/// Cloning is cheap, the samples are shared.
+#[derive(Clone)]
+pub struct Sound {
+    data: Arc<SoundData>,
+}
+
+impl std::fmt::Debug for Sound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sound")
+            .field("sample_rate", &self.data.sample_rate)
+            .field("channels", &self.data.channels)
+            .field("frames", &self.frame_count())
+            .finish()
+    }
+}
+
+/// Rejects headers `Sound::from_samples` would panic on, before decoding.
+pub(crate) fn check_header(sample_rate: u32, channels: u16) -> Result<(), Error> {
+    if sample_rate == 0 || channels == 0 {
+        return Err(Error::InvalidAudioHeader { sample_rate, channels });
+    }
+
+    Ok(())
+}
+
+impl Sound {
+    /// A sound from interleaved samples in the range [-1.0, 1.0].
+    pub fn from_samples(sample_rate: u32, channels: u16, samples: Vec<f32>) -> Self {
+        assert!(sample_rate > 0, "expecting a positive sample rate");
+        assert!(channels == 1 || channels == 2, "expecting a mono or stereo sound");
+        assert_eq!(samples.len() % channels as usize, 0, "expecting whole frames");
+
+        Self { data: Arc::new(SoundData { sample_rate, channels, samples }) }
+    }
+
+    /// Loads a WAV or Ogg Vorbis file, detected by its contents.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Decodes a WAV or Ogg Vorbis file, detected by its contents.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes.get(..4) {
+            Some(b"RIFF") => Self::from_wav_bytes(bytes),
+            Some(b"OggS") => Self::from_ogg_bytes(bytes),
+            _ => Err(Error::UnsupportedAudioFormat),
+        }
+    }
+
+    /// Decodes an integer or float PCM WAV file.
+    pub fn from_wav_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = hound::WavReader::new(Cursor::new(bytes))?;
+        let spec = reader.spec();
+        check_header(spec.sample_rate, spec.channels)?;
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            },
+        };
+
+        Ok(Self::from_interleaved(spec.sample_rate, spec.channels, samples))
+    }
+
+    /// Decodes an Ogg Vorbis file.
+    pub fn from_ogg_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))?;
+        let sample_rate = reader.ident_hdr.audio_sample_rate;
+        let channels = reader.ident_hdr.audio_channels as u16;
+        check_header(sample_rate, channels)?;
+
+        let mut samples = Vec::new();
+
+        while let Some(packet) = reader.read_dec_packet_itl()? {
+            samples.extend(packet.into_iter().map(|sample: i16| sample as f32 / 32768.0));
+        }
+
+        Ok(Self::from_interleaved(sample_rate, channels, samples))
+    }
+
+    /// Keeps the first two channels of files with more than two, which are the front left
+    /// and right channels in both WAV and Vorbis.
+    fn from_interleaved(sample_rate: u32, channels: u16, mut samples: Vec<f32>) -> Self {
+        // Files cut off in the middle of a frame lose that frame.
+        samples.truncate(samples.len() - samples.len() % channels as usize);
+
+        if channels <= 2 {
+            return Self::from_samples(sample_rate, channels, samples);
+        }
+
+        let samples = samples
+            .chunks_exact(channels as usize)
+            .flat_map(|frame| [frame[0], frame[1]])
+            .collect();
+
+        Self::from_samples(sample_rate, 2, samples)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.data.sample_rate
+    }
+
+    /// 1 for mono, 2 for stereo.
+    pub fn channels(&self) -> u16 {
+        self.data.channels
+    }
+
+    /// Interleaved samples in the range [-1.0, 1.0].
+    pub fn samples(&self) -> &[f32] {
+        &self.data.samples
+    }
+
+    /// The number of samples per channel.
+    pub fn frame_count(&self) -> usize {
+        self.data.samples.len() / self.data.channels as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frame_count() as f64 / self.data.sample_rate as f64)
+    }
+
+    /// The left and right samples of a frame. Mono sounds return the same sample twice.
+    pub(crate) fn frame(&self, index: usize) -> (f32, f32) {
+        match self.data.channels {
+            1 => {
+                let sample = self.data.samples[index];
+                (sample, sample)
+            },
+            _ => (self.data.samples[index * 2], self.data.samples[index * 2 + 1]),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::audio::PlaybackParams;
+use glam::{Mat4, Vec2, Vec3};
+
+/// Doppler shifts are clamped to an octave either way, sources moving at the speed of
+/// sound would stop or go infinitely high.
+const MAX_DOPPLER_SHIFT: f32 = 2.0;
+
+/// Where positioned voices are heard from, see `Mixer::set_listener`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Listener {
+    pub position: Vec2,
+    /// Unit vector pointing to the listener's right.
+    pub right: Vec2,
+    /// Voices this far to the side are panned all the way, in world units.
+    pub pan_width: f32,
+    /// In world units per second, for Doppler shifts.
+    pub velocity: Vec2,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self::new(Vec2::ZERO, 10.0)
+    }
+}
+
+impl Listener {
+    pub fn new(position: Vec2, pan_width: f32) -> Self {
+        Self { position, right: Vec2::X, pan_width, velocity: Vec2::ZERO }
+    }
+
+    /// A listener at the center of the view of `camera_matrix`, the same matrix passed to
+    /// `LineRecorder::end`. Voices at the left and right edges of the view are panned
+    /// all the way.
+    pub fn from_camera_matrix(camera_matrix: Mat4) -> Self {
+        let inverse = camera_matrix.inverse();
+        let center = inverse.project_point3(Vec3::ZERO).truncate();
+        let right = inverse.project_point3(Vec3::X).truncate() - center;
+
+        Self {
+            position: center,
+            right: right.normalize_or(Vec2::X),
+            pan_width: right.length(),
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    pub fn velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+}
+
+/// How positioned voices get quieter with their distance to the listener.
+#[derive(Debug, Copy, Clone)]
+pub enum Attenuation {
+    /// Full volume up to `min_distance`, fading linearly to silence at `max_distance`.
+    Linear { min_distance: f32, max_distance: f32 },
+    /// Full volume up to `min_distance`, then `min_distance / distance` for a rolloff of
+    /// 1.0. Larger rolloffs get quieter sooner.
+    Inverse { min_distance: f32, rolloff: f32 },
+    /// Maps the distance to a gain.
+    Custom(fn(f32) -> f32),
+}
+
+impl PartialEq for Attenuation {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Attenuation::Linear { min_distance, max_distance },
+                Attenuation::Linear { min_distance: other_min, max_distance: other_max },
+            ) => (min_distance, max_distance) == (other_min, other_max),
+            (
+                Attenuation::Inverse { min_distance, rolloff },
+                Attenuation::Inverse { min_distance: other_min, rolloff: other_rolloff },
+            ) => (min_distance, rolloff) == (other_min, other_rolloff),
+            (Attenuation::Custom(curve), Attenuation::Custom(other)) => {
+                std::ptr::fn_addr_eq(*curve, *other)
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::Inverse { min_distance: 1.0, rolloff: 1.0 }
+    }
+}
+
+impl Attenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        match *self {
+            Attenuation::Linear { min_distance, max_distance } => {
+                let range = (max_distance - min_distance).max(f32::EPSILON);
+                (1.0 - (distance - min_distance) / range).clamp(0.0, 1.0)
+            },
+            Attenuation::Inverse { min_distance, rolloff } => {
+                let beyond = (distance - min_distance).max(0.0);
+                min_distance / (min_distance + rolloff * beyond).max(f32::EPSILON)
+            },
+            Attenuation::Custom(curve) => curve(distance).max(0.0),
+        }
+    }
+}
+
+/// The gain, pan and pitch factor of a positioned voice, heard by `listener`.
+pub(crate) struct Spatialization {
+    pub(crate) gain: f32,
+    pub(crate) pan: f32,
+    pub(crate) pitch: f32,
+}
+
+impl Spatialization {
+    pub(crate) fn new(
+        params: &PlaybackParams,
+        position: Vec2,
+        listener: &Listener,
+        speed_of_sound: f32,
+    ) -> Self {
+        let offset = position - listener.position;
+        let distance = offset.length();
+        let direction = offset.normalize_or_zero();
+
+        let pan_width = listener.pan_width.max(f32::EPSILON);
+        let pan = (offset.dot(listener.right) / pan_width).clamp(-1.0, 1.0);
+
+        // Positive speeds close the distance between the two.
+        let listener_speed = listener.velocity.dot(direction) * params.doppler;
+        let source_speed = -params.velocity.dot(direction) * params.doppler;
+        let pitch = ((speed_of_sound + listener_speed)
+            / (speed_of_sound - source_speed).max(f32::EPSILON))
+        .clamp(1.0 / MAX_DOPPLER_SHIFT, MAX_DOPPLER_SHIFT);
+
+        Self { gain: params.attenuation.gain(distance), pan, pitch }
+    }
+}
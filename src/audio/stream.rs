@@ -0,0 +1,408 @@
+use crate::{audio::check_header, Error};
+use lewton::{audio::AudioReadError, VorbisError};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError},
+};
+
+/// Frames decoded at once by a streaming decoder. Vorbis packets are much shorter, so
+/// they're gathered until there are at least this many.
+const CHUNK_FRAMES: usize = 4096;
+
+/// Decoded chunks buffered ahead of the mixer, about 0.7 seconds at 48 kHz for both
+/// formats.
+const BUFFERED_CHUNKS: usize = 8;
+
+/// A music file streamed from disk while it plays, with optional loop points.
+///
+/// Streams are decoded on a background thread, starting with opening the file, so
+/// playing one never blocks the calling thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MusicTrack {
+    path: PathBuf,
+    looping: bool,
+    loop_start: u64,
+    loop_end: Option<u64>,
+}
+
+impl MusicTrack {
+    /// A WAV or Ogg Vorbis file, played once.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into(), looping: false, loop_start: 0, loop_end: None }
+    }
+
+    /// Plays the whole file over and over.
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    /// Plays from the start of the file up to `end`, then jumps back to `start` forever.
+    /// Everything before `start` is an intro which only plays once. Both are in frames at
+    /// the file's sample rate, `None` is the end of the file.
+    pub fn loop_points(mut self, start: u64, end: Option<u64>) -> Self {
+        assert!(end.is_none_or(|end| end > start), "expecting the loop to end after it starts");
+
+        self.looping = true;
+        self.loop_start = start;
+        self.loop_end = end;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn spawn(&self) -> StreamSource {
+        let (sender, receiver) = sync_channel(BUFFERED_CHUNKS);
+        let track = self.clone();
+
+        std::thread::spawn(move || {
+            if let Err(err) = track.decode(&sender) {
+                let _ = sender.send(StreamMessage::Error(err.to_string()));
+            }
+        });
+
+        StreamSource {
+            path: self.path.clone(),
+            receiver,
+            sample_rate: None,
+            frames: VecDeque::new(),
+            position: 0.0,
+            primed: false,
+            ended: false,
+        }
+    }
+
+    /// Decodes the track into `sender` until it ends or the voice playing it is gone.
+    fn decode(&self, sender: &SyncSender<StreamMessage>) -> Result<(), Error> {
+        let mut decoder = open_decoder(&self.path)?;
+
+        if sender.send(StreamMessage::Format { sample_rate: decoder.sample_rate() }).is_err() {
+            return Ok(());
+        }
+
+        let mut position = 0;
+        let mut frames_since_loop = 0;
+
+        loop {
+            let Some(mut samples) = decoder.next_chunk()? else {
+                // An empty loop would spin forever.
+                if !self.looping || frames_since_loop == 0 {
+                    break;
+                }
+
+                decoder.seek(self.loop_start)?;
+                position = self.loop_start;
+                frames_since_loop = 0;
+                continue;
+            };
+
+            let mut frames = (samples.len() / 2) as u64;
+            let loop_end = self.loop_end.filter(|_| self.looping);
+            let reached_loop_end = loop_end.is_some_and(|end| position + frames >= end);
+
+            if let Some(end) = loop_end.filter(|_| reached_loop_end) {
+                frames = end - position;
+                samples.truncate(frames as usize * 2);
+            }
+
+            position += frames;
+            frames_since_loop += frames;
+
+            if !samples.is_empty() && sender.send(StreamMessage::Samples(samples)).is_err() {
+                return Ok(());
+            }
+
+            if reached_loop_end {
+                decoder.seek(self.loop_start)?;
+                position = self.loop_start;
+                frames_since_loop = 0;
+            }
+        }
+
+        let _ = sender.send(StreamMessage::End);
+        Ok(())
+    }
+}
+
+enum StreamMessage {
+    Format {
+        sample_rate: u32,
+    },
+    /// Interleaved stereo.
+    Samples(Vec<f32>),
+    End,
+    Error(String),
+}
+
+/// The mixer's end of a stream, which plays whatever was decoded so far.
+pub(crate) struct StreamSource {
+    path: PathBuf,
+    receiver: Receiver<StreamMessage>,
+    /// Known once the decoder opened the file.
+    sample_rate: Option<u32>,
+    frames: VecDeque<(f32, f32)>,
+    /// Position between the first two buffered frames.
+    position: f64,
+    /// Set once the first samples arrived.
+    primed: bool,
+    ended: bool,
+}
+
+impl StreamSource {
+    /// The next frame, interpolated between decoded frames and advanced by `pitch`.
+    /// Silent while the decoder falls behind, `None` once the stream ended.
+    pub(crate) fn next_frame(&mut self, output_rate: u32, pitch: f32) -> Option<(f32, f32)> {
+        while self.frames.len() < 2 && !self.ended {
+            match self.receiver.try_recv() {
+                Ok(StreamMessage::Format { sample_rate }) => self.sample_rate = Some(sample_rate),
+                Ok(StreamMessage::Samples(samples)) => {
+                    self.primed = true;
+                    self.frames.extend(samples.chunks_exact(2).map(|frame| (frame[0], frame[1])));
+                },
+                Ok(StreamMessage::End) | Err(TryRecvError::Disconnected) => self.ended = true,
+                Ok(StreamMessage::Error(err)) => {
+                    println!("Failed to stream {}: {}", self.path.display(), err);
+                    self.ended = true;
+                },
+                Err(TryRecvError::Empty) => return Some((0.0, 0.0)),
+            }
+        }
+
+        let (Some(sample_rate), Some(&current)) = (self.sample_rate, self.frames.front()) else {
+            return None;
+        };
+
+        let next = self.frames.get(1).copied().unwrap_or((0.0, 0.0));
+        let fraction = self.position as f32;
+
+        self.position += pitch as f64 * sample_rate as f64 / output_rate as f64;
+
+        let consumed = (self.position as usize).min(self.frames.len());
+        self.frames.drain(..consumed);
+        self.position -= consumed as f64;
+
+        Some((
+            current.0 + (next.0 - current.0) * fraction,
+            current.1 + (next.1 - current.1) * fraction,
+        ))
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.ended && self.frames.is_empty()
+    }
+
+    /// Whether the stream is still waiting for its first samples.
+    pub(crate) fn is_buffering(&self) -> bool {
+        !self.primed && !self.ended
+    }
+}
+
+/// Decodes a file a chunk at a time.
+trait StreamDecoder {
+    fn sample_rate(&self) -> u32;
+
+    /// The next interleaved stereo samples, `None` at the end of the file.
+    fn next_chunk(&mut self) -> Result<Option<Vec<f32>>, Error>;
+
+    /// Continues decoding from `frame`.
+    fn seek(&mut self, frame: u64) -> Result<(), Error>;
+}
+
+fn open_decoder(path: &Path) -> Result<Box<dyn StreamDecoder>, Error> {
+    let mut magic = [0; 4];
+    std::io::Read::read_exact(&mut File::open(path)?, &mut magic)?;
+
+    match &magic {
+        b"RIFF" => Ok(Box::new(WavStream::open(path)?)),
+        b"OggS" => Ok(Box::new(OggStream::open(path)?)),
+        _ => Err(Error::UnsupportedAudioFormat),
+    }
+}
+
+/// Interleaves the first two channels of `samples` as stereo, duplicating mono.
+fn to_stereo(samples: &[f32], channels: u16) -> Vec<f32> {
+    match channels {
+        1 => samples.iter().flat_map(|&sample| [sample, sample]).collect(),
+        2 => samples.to_vec(),
+        _ => {
+            samples.chunks_exact(channels as usize).flat_map(|frame| [frame[0], frame[1]]).collect()
+        },
+    }
+}
+
+struct WavStream {
+    reader: hound::WavReader<BufReader<File>>,
+}
+
+impl WavStream {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let reader = hound::WavReader::open(path)?;
+        check_header(reader.spec().sample_rate, reader.spec().channels)?;
+
+        Ok(Self { reader })
+    }
+}
+
+impl StreamDecoder for WavStream {
+    fn sample_rate(&self) -> u32 {
+        self.reader.spec().sample_rate
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<Vec<f32>>, Error> {
+        let spec = self.reader.spec();
+        let count = CHUNK_FRAMES * spec.channels as usize;
+
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => {
+                self.reader.samples::<f32>().take(count).collect::<Result<_, _>>()?
+            },
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+
+                self.reader
+                    .samples::<i32>()
+                    .take(count)
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            },
+        };
+
+        Ok((!samples.is_empty()).then(|| to_stereo(&samples, spec.channels)))
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Error> {
+        Ok(self.reader.seek(frame as u32)?)
+    }
+}
+
+struct OggStream {
+    path: PathBuf,
+    reader: lewton::inside_ogg::OggStreamReader<BufReader<File>>,
+    /// Decoded samples left over from seeking into the middle of a packet, and the
+    /// packets after it which were decoded to find out where the seek landed.
+    pending: Option<Vec<f32>>,
+}
+
+impl OggStream {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let reader = lewton::inside_ogg::OggStreamReader::new(BufReader::new(File::open(path)?))?;
+        check_header(reader.ident_hdr.audio_sample_rate, reader.ident_hdr.audio_channels as u16)?;
+
+        Ok(Self { path: path.to_path_buf(), reader, pending: None })
+    }
+
+    fn decode_packet(&mut self) -> Result<Option<Vec<f32>>, Error> {
+        let channels = self.reader.ident_hdr.audio_channels as u16;
+
+        while let Some(packet) = self.reader.read_dec_packet_itl()? {
+            // Vorbis packets can be empty, mostly the first one.
+            if !packet.is_empty() {
+                let samples: Vec<f32> =
+                    packet.into_iter().map(|sample: i16| sample as f32 / 32768.0).collect();
+                return Ok(Some(to_stereo(&samples, channels)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Decodes from `position`, starting with the `buffered` chunks, until `frame` and
+    /// keeps the rest for `next_chunk`.
+    fn skip_to(
+        &mut self,
+        frame: u64,
+        mut position: u64,
+        buffered: Vec<Vec<f32>>,
+    ) -> Result<(), Error> {
+        let mut buffered = buffered.into_iter();
+
+        loop {
+            let samples = match buffered.next() {
+                Some(samples) => samples,
+                None => match self.decode_packet()? {
+                    Some(samples) => samples,
+                    None => return Ok(()),
+                },
+            };
+
+            let frames = (samples.len() / 2) as u64;
+
+            if position + frames > frame {
+                let skip = (frame - position) as usize * 2;
+                let mut pending = samples[skip..].to_vec();
+                pending.extend(buffered.flatten());
+                self.pending = Some(pending);
+                return Ok(());
+            }
+
+            position += frames;
+        }
+    }
+}
+
+impl StreamDecoder for OggStream {
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<Vec<f32>>, Error> {
+        let mut chunk = self.pending.take().unwrap_or_default();
+
+        while chunk.len() < CHUNK_FRAMES * 2 {
+            match self.decode_packet()? {
+                Some(samples) => chunk.extend(samples),
+                None => break,
+            }
+        }
+
+        Ok((!chunk.is_empty()).then_some(chunk))
+    }
+
+    /// Vorbis can only seek to pages, and only knows where the last packet of each page
+    /// ends. The first packet after seeking only primes the decoder, so this seeks a long
+    /// block early, decodes until a page end tells where the packets are, and skips frames
+    /// to land on the exact frame.
+    fn seek(&mut self, frame: u64) -> Result<(), Error> {
+        let margin = 1 << self.reader.ident_hdr.blocksize_1;
+        self.reader.seek_absgp_pg(frame.saturating_sub(margin))?;
+        self.pending = None;
+
+        let mut buffered = Vec::new();
+
+        let end = loop {
+            let samples = match self.decode_packet() {
+                // Seeking close to the start can land on the header pages.
+                Err(Error::VorbisError(VorbisError::BadAudio(AudioReadError::AudioIsHeader))) => {
+                    continue
+                },
+                result => result?,
+            };
+
+            let Some(samples) = samples else {
+                return Ok(());
+            };
+
+            buffered.push(samples);
+
+            if let Some(end) = self.reader.get_last_absgp() {
+                break end;
+            }
+        };
+
+        let buffered_frames = buffered.iter().map(|samples| samples.len() as u64 / 2).sum();
+
+        match end.checked_sub(buffered_frames).filter(|&start| start <= frame) {
+            Some(start) => self.skip_to(frame, start, buffered),
+            None => {
+                // The seek overshot, which only happens with unusual page layouts.
+                *self = Self::open(&self.path)?;
+                self.skip_to(frame, 0, Vec::new())
+            },
+        }
+    }
+}
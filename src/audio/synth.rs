@@ -0,0 +1,470 @@
+use crate::{audio::Sound, Error};
+use std::{
+    f32::consts::{PI, TAU},
+    fmt,
+    path::Path,
+    str::FromStr,
+};
+
+/// Noise values generated per oscillator period, which gives noise a pitch.
+const NOISE_STEPS: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sawtooth,
+    Triangle,
+    Sine,
+    Noise,
+}
+
+impl Waveform {
+    const ALL: [Waveform; 5] =
+        [Waveform::Square, Waveform::Sawtooth, Waveform::Triangle, Waveform::Sine, Waveform::Noise];
+
+    fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Sawtooth => "sawtooth",
+            Waveform::Triangle => "triangle",
+            Waveform::Sine => "sine",
+            Waveform::Noise => "noise",
+        }
+    }
+}
+
+/// Starting points for `SynthParams::preset`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SfxPreset {
+    Coin,
+    Laser,
+    Explosion,
+    PowerUp,
+    Hit,
+    Jump,
+    Blip,
+}
+
+/// Parameters of a procedurally generated sound effect, in the spirit of sfxr.
+///
+/// An oscillator plays `frequency`, sliding and wobbling over time, shaped by an
+/// attack, sustain and decay envelope and then filtered. Times are in seconds and
+/// frequencies in Hz, so a sound renders the same at any sample rate.
+///
+/// Parameters save as plain text with one `name = value` line per field. Missing fields
+/// keep their defaults:
+///
+/// ```text
+/// waveform = square
+/// frequency = 980
+/// arpeggio = 1.5
+/// arpeggio_time = 0.06
+/// sustain = 0.05
+/// sustain_punch = 0.45
+/// decay = 0.25
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthParams {
+    pub waveform: Waveform,
+    /// The oscillator frequency at the start.
+    pub frequency: f32,
+    /// The sound ends early once the frequency slides below this.
+    pub min_frequency: f32,
+    /// Octaves per second, negative slides down.
+    pub frequency_slide: f32,
+    /// Change of `frequency_slide` per second.
+    pub frequency_slide_delta: f32,
+    /// Multiplies the frequency once `arpeggio_time` has passed, for jumps in pitch.
+    pub arpeggio: f32,
+    /// 0.0 never changes the frequency.
+    pub arpeggio_time: f32,
+    /// Depth of the vibrato as a fraction of the frequency.
+    pub vibrato_depth: f32,
+    pub vibrato_speed: f32,
+    /// The fraction of a period a square wave is high.
+    pub duty: f32,
+    /// Change of `duty` per second.
+    pub duty_sweep: f32,
+    pub attack: f32,
+    pub sustain: f32,
+    /// Extra volume at the start of the sustain, fading out over it.
+    pub sustain_punch: f32,
+    pub decay: f32,
+    /// 0.0 disables the low pass filter.
+    pub low_pass_cutoff: f32,
+    /// From 0.0 to 1.0, boosts frequencies around the cutoff.
+    pub low_pass_resonance: f32,
+    /// 0.0 disables the high pass filter.
+    pub high_pass_cutoff: f32,
+    pub volume: f32,
+}
+
+impl Default for SynthParams {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            min_frequency: 0.0,
+            frequency_slide: 0.0,
+            frequency_slide_delta: 0.0,
+            arpeggio: 1.0,
+            arpeggio_time: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            duty: 0.5,
+            duty_sweep: 0.0,
+            attack: 0.0,
+            sustain: 0.1,
+            sustain_punch: 0.0,
+            decay: 0.2,
+            low_pass_cutoff: 0.0,
+            low_pass_resonance: 0.0,
+            high_pass_cutoff: 0.0,
+            volume: 0.5,
+        }
+    }
+}
+
+impl SynthParams {
+    /// A random variation of `preset`, the same `seed` always gives the same sound.
+    pub fn preset(preset: SfxPreset, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut params = Self::default();
+
+        match preset {
+            SfxPreset::Coin => {
+                params.waveform = rng.pick(&[Waveform::Square, Waveform::Sawtooth]);
+                params.frequency = rng.range(800.0, 1600.0);
+                params.arpeggio = rng.range(1.3, 1.7);
+                params.arpeggio_time = rng.range(0.04, 0.1);
+                params.sustain = rng.range(0.02, 0.1);
+                params.sustain_punch = rng.range(0.3, 0.6);
+                params.decay = rng.range(0.1, 0.3);
+            },
+            SfxPreset::Laser => {
+                params.waveform = rng.pick(&[Waveform::Square, Waveform::Sawtooth, Waveform::Sine]);
+                params.frequency = rng.range(500.0, 1600.0);
+                params.min_frequency = rng.range(80.0, 200.0);
+                params.frequency_slide = rng.range(-12.0, -4.0);
+                params.duty = rng.range(0.2, 0.5);
+                params.duty_sweep = rng.range(-1.0, 1.0);
+                params.sustain = rng.range(0.05, 0.15);
+                params.sustain_punch = rng.range(0.1, 0.3);
+                params.decay = rng.range(0.05, 0.2);
+            },
+            SfxPreset::Explosion => {
+                params.waveform = Waveform::Noise;
+                params.frequency = rng.range(100.0, 600.0);
+                params.frequency_slide = rng.range(-3.0, -0.5);
+                params.vibrato_depth = rng.range(0.0, 0.3);
+                params.vibrato_speed = rng.range(5.0, 20.0);
+                params.sustain = rng.range(0.1, 0.3);
+                params.sustain_punch = rng.range(0.2, 0.6);
+                params.decay = rng.range(0.3, 0.6);
+            },
+            SfxPreset::PowerUp => {
+                params.waveform = rng.pick(&[Waveform::Square, Waveform::Sawtooth]);
+                params.frequency = rng.range(200.0, 500.0);
+                params.frequency_slide = rng.range(2.0, 5.0);
+                params.vibrato_depth = rng.range(0.05, 0.2);
+                params.vibrato_speed = rng.range(10.0, 20.0);
+                params.sustain = rng.range(0.1, 0.3);
+                params.decay = rng.range(0.1, 0.3);
+            },
+            SfxPreset::Hit => {
+                params.waveform =
+                    rng.pick(&[Waveform::Square, Waveform::Sawtooth, Waveform::Noise]);
+                params.frequency = rng.range(200.0, 800.0);
+                params.frequency_slide = rng.range(-8.0, -4.0);
+                params.sustain = rng.range(0.02, 0.05);
+                params.decay = rng.range(0.05, 0.15);
+                params.high_pass_cutoff = rng.range(0.0, 300.0);
+            },
+            SfxPreset::Jump => {
+                params.frequency = rng.range(250.0, 500.0);
+                params.frequency_slide = rng.range(3.0, 6.0);
+                params.duty = rng.range(0.2, 0.5);
+                params.sustain = rng.range(0.05, 0.15);
+                params.decay = rng.range(0.05, 0.15);
+                params.low_pass_cutoff = rng.range(2000.0, 6000.0);
+            },
+            SfxPreset::Blip => {
+                params.waveform = rng.pick(&[Waveform::Square, Waveform::Sine]);
+                params.frequency = rng.range(400.0, 1200.0);
+                params.duty = rng.range(0.2, 0.5);
+                params.sustain = rng.range(0.03, 0.08);
+                params.decay = rng.range(0.02, 0.06);
+                params.high_pass_cutoff = 100.0;
+            },
+        }
+
+        params
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// The length of the envelope in seconds. The sound can end earlier when its
+    /// frequency drops below `min_frequency`.
+    pub fn duration(&self) -> f32 {
+        self.attack.max(0.0) + self.sustain.max(0.0) + self.decay.max(0.0)
+    }
+
+    /// Renders mono samples in the range [-1.0, 1.0].
+    pub fn render(&self, sample_rate: u32) -> Vec<f32> {
+        assert!(sample_rate > 0, "expecting a positive sample rate");
+
+        let rate = sample_rate as f32;
+        let attack = (self.attack.max(0.0) * rate) as usize;
+        let sustain = (self.sustain.max(0.0) * rate) as usize;
+        let decay = (self.decay.max(0.0) * rate) as usize;
+        let arpeggio_frame =
+            (self.arpeggio_time > 0.0).then_some((self.arpeggio_time * rate) as usize);
+
+        let mut rng = Rng::new(0);
+        let mut noise = [0.0; NOISE_STEPS];
+        noise.iter_mut().for_each(|value| *value = rng.range(-1.0, 1.0));
+
+        let mut frequency = self.frequency;
+        let mut slide = self.frequency_slide;
+        let mut duty = self.duty;
+        let mut phase = 0.0f32;
+        let mut low_pass = LowPass::new(self.low_pass_cutoff, self.low_pass_resonance, rate);
+        let mut high_pass = HighPass::new(self.high_pass_cutoff, rate);
+        let mut samples = Vec::with_capacity(attack + sustain + decay);
+
+        for frame in 0..attack + sustain + decay {
+            let envelope = if frame < attack {
+                frame as f32 / attack as f32
+            } else if frame < attack + sustain {
+                let progress = (frame - attack) as f32 / sustain as f32;
+                1.0 + (1.0 - progress) * 2.0 * self.sustain_punch
+            } else {
+                1.0 - (frame - attack - sustain) as f32 / decay as f32
+            };
+
+            if arpeggio_frame == Some(frame) {
+                frequency *= self.arpeggio;
+            }
+
+            if frequency < self.min_frequency {
+                break;
+            }
+
+            let time = frame as f32 / rate;
+            let vibrato = 1.0 + self.vibrato_depth * (TAU * self.vibrato_speed * time).sin();
+
+            phase += frequency * vibrato / rate;
+
+            if phase >= 1.0 {
+                phase %= 1.0;
+
+                if self.waveform == Waveform::Noise {
+                    noise.iter_mut().for_each(|value| *value = rng.range(-1.0, 1.0));
+                }
+            }
+
+            let sample = match self.waveform {
+                Waveform::Square if phase < duty => 1.0,
+                Waveform::Square => -1.0,
+                Waveform::Sawtooth => 1.0 - 2.0 * phase,
+                Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+                Waveform::Sine => (TAU * phase).sin(),
+                Waveform::Noise => noise[(phase * NOISE_STEPS as f32) as usize % NOISE_STEPS],
+            };
+
+            let sample = high_pass.apply(low_pass.apply(sample));
+            samples.push((sample * envelope * self.volume).clamp(-1.0, 1.0));
+
+            frequency *= (slide / rate).exp2();
+            slide += self.frequency_slide_delta / rate;
+            duty = (duty + self.duty_sweep / rate).clamp(0.0, 1.0);
+        }
+
+        samples
+    }
+
+    pub fn to_sound(&self, sample_rate: u32) -> Sound {
+        Sound::from_samples(sample_rate, 1, self.render(sample_rate))
+    }
+
+    fn numbers_mut(&mut self) -> [(&'static str, &mut f32); 18] {
+        [
+            ("frequency", &mut self.frequency),
+            ("min_frequency", &mut self.min_frequency),
+            ("frequency_slide", &mut self.frequency_slide),
+            ("frequency_slide_delta", &mut self.frequency_slide_delta),
+            ("arpeggio", &mut self.arpeggio),
+            ("arpeggio_time", &mut self.arpeggio_time),
+            ("vibrato_depth", &mut self.vibrato_depth),
+            ("vibrato_speed", &mut self.vibrato_speed),
+            ("duty", &mut self.duty),
+            ("duty_sweep", &mut self.duty_sweep),
+            ("attack", &mut self.attack),
+            ("sustain", &mut self.sustain),
+            ("sustain_punch", &mut self.sustain_punch),
+            ("decay", &mut self.decay),
+            ("low_pass_cutoff", &mut self.low_pass_cutoff),
+            ("low_pass_resonance", &mut self.low_pass_resonance),
+            ("high_pass_cutoff", &mut self.high_pass_cutoff),
+            ("volume", &mut self.volume),
+        ]
+    }
+}
+
+impl fmt::Display for SynthParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "waveform = {}", self.waveform.name())?;
+
+        for (name, value) in self.clone().numbers_mut() {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SynthParams {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut params = SynthParams::default();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error =
+                |message: String| Error::SynthParseError { line: line_index + 1, message };
+
+            let (name, value) = line
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .ok_or_else(|| parse_error("expected '='".to_string()))?;
+
+            if name == "waveform" {
+                params.waveform = Waveform::ALL
+                    .iter()
+                    .copied()
+                    .find(|waveform| waveform.name() == value)
+                    .ok_or_else(|| parse_error(format!("unknown waveform '{}'", value)))?;
+                continue;
+            }
+
+            let mut numbers = params.numbers_mut();
+            let (_, field) = numbers
+                .iter_mut()
+                .find(|(field_name, _)| *field_name == name)
+                .ok_or_else(|| parse_error(format!("unknown parameter '{}'", name)))?;
+
+            **field =
+                value.parse().map_err(|_| parse_error(format!("invalid number '{}'", value)))?;
+        }
+
+        Ok(params)
+    }
+}
+
+/// A resonant state variable filter.
+struct LowPass {
+    enabled: bool,
+    coefficient: f32,
+    damping: f32,
+    low: f32,
+    band: f32,
+}
+
+impl LowPass {
+    fn new(cutoff: f32, resonance: f32, sample_rate: f32) -> Self {
+        // The filter turns unstable above a sixth of the sample rate.
+        let cutoff = cutoff.min(sample_rate / 6.0);
+
+        Self {
+            enabled: cutoff > 0.0,
+            coefficient: 2.0 * (PI * cutoff / sample_rate).sin(),
+            damping: 1.0 - resonance.clamp(0.0, 0.9),
+            low: 0.0,
+            band: 0.0,
+        }
+    }
+
+    fn apply(&mut self, input: f32) -> f32 {
+        if !self.enabled {
+            return input;
+        }
+
+        self.low += self.coefficient * self.band;
+        let high = input - self.low - self.damping * self.band;
+        self.band += self.coefficient * high;
+        self.low
+    }
+}
+
+/// A one pole filter.
+struct HighPass {
+    enabled: bool,
+    coefficient: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl HighPass {
+    fn new(cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (TAU * cutoff);
+
+        Self {
+            enabled: cutoff > 0.0,
+            coefficient: rc / (rc + 1.0 / sample_rate),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    fn apply(&mut self, input: f32) -> f32 {
+        if !self.enabled {
+            return input;
+        }
+
+        self.previous_output =
+            self.coefficient * (self.previous_output + input - self.previous_input);
+        self.previous_input = input;
+        self.previous_output
+    }
+}
+
+/// xorshift64*, enough for noise and preset variations.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        const MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+        // The state must never be zero.
+        Self(Some(seed ^ MIX).filter(|&state| state != 0).unwrap_or(MIX))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[(self.next() % values.len() as u64) as usize]
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{graphics::RecordingMode, input::Button, RefreshRate, WindowDimensions};
+use std::{path::PathBuf, time::Duration};
+
+/// Runtime configuration for the window, the game loop and the graphics device.
+/// Passed to `run_game_app`.
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+    pub(crate) title: String,
+    pub(crate) dimensions: WindowDimensions,
+    pub(crate) resizable: bool,
+    pub(crate) min_size: Option<(u32, u32)>,
+    pub(crate) max_size: Option<(u32, u32)>,
+    pub(crate) refresh_rate: RefreshRate,
+    pub(crate) ticks_per_second: u32,
+    pub(crate) present_mode: wgpu::PresentMode,
+    pub(crate) required_features: wgpu::Features,
+    pub(crate) required_limits: wgpu::Limits,
+    pub(crate) power_preference: wgpu::PowerPreference,
+    pub(crate) record_input: Option<PathBuf>,
+    pub(crate) frame_budget: Option<Duration>,
+    pub(crate) gpu_profiler: bool,
+    pub(crate) shader_hot_reload: bool,
+    pub(crate) shader_dirs: Vec<PathBuf>,
+    pub(crate) screenshot_key: Option<Button>,
+    pub(crate) screenshot_dir: PathBuf,
+    pub(crate) record_frames: Option<RecordingMode>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameConfig {
+    pub fn new() -> Self {
+        Self {
+            title: "Simple Game".to_string(),
+            dimensions: WindowDimensions::Windowed(1280, 720),
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            refresh_rate: RefreshRate::Monitor,
+            ticks_per_second: 60,
+            present_mode: wgpu::PresentMode::Fifo,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+            power_preference: wgpu::PowerPreference::default(),
+            record_input: None,
+            frame_budget: None,
+            gpu_profiler: false,
+            shader_hot_reload: false,
+            shader_dirs: Vec::new(),
+            screenshot_key: None,
+            screenshot_dir: PathBuf::from("screenshots"),
+            record_frames: None,
+        }
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn dimensions(mut self, dimensions: WindowDimensions) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Minimum inner size of the window, in physical pixels.
+    pub fn min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// Maximum inner size of the window, in physical pixels.
+    pub fn max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    /// How often frames are rendered.
+    pub fn refresh_rate(mut self, refresh_rate: RefreshRate) -> Self {
+        self.refresh_rate = refresh_rate;
+        self
+    }
+
+    /// The number of fixed-length simulation ticks per second, independent of the render rate.
+    pub fn ticks_per_second(mut self, ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0, "expecting a positive tick rate");
+        self.ticks_per_second = ticks_per_second;
+        self
+    }
+
+    /// Falls back to `PresentMode::Fifo` if the surface doesn't support the requested mode.
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn required_features(mut self, required_features: wgpu::Features) -> Self {
+        self.required_features = required_features;
+        self
+    }
+
+    pub fn required_limits(mut self, required_limits: wgpu::Limits) -> Self {
+        self.required_limits = required_limits;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Enables the `GpuProfiler` on the graphics device, requesting timestamp query
+    /// features if the adapter supports them.
+    pub fn gpu_profiler(mut self, gpu_profiler: bool) -> Self {
+        self.gpu_profiler = gpu_profiler;
+        self
+    }
+
+    /// Watches the built-in shader sources and any `shader_dir` for changes in debug
+    /// builds, so drawers can rebuild their pipelines in `reload_shaders`. Does nothing
+    /// in release builds.
+    ///
+    /// Pipelines are only rebuilt when the game calls `reload_shaders` on each of its
+    /// drawers, typically at the start of `GameApp::render`. Drawers which are never asked
+    /// keep their pipelines.
+    pub fn shader_hot_reload(mut self, shader_hot_reload: bool) -> Self {
+        self.shader_hot_reload = shader_hot_reload;
+        self
+    }
+
+    /// An extra directory to watch when hot reloading shaders. Shaders in it replace the
+    /// built-in shaders with the same file name.
+    pub fn shader_dir<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.shader_dirs.push(directory.into());
+        self
+    }
+
+    /// Pressing `key` saves the next frame as a PNG file in the `screenshot_dir`. Only
+    /// works for frames finished with `GraphicsDevice::end_frame`.
+    pub fn screenshot_key<B: Into<Button>>(mut self, key: B) -> Self {
+        self.screenshot_key = Some(key.into());
+        self
+    }
+
+    /// Where screenshots taken with the `screenshot_key` are saved. Defaults to
+    /// `screenshots` in the working directory.
+    pub fn screenshot_dir<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.screenshot_dir = directory.into();
+        self
+    }
+
+    /// Records every frame from the start of the game, for trailers and bug clips. The
+    /// simulation advances by the recording's fixed frame time each frame, so the
+    /// recording plays back at the right speed however long frames take to render. Only
+    /// frames finished with `GraphicsDevice::end_frame` are recorded, the simulation holds
+    /// still after a frame which wasn't, such as while minimized. The recording carries on
+    /// across a suspend and resume.
+    pub fn record_frames(mut self, mode: RecordingMode) -> Self {
+        self.record_frames = Some(mode);
+        self
+    }
+
+    /// The tick and render time a frame may take before `FrameStats` counts it as over
+    /// budget. Defaults to the target frame time of the refresh rate.
+    pub fn frame_budget(mut self, frame_budget: Duration) -> Self {
+        self.frame_budget = Some(frame_budget);
+        self
+    }
+
+    /// Records all input into an `InputRecording` which is written to `path` when the
+    /// game exits, for replaying with `InputRecording::replay`.
+    pub fn record_input<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.record_input = Some(path.into());
+        self
+    }
+}
@@ -0,0 +1,211 @@
+//! Golden-image regression tests.
+//!
+//! Renders a `GameApp` or a draw closure on a headless `GraphicsDevice`, reads the
+//! result back and compares it against a reference PNG checked into the repo.
+//!
+//! Set `SIMPLE_GAME_UPDATE_GOLDEN=1` to (re)write the reference images instead of
+//! comparing against them.
+
+use crate::{
+    graphics::{FrameEncoder, GraphicsDevice},
+    input::Input,
+    util::write_png_rgba,
+    GameApp,
+};
+use std::path::PathBuf;
+
+const UPDATE_ENV_VAR: &str = "SIMPLE_GAME_UPDATE_GOLDEN";
+
+pub struct GoldenTest {
+    name: String,
+    width: u32,
+    height: u32,
+    frames: usize,
+    ticks_per_second: u32,
+    tolerance: u8,
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+}
+
+impl GoldenTest {
+    /// References are read from `tests/golden/<name>.png` and failures are written to
+    /// `target/golden/`, both relative to the crate being tested.
+    pub fn new(name: &str) -> Self {
+        let manifest_dir =
+            PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into()));
+
+        Self {
+            name: name.to_string(),
+            width: 256,
+            height: 256,
+            frames: 1,
+            ticks_per_second: 60,
+            tolerance: 2,
+            reference_dir: manifest_dir.join("tests").join("golden"),
+            output_dir: manifest_dir.join("target").join("golden"),
+        }
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// How many frames to render before reading back the result.
+    pub fn frames(mut self, frames: usize) -> Self {
+        assert!(frames > 0, "expecting at least one frame");
+        self.frames = frames;
+        self
+    }
+
+    /// The tick rate used by `run_app`, each frame runs exactly one tick.
+    pub fn ticks_per_second(mut self, ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0, "expecting a positive tick rate");
+        self.ticks_per_second = ticks_per_second;
+        self
+    }
+
+    /// The maximum allowed difference of any color channel in a single pixel.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn reference_dir<P: Into<PathBuf>>(mut self, reference_dir: P) -> Self {
+        self.reference_dir = reference_dir.into();
+        self
+    }
+
+    pub fn output_dir<P: Into<PathBuf>>(mut self, output_dir: P) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    /// Creates a headless device of the configured size for use with `run`.
+    pub fn graphics_device(&self) -> GraphicsDevice {
+        pollster::block_on(GraphicsDevice::new_headless(self.width, self.height))
+            .expect("Failed to create a headless graphics device")
+    }
+
+    /// Initializes `G` on a headless device, then ticks and renders it once per frame.
+    pub fn run_app<G: GameApp>(self) {
+        let mut graphics_device = self.graphics_device();
+        let mut game_app = G::init(&mut graphics_device);
+        let dt = 1.0 / self.ticks_per_second as f32;
+        let input = Input::new();
+
+        for _ in 0..self.frames {
+            game_app.tick(dt, &input);
+            game_app.render(&mut graphics_device, 0.0);
+        }
+
+        let texture = graphics_device.offscreen_texture().expect("Device should be headless");
+        let pixels = graphics_device.read_texture_rgba(texture);
+
+        self.check(&pixels);
+    }
+
+    /// Calls `draw` once per frame with a fresh `FrameEncoder`, which is then submitted.
+    /// `graphics_device` must be headless, usually created with `GoldenTest::graphics_device`.
+    pub fn run<F>(self, graphics_device: &mut GraphicsDevice, mut draw: F)
+    where
+        F: FnMut(&GraphicsDevice, &mut FrameEncoder),
+    {
+        for _ in 0..self.frames {
+            let mut frame_encoder = graphics_device
+                .begin_frame()
+                .expect("Failed to begin frame")
+                .expect("Headless devices always have a frame available");
+            draw(graphics_device, &mut frame_encoder);
+
+            graphics_device.queue().submit(Some(frame_encoder.encoder.finish()));
+            frame_encoder.frame.present();
+        }
+
+        let texture = graphics_device.offscreen_texture().expect("Device should be headless");
+        let pixels = graphics_device.read_texture_rgba(texture);
+
+        self.check(&pixels);
+    }
+
+    fn check(&self, pixels: &[u8]) {
+        let reference_path = self.reference_dir.join(format!("{}.png", self.name));
+
+        if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+            std::fs::create_dir_all(&self.reference_dir)
+                .expect("Failed to create golden reference directory");
+            write_png_rgba(&reference_path, self.width, self.height, pixels)
+                .expect("Failed to write golden reference image");
+            return;
+        }
+
+        let reference_bytes = std::fs::read(&reference_path).unwrap_or_else(|err| {
+            panic!(
+                "Missing golden reference {} ({}), run with {}=1 to create it",
+                reference_path.display(),
+                err,
+                UPDATE_ENV_VAR
+            )
+        });
+
+        let (header, expected) =
+            png_decoder::decode(&reference_bytes).expect("Invalid golden reference PNG");
+
+        assert_eq!(
+            (header.width, header.height),
+            (self.width, self.height),
+            "Golden reference {} has a different size than the rendered image",
+            reference_path.display()
+        );
+
+        let (mismatched_pixels, diff) = compare_rgba(&expected, pixels, self.tolerance);
+
+        if mismatched_pixels > 0 {
+            std::fs::create_dir_all(&self.output_dir)
+                .expect("Failed to create golden output directory");
+
+            let actual_path = self.output_dir.join(format!("{}.actual.png", self.name));
+            let diff_path = self.output_dir.join(format!("{}.diff.png", self.name));
+
+            write_png_rgba(&actual_path, self.width, self.height, pixels)
+                .expect("Failed to write actual image");
+            write_png_rgba(&diff_path, self.width, self.height, &diff)
+                .expect("Failed to write diff image");
+
+            panic!(
+                "Golden image {} differs in {} pixels (tolerance {}), see {} and {}",
+                self.name,
+                mismatched_pixels,
+                self.tolerance,
+                actual_path.display(),
+                diff_path.display()
+            );
+        }
+    }
+}
+
+/// Compares two RGBA8 images of the same size. Returns the number of pixels where any
+/// channel differs by more than `tolerance`, and a diff image with those pixels in red
+/// on top of a faded grayscale copy of `expected`.
+fn compare_rgba(expected: &[u8], actual: &[u8], tolerance: u8) -> (usize, Vec<u8>) {
+    assert_eq!(expected.len(), actual.len(), "images should be the same size");
+
+    let mut mismatched_pixels = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+
+    for (expected, actual) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let mismatch = expected.iter().zip(actual).any(|(e, a)| e.abs_diff(*a) > tolerance);
+
+        if mismatch {
+            mismatched_pixels += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 3;
+            let faded = (luma / 4) as u8;
+            diff.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    (mismatched_pixels, diff)
+}
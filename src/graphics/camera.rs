@@ -0,0 +1,38 @@
+use glam::{vec2, Mat4, Vec2};
+
+/// Anything drawers can take their view and projection from, see
+/// `DebugDrawer::set_camera`.
+pub trait Camera {
+    /// Maps world positions to normalized device coordinates of the camera's viewport,
+    /// which drawers leave to the render pass, see `Viewport::apply`.
+    fn matrix(&self) -> Mat4;
+}
+
+/// The rectangle of the render target a camera draws to, in pixels from the top left.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    /// The whole render target.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { x: 0.0, y: 0.0, width: width as f32, height: height as f32 }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        vec2(self.width, self.height)
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width / self.height.max(1.0)
+    }
+
+    /// Restricts drawing in `render_pass` to the viewport.
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_viewport(self.x, self.y, self.width, self.height, 0.0, 1.0);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::graphics::{Camera, Viewport};
+use glam::{vec2, vec3, Mat4, Vec2};
+
+/// Screen shake from `Camera2D::add_trauma`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Shake {
+    /// In world units.
+    max_offset: f32,
+    /// In radians.
+    max_angle: f32,
+    /// Trauma lost per second.
+    decay: f32,
+    trauma: f32,
+    time: f32,
+}
+
+/// A 2D camera for `DebugDrawer`, `LineDrawer2d`, `ImageDrawer` and `TextSystem`, see
+/// their `set_camera` methods.
+///
+/// The matrix maps the world to the viewport, not to the whole render target. Unless
+/// the viewport covers the whole target, apply it to the render pass with
+/// `Viewport::apply` before ending the recorders which use the camera.
+///
+/// The world has the same orientation as the screen, with y pointing down, so text and
+/// images stay upright. At a zoom of 1.0 a world unit is a pixel, and a fresh camera
+/// shows exactly the same as drawing without a camera.
+///
+/// Following a target, smoothing and screen shake happen in `update`, which is meant to
+/// be called from `GameApp::tick`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera2D {
+    /// The world position at the center of the viewport.
+    position: Vec2,
+    zoom: f32,
+    /// In radians, positive values turn the camera clockwise so the world turns
+    /// counter-clockwise on screen.
+    rotation: f32,
+    viewport: Viewport,
+    /// The view is kept within these world bounds.
+    bounds: Option<(Vec2, Vec2)>,
+    target: Option<Vec2>,
+    /// Seconds to close half the distance to the target, zero snaps to it.
+    smoothing: f32,
+    shake: Shake,
+}
+
+impl Camera for Camera2D {
+    fn matrix(&self) -> Mat4 {
+        Camera2D::matrix(self)
+    }
+}
+
+impl Camera2D {
+    /// A camera with a viewport covering the whole render target, looking at its center.
+    pub fn new(viewport_width: u32, viewport_height: u32) -> Self {
+        let viewport = Viewport::new(viewport_width, viewport_height);
+
+        Self {
+            position: viewport.size() / 2.0,
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            bounds: None,
+            target: None,
+            smoothing: 0.0,
+            shake: Shake { max_offset: 20.0, max_angle: 0.05, decay: 1.0, trauma: 0.0, time: 0.0 },
+        }
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.set_position(position);
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.set_zoom(zoom);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Zooms so that `height` world units fill the viewport vertically.
+    pub fn with_view_height(mut self, height: f32) -> Self {
+        self.set_zoom(self.viewport.height / height);
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.set_viewport(viewport);
+        self
+    }
+
+    /// Keeps the view within the rectangle from `min` to `max`. Rotation is ignored, and
+    /// views larger than the bounds are centered on them.
+    pub fn with_bounds(mut self, min: Vec2, max: Vec2) -> Self {
+        self.set_bounds(Some((min, max)));
+        self
+    }
+
+    /// How many seconds following a target takes to close half the distance to it.
+    pub fn with_smoothing(mut self, half_life: f32) -> Self {
+        self.smoothing = half_life.max(0.0);
+        self
+    }
+
+    /// The strongest screen shake, at a trauma of 1.0, moves the camera by up to
+    /// `max_offset` world units and rotates it by up to `max_angle` radians. Trauma wears
+    /// off by `decay` per second.
+    pub fn with_shake(mut self, max_offset: f32, max_angle: f32, decay: f32) -> Self {
+        self.shake.max_offset = max_offset;
+        self.shake.max_angle = max_angle;
+        self.shake.decay = decay;
+        self
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        self.bounds
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.shake.trauma
+    }
+
+    /// Moves the camera right away, and stops following a target.
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = self.clamp_to_bounds(position);
+        self.target = None;
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        assert!(zoom > 0.0, "expecting a positive zoom");
+        self.zoom = zoom;
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    /// Resizes the viewport, meant to be called when the window resizes.
+    pub fn resize(&mut self, viewport_width: u32, viewport_height: u32) {
+        let Viewport { x, y, .. } = self.viewport;
+        self.set_viewport(Viewport { x, y, ..Viewport::new(viewport_width, viewport_height) });
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<(Vec2, Vec2)>) {
+        self.bounds = bounds;
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    /// Moves towards `target` in `update`, smoothed by `with_smoothing`.
+    pub fn follow(&mut self, target: Vec2) {
+        self.target = Some(target);
+    }
+
+    /// Adds to the trauma, from 0.0 to 1.0, which shakes the camera. The shake grows with
+    /// the square of the trauma, so small hits barely shake while big ones add up.
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.shake.trauma = (self.shake.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    /// Follows the target and advances the screen shake by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(target) = self.target {
+            let progress =
+                if self.smoothing > 0.0 { 1.0 - 0.5f32.powf(dt / self.smoothing) } else { 1.0 };
+
+            let position = self.position + (target - self.position) * progress;
+            self.position = self.clamp_to_bounds(position);
+        }
+
+        self.shake.trauma = (self.shake.trauma - self.shake.decay * dt).max(0.0);
+        self.shake.time += dt;
+    }
+
+    /// Maps world positions to the normalized device coordinates of the viewport,
+    /// including the screen shake.
+    pub fn matrix(&self) -> Mat4 {
+        let Viewport { width, height, .. } = self.viewport;
+        Mat4::orthographic_rh(0.0, width, height, 0.0, -1.0, 1.0) * self.view_matrix()
+    }
+
+    /// The world position under `screen_position`, in pixels of the render target.
+    pub fn screen_to_world(&self, screen_position: Vec2) -> Vec2 {
+        let viewport_position = screen_position - vec2(self.viewport.x, self.viewport.y);
+        self.view_matrix().inverse().transform_point3(viewport_position.extend(0.0)).truncate()
+    }
+
+    /// The position of `world_position` in pixels of the render target.
+    pub fn world_to_screen(&self, world_position: Vec2) -> Vec2 {
+        let viewport_position =
+            self.view_matrix().transform_point3(world_position.extend(0.0)).truncate();
+        viewport_position + vec2(self.viewport.x, self.viewport.y)
+    }
+
+    /// Maps world positions to pixels of the viewport.
+    fn view_matrix(&self) -> Mat4 {
+        let (offset, angle) = self.shake_offset();
+
+        Mat4::from_translation((self.viewport.size() / 2.0).extend(0.0))
+            * Mat4::from_scale(vec3(self.zoom, self.zoom, 1.0))
+            * Mat4::from_rotation_z(-(self.rotation + angle))
+            * Mat4::from_translation(-(self.position + offset).extend(0.0))
+    }
+
+    /// The current shake as a world offset and an angle. Smooth noise from a few
+    /// unrelated sine waves, so it's deterministic for replays.
+    fn shake_offset(&self) -> (Vec2, f32) {
+        let Shake { max_offset, max_angle, trauma, time, .. } = self.shake;
+        let shake = trauma * trauma;
+
+        if shake == 0.0 {
+            return (Vec2::ZERO, 0.0);
+        }
+
+        let noise =
+            |seed: f32| ((time * 23.0 + seed).sin() + (time * 37.1 + seed * 2.3).sin() * 0.5) / 1.5;
+
+        (vec2(noise(0.0), noise(1.7)) * max_offset * shake, noise(4.1) * max_angle * shake)
+    }
+
+    fn clamp_to_bounds(&self, position: Vec2) -> Vec2 {
+        let Some((min, max)) = self.bounds else {
+            return position;
+        };
+
+        let half_view = self.viewport.size() / (2.0 * self.zoom);
+        let clamp = |value: f32, min: f32, max: f32, half: f32| {
+            if max - min <= half * 2.0 {
+                (min + max) / 2.0
+            } else {
+                value.clamp(min + half, max - half)
+            }
+        };
+
+        vec2(
+            clamp(position.x, min.x, max.x, half_view.x),
+            clamp(position.y, min.y, max.y, half_view.y),
+        )
+    }
+}
@@ -0,0 +1,579 @@
+use crate::{
+    graphics::{Camera, Viewport},
+    input::{Button, GamepadAxis, Input},
+};
+use glam::{vec2, vec3, EulerRot, Mat4, Quat, Vec2, Vec3};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+/// Controllers stop just short of looking straight up or down, where yaw is undefined.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// How a `Camera3D` maps its view to the viewport.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view in radians. `far` can be `f32::INFINITY`,
+    /// which works best with reverse-Z.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// `height` world units fill the viewport vertically.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// A half-line from `origin` along `direction`, which has unit length for the rays
+/// from `Camera3D::screen_ray`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Where the ray hits the plane through `point` facing `normal`, from either side.
+    /// `None` if the ray runs parallel to the plane or points away from it.
+    pub fn intersect_plane(&self, point: Vec3, normal: Vec3) -> Option<Vec3> {
+        let denominator = self.direction.dot(normal);
+
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let distance = (point - self.origin).dot(normal) / denominator;
+        (distance >= 0.0).then(|| self.at(distance))
+    }
+}
+
+/// A 3D camera with a right-handed world, y pointing up and the camera looking down its
+/// local -z axis.
+///
+/// Pass the camera to `LineRecorder::end_with_camera` or `DebugDrawer::set_camera`.
+/// `OrbitController` and `FlyController` move it around with the mouse, keyboard and
+/// gamepad.
+///
+/// With reverse-Z the near plane maps to a depth of 1.0 and the far plane to 0.0, which
+/// spreads the depth precision much more evenly. Depth buffers then have to be cleared
+/// to `depth_clear_value` and tested with `depth_compare`, see
+/// `LineDrawer::new_with_depth_compare`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera3D {
+    position: Vec3,
+    orientation: Quat,
+    projection: Projection,
+    viewport: Viewport,
+    reverse_z: bool,
+}
+
+impl Camera for Camera3D {
+    fn matrix(&self) -> Mat4 {
+        Camera3D::matrix(self)
+    }
+}
+
+impl Camera3D {
+    /// A perspective camera at the origin looking along -z, with a vertical field of view
+    /// of `fov_y` radians.
+    pub fn perspective(viewport_width: u32, viewport_height: u32, fov_y: f32) -> Self {
+        Self::new(
+            viewport_width,
+            viewport_height,
+            Projection::Perspective { fov_y, near: 0.1, far: 1000.0 },
+        )
+    }
+
+    /// An orthographic camera at the origin looking along -z, showing `height` world
+    /// units vertically.
+    pub fn orthographic(viewport_width: u32, viewport_height: u32, height: f32) -> Self {
+        Self::new(
+            viewport_width,
+            viewport_height,
+            Projection::Orthographic { height, near: 0.1, far: 1000.0 },
+        )
+    }
+
+    pub fn new(viewport_width: u32, viewport_height: u32, projection: Projection) -> Self {
+        Self {
+            position: Vec3::ZERO,
+            orientation: Quat::IDENTITY,
+            projection,
+            viewport: Viewport::new(viewport_width, viewport_height),
+            reverse_z: false,
+        }
+    }
+
+    pub fn with_position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: Quat) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Turns the camera towards `target`, keeping y up.
+    pub fn with_target(mut self, target: Vec3) -> Self {
+        self.look_at(target, Vec3::Y);
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn orientation(&self) -> Quat {
+        self.orientation
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    pub fn reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
+    /// The direction the camera looks in.
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::Y
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub fn set_orientation(&mut self, orientation: Quat) {
+        self.orientation = orientation;
+    }
+
+    /// Turns the camera towards `target`. Does nothing if the camera is at `target`.
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let forward = target - self.position;
+
+        if forward.length_squared() > 0.0 {
+            let forward = forward.normalize();
+
+            // Any up works when looking straight along it.
+            let up = if forward.cross(up).length_squared() < 1e-6 {
+                forward.any_orthonormal_vector()
+            } else {
+                up
+            };
+
+            let view = Mat4::look_to_rh(Vec3::ZERO, forward, up);
+            self.orientation = Quat::from_mat4(&view).inverse();
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// Resizes the viewport, meant to be called when the window resizes.
+    pub fn resize(&mut self, viewport_width: u32, viewport_height: u32) {
+        let Viewport { x, y, .. } = self.viewport;
+        self.viewport = Viewport { x, y, ..Viewport::new(viewport_width, viewport_height) };
+    }
+
+    pub fn set_reverse_z(&mut self, reverse_z: bool) {
+        self.reverse_z = reverse_z;
+    }
+
+    /// The depth test matching the projection, `Less` or `Greater` with reverse-Z.
+    pub fn depth_compare(&self) -> wgpu::CompareFunction {
+        if self.reverse_z {
+            wgpu::CompareFunction::Greater
+        } else {
+            wgpu::CompareFunction::Less
+        }
+    }
+
+    /// The depth of the far plane, what depth buffers are cleared to.
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reverse_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Maps world positions to view space, where the camera sits at the origin.
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.orientation, self.position).inverse()
+    }
+
+    /// Maps view space to normalized device coordinates of the viewport.
+    pub fn projection_matrix(&self) -> Mat4 {
+        let aspect_ratio = self.viewport.aspect_ratio();
+
+        match (self.projection, self.reverse_z) {
+            (Projection::Perspective { fov_y, near, far }, false) if far.is_infinite() => {
+                Mat4::perspective_infinite_rh(fov_y, aspect_ratio, near)
+            },
+            (Projection::Perspective { fov_y, near, far }, true) if far.is_infinite() => {
+                Mat4::perspective_infinite_reverse_rh(fov_y, aspect_ratio, near)
+            },
+            // Swapping the planes flips the depth range.
+            (Projection::Perspective { fov_y, near, far }, false) => {
+                Mat4::perspective_rh(fov_y, aspect_ratio, near, far)
+            },
+            (Projection::Perspective { fov_y, near, far }, true) => {
+                Mat4::perspective_rh(fov_y, aspect_ratio, far, near)
+            },
+            (Projection::Orthographic { height, near, far }, reverse_z) => {
+                let half = vec2(height * aspect_ratio, height) / 2.0;
+                let (near, far) = if reverse_z { (far, near) } else { (near, far) };
+                Mat4::orthographic_rh(-half.x, half.x, -half.y, half.y, near, far)
+            },
+        }
+    }
+
+    /// Maps world positions to normalized device coordinates of the viewport.
+    pub fn matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// The ray through `screen_position`, in pixels of the render target. Perspective
+    /// rays start at the camera, orthographic ones on the plane through the camera.
+    pub fn screen_ray(&self, screen_position: Vec2) -> Ray {
+        let ndc = self.screen_to_ndc(screen_position);
+
+        match self.projection {
+            Projection::Perspective { fov_y, .. } => {
+                let half_height = (fov_y / 2.0).tan();
+                let half = vec2(half_height * self.viewport.aspect_ratio(), half_height);
+                let direction = (ndc * half).extend(-1.0).normalize();
+
+                Ray::new(self.position, self.orientation * direction)
+            },
+            Projection::Orthographic { height, .. } => {
+                let half = vec2(height * self.viewport.aspect_ratio(), height) / 2.0;
+                let offset = self.orientation * (ndc * half).extend(0.0);
+
+                Ray::new(self.position + offset, self.forward())
+            },
+        }
+    }
+
+    /// The position of `world_position` in pixels of the render target, or `None` if
+    /// it's behind the camera.
+    pub fn world_to_screen(&self, world_position: Vec3) -> Option<Vec2> {
+        let clip = self.matrix() * world_position.extend(1.0);
+
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let ndc = clip.truncate().truncate() / clip.w;
+        let Viewport { x, y, width, height } = self.viewport;
+        Some(vec2(x + (ndc.x + 1.0) / 2.0 * width, y + (1.0 - ndc.y) / 2.0 * height))
+    }
+
+    /// How many world units a pixel covers at `distance` in front of the camera.
+    pub fn world_units_per_pixel(&self, distance: f32) -> f32 {
+        let view_height = match self.projection {
+            Projection::Perspective { fov_y, .. } => 2.0 * distance * (fov_y / 2.0).tan(),
+            Projection::Orthographic { height, .. } => height,
+        };
+
+        view_height / self.viewport.height.max(1.0)
+    }
+
+    fn screen_to_ndc(&self, screen_position: Vec2) -> Vec2 {
+        let Viewport { x, y, width, height } = self.viewport;
+        let relative = (screen_position - vec2(x, y)) / vec2(width, height).max(Vec2::ONE);
+        vec2(relative.x * 2.0 - 1.0, 1.0 - relative.y * 2.0)
+    }
+}
+
+/// Orbits a `Camera3D` around a target, like the viewports of modeling tools. Dragging
+/// with the left mouse button rotates, the middle button pans and scrolling zooms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitController {
+    target: Vec3,
+    distance: f32,
+    /// In radians around the y axis, zero looks along -z.
+    yaw: f32,
+    /// In radians, positive values look down at the target from above.
+    pitch: f32,
+    rotate_button: Button,
+    pan_button: Option<Button>,
+    /// Radians per pixel of mouse motion.
+    sensitivity: f32,
+    /// How much closer each line scrolled gets, as a fraction of the distance.
+    zoom_speed: f32,
+    min_distance: f32,
+    max_distance: f32,
+}
+
+impl OrbitController {
+    /// Orbits `target` from where `camera` is now.
+    pub fn new(camera: &Camera3D, target: Vec3) -> Self {
+        let offset = camera.position() - target;
+        let distance = offset.length().max(f32::EPSILON);
+
+        Self {
+            target,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin().clamp(-MAX_PITCH, MAX_PITCH),
+            rotate_button: MouseButton::Left.into(),
+            pan_button: Some(MouseButton::Middle.into()),
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+            max_distance: f32::INFINITY,
+        }
+    }
+
+    pub fn with_rotate_button<B: Into<Button>>(mut self, button: B) -> Self {
+        self.rotate_button = button.into();
+        self
+    }
+
+    /// `None` turns panning off.
+    pub fn with_pan_button(mut self, button: Option<Button>) -> Self {
+        self.pan_button = button;
+        self
+    }
+
+    /// Radians the camera turns per pixel of mouse motion.
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    /// How much closer each line scrolled gets, as a fraction of the distance.
+    pub fn with_zoom_speed(mut self, zoom_speed: f32) -> Self {
+        self.zoom_speed = zoom_speed.clamp(0.0, 0.99);
+        self
+    }
+
+    pub fn with_distance_limits(mut self, min_distance: f32, max_distance: f32) -> Self {
+        assert!(min_distance <= max_distance, "expecting min_distance <= max_distance");
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self.distance = self.distance.clamp(min_distance, max_distance);
+        self
+    }
+
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = target;
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn set_angles(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Applies this tick's mouse input and moves `camera` into place. Zooming also scales
+    /// the height of orthographic cameras, which would look the same at any distance.
+    pub fn update(&mut self, input: &Input, camera: &mut Camera3D) {
+        let mouse_delta = input.mouse_delta();
+
+        if input.is_down(self.rotate_button) {
+            self.set_angles(
+                self.yaw - mouse_delta.x * self.sensitivity,
+                self.pitch + mouse_delta.y * self.sensitivity,
+            );
+        }
+
+        if self.pan_button.is_some_and(|button| input.is_down(button)) {
+            let units_per_pixel = camera.world_units_per_pixel(self.distance);
+            let pan = camera.right() * -mouse_delta.x + camera.up() * mouse_delta.y;
+            self.target += pan * units_per_pixel;
+        }
+
+        let scroll = input.scroll_delta().y;
+
+        if scroll != 0.0 {
+            let previous = self.distance;
+            self.set_distance(self.distance * (1.0 - self.zoom_speed).powf(scroll));
+
+            if let Projection::Orthographic { height, near, far } = camera.projection() {
+                let height = height * self.distance / previous;
+                camera.set_projection(Projection::Orthographic { height, near, far });
+            }
+        }
+
+        let orientation = orientation(self.yaw, -self.pitch);
+        camera.set_orientation(orientation);
+        camera.set_position(self.target + orientation * vec3(0.0, 0.0, self.distance));
+    }
+}
+
+/// Flies a `Camera3D` around like a first person game. WASD moves, Q and E move down and
+/// up, shift moves faster, and the mouse looks around while the right button is held.
+/// The left stick of a gamepad moves and the right stick looks around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlyController {
+    /// In radians around the y axis, zero looks along -z.
+    yaw: f32,
+    /// In radians, positive values look up.
+    pitch: f32,
+    /// World units per second.
+    speed: f32,
+    /// Speed multiplier while shift is held.
+    boost: f32,
+    /// Radians per pixel of mouse motion.
+    sensitivity: f32,
+    /// Radians per second with the stick all the way.
+    stick_speed: f32,
+    look_button: Option<Button>,
+}
+
+impl FlyController {
+    /// Flies `camera` from where it is now, facing the same way.
+    pub fn new(camera: &Camera3D) -> Self {
+        let forward = camera.forward();
+
+        Self {
+            yaw: (-forward.x).atan2(-forward.z),
+            pitch: forward.y.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH),
+            speed: 5.0,
+            boost: 4.0,
+            sensitivity: 0.003,
+            stick_speed: 2.5,
+            look_button: Some(MouseButton::Right.into()),
+        }
+    }
+
+    /// In world units per second.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Speed multiplier while shift is held.
+    pub fn with_boost(mut self, boost: f32) -> Self {
+        self.boost = boost;
+        self
+    }
+
+    /// Radians the camera turns per pixel of mouse motion.
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    /// The button to hold for looking around with the mouse, `None` always looks around,
+    /// which suits a grabbed cursor.
+    pub fn with_look_button(mut self, button: Option<Button>) -> Self {
+        self.look_button = button;
+        self
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn set_angles(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Applies this tick's input and moves `camera` by `dt` seconds.
+    pub fn update(&mut self, input: &Input, camera: &mut Camera3D, dt: f32) {
+        let mut look = vec2(
+            input.gamepad_axis(GamepadAxis::RightStickX),
+            -input.gamepad_axis(GamepadAxis::RightStickY),
+        ) * self.stick_speed
+            * dt;
+
+        if self.look_button.is_none_or(|button| input.is_down(button)) {
+            look += input.mouse_delta() * self.sensitivity;
+        }
+
+        self.set_angles(self.yaw - look.x, self.pitch - look.y);
+
+        let key = |negative: KeyCode, positive: KeyCode| {
+            input.is_down(positive) as i32 as f32 - input.is_down(negative) as i32 as f32
+        };
+
+        // Right, up and backwards in the camera's frame, up and down stay vertical.
+        let mut movement = vec3(
+            key(KeyCode::KeyA, KeyCode::KeyD) + input.gamepad_axis(GamepadAxis::LeftStickX),
+            key(KeyCode::KeyQ, KeyCode::KeyE),
+            key(KeyCode::KeyW, KeyCode::KeyS) - input.gamepad_axis(GamepadAxis::LeftStickY),
+        );
+
+        if movement.length_squared() > 1.0 {
+            movement = movement.normalize();
+        }
+
+        let boosting = input.is_down(KeyCode::ShiftLeft) || input.is_down(KeyCode::ShiftRight);
+        let speed = if boosting { self.speed * self.boost } else { self.speed };
+
+        let orientation = orientation(self.yaw, self.pitch);
+        let horizontal = orientation * vec3(movement.x, 0.0, movement.z);
+        let velocity = horizontal + Vec3::Y * movement.y;
+
+        camera.set_orientation(orientation);
+        camera.set_position(camera.position() + velocity * speed * dt);
+    }
+}
+
+/// Turns by `yaw` around the y axis after tilting by `pitch` around the x axis.
+fn orientation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0)
+}
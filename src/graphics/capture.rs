@@ -0,0 +1,354 @@
+use crate::{
+    graphics::{
+        recording::{FrameRecorder, MAX_RECORDED_FRAMES_IN_FLIGHT},
+        RecordingMode,
+    },
+    util::write_png_rgba,
+    Error,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use wgpu::{Buffer, CommandEncoder, Device, Texture, TextureFormat};
+
+/// Where a captured frame is written to.
+pub(crate) enum CaptureTarget {
+    File(PathBuf),
+    /// The next frame of the active recording.
+    Recording,
+}
+
+/// A copy of a frame on its way from the GPU to a PNG file or a recording.
+struct PendingCapture {
+    target: CaptureTarget,
+    buffer: Buffer,
+    layout: ReadbackLayout,
+    /// Set by the `map_async` callback, `Some(true)` once the buffer is mapped.
+    /// Stays `None` until mapping starts, which has to wait until the copy is submitted.
+    mapped: Arc<Mutex<Option<bool>>>,
+    mapping: bool,
+}
+
+/// Saves frames to PNG files without waiting for the GPU.
+///
+/// Frames are copied into a buffer as part of their own command encoder. The buffer is
+/// mapped once the frame is submitted, and checked for completion at the start of later
+/// frames. Converting the pixels and encoding the PNG happens on a separate thread.
+pub(crate) struct FrameCapture {
+    pending: Vec<PendingCapture>,
+    writers: Vec<JoinHandle<Result<PathBuf, Error>>>,
+    /// Captured by the next `GraphicsDevice::end_frame`.
+    requested: Option<PathBuf>,
+    recorder: Option<FrameRecorder>,
+}
+
+impl FrameCapture {
+    pub(crate) fn new() -> Self {
+        Self { pending: Vec::new(), writers: Vec::new(), requested: None, recorder: None }
+    }
+
+    pub(crate) fn request(&mut self, path: PathBuf) {
+        self.requested = Some(path);
+    }
+
+    pub(crate) fn take_request(&mut self) -> Option<PathBuf> {
+        self.requested.take()
+    }
+
+    /// Captures still being read back or written.
+    pub(crate) fn pending_count(&self) -> usize {
+        let recorder_writes = self.recorder.as_ref().map_or(0, FrameRecorder::pending_writes);
+        self.pending.len() + self.writers.len() + recorder_writes
+    }
+
+    pub(crate) fn start_recording(
+        &mut self,
+        mode: RecordingMode,
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        if self.recorder.is_some() {
+            return Err(Error::AlreadyRecording);
+        }
+
+        self.recorder = Some(FrameRecorder::start(mode, size)?);
+        Ok(())
+    }
+
+    /// Writes the remaining frames of the active recording and stops it.
+    pub(crate) fn stop_recording(&mut self, device: &Device) -> Result<(), Error> {
+        self.read_back_pending(device);
+
+        // Failed screenshots are reported as they're joined, and don't fail the recording.
+        let _ = self.join_writers(true);
+
+        self.recorder.take().map_or(Ok(()), FrameRecorder::finish)
+    }
+
+    /// Reads back the frames in flight and hands over the active recording without
+    /// finishing it, so it can continue on another device with `resume_recording`.
+    pub(crate) fn suspend_recording(&mut self, device: &Device) -> Option<FrameRecorder> {
+        self.read_back_pending(device);
+        self.recorder.take()
+    }
+
+    pub(crate) fn resume_recording(&mut self, recorder: FrameRecorder) -> Result<(), Error> {
+        if self.recorder.is_some() {
+            return Err(Error::AlreadyRecording);
+        }
+
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    pub(crate) fn recording(&self) -> Option<&RecordingMode> {
+        self.recorder.as_ref().map(FrameRecorder::mode)
+    }
+
+    /// Waits for the oldest recorded frames while too many are in flight, so a recording
+    /// can't use up all memory when frames render faster than they're written.
+    pub(crate) fn throttle(&mut self, device: &Device) {
+        while self.pending_count() > MAX_RECORDED_FRAMES_IN_FLIGHT {
+            if self.pending.is_empty() {
+                if let Some(recorder) = &mut self.recorder {
+                    // Errors are reported when the recording stops.
+                    let _ = recorder.join_writers(true);
+                }
+
+                let _ = self.join_writers(true);
+            } else {
+                device.poll(wgpu::Maintain::Wait);
+                self.poll(device);
+            }
+        }
+    }
+
+    /// Records a copy of `texture` into `encoder`, which is written to `target` once the
+    /// encoder is submitted.
+    pub(crate) fn capture(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        target: CaptureTarget,
+    ) -> Result<(), Error> {
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(Error::CaptureNotSupported);
+        }
+
+        let (buffer, layout) = copy_texture_to_buffer(device, encoder, texture)?;
+
+        self.pending.push(PendingCapture {
+            target,
+            buffer,
+            layout,
+            mapped: Arc::new(Mutex::new(None)),
+            mapping: false,
+        });
+
+        Ok(())
+    }
+
+    /// Starts mapping the buffers of captures whose copies have been submitted, and
+    /// hands the mapped ones to writer threads. Never blocks.
+    pub(crate) fn poll(&mut self, device: &Device) {
+        for capture in &mut self.pending {
+            if !capture.mapping {
+                let mapped = capture.mapped.clone();
+
+                capture.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                    *mapped.lock().unwrap() = Some(result.is_ok());
+                });
+
+                capture.mapping = true;
+            }
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+
+        // Buffers are mapped in the order they were submitted, so taking them from the
+        // front keeps recorded frames in order.
+        while let Some(capture) = self.pending.first() {
+            let Some(mapped) = *capture.mapped.lock().unwrap() else {
+                break;
+            };
+
+            let capture = self.pending.remove(0);
+
+            if !mapped {
+                println!("Failed to read back a captured frame");
+                continue;
+            }
+
+            let data = capture.buffer.slice(..).get_mapped_range().to_vec();
+            capture.buffer.unmap();
+
+            let PendingCapture { target, layout, .. } = capture;
+
+            let path = match target {
+                CaptureTarget::File(path) => path,
+                CaptureTarget::Recording => {
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.write_frame(layout, data);
+                    }
+
+                    continue;
+                },
+            };
+
+            self.writers.push(std::thread::spawn(move || {
+                let pixels = layout.to_rgba(&data);
+
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                write_png_rgba(&path, layout.width, layout.height, &pixels)?;
+                Ok(path)
+            }));
+        }
+
+        // Failed writes are reported as they're joined.
+        let _ = self.join_writers(false);
+
+        if let Some(recorder) = &mut self.recorder {
+            let _ = recorder.join_writers(false);
+        }
+    }
+
+    /// Waits for every pending capture to be written. Returns the first error, if any.
+    pub(crate) fn finish(&mut self, device: &Device) -> Result<(), Error> {
+        self.read_back_pending(device);
+
+        let result = self.join_writers(true);
+
+        match &mut self.recorder {
+            Some(recorder) => result.and(recorder.join_writers(true)),
+            None => result,
+        }
+    }
+
+    /// Blocks until every pending capture has been read back and handed to a writer.
+    fn read_back_pending(&mut self, device: &Device) {
+        while !self.pending.is_empty() {
+            self.poll(device);
+            device.poll(wgpu::Maintain::Wait);
+        }
+    }
+
+    /// Joins the writer threads which finished, or all of them if `wait` is set.
+    fn join_writers(&mut self, wait: bool) -> Result<(), Error> {
+        let mut result = Ok(());
+        let mut index = 0;
+
+        while index < self.writers.len() {
+            if !wait && !self.writers[index].is_finished() {
+                index += 1;
+                continue;
+            }
+
+            match self.writers.remove(index).join().expect("Screenshot writer panicked") {
+                Ok(path) => println!("Saved screenshot to {}", path.display()),
+                Err(err) => {
+                    println!("Failed to save screenshot: {}", err);
+
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                },
+            }
+        }
+
+        result
+    }
+}
+
+/// How the rows of a texture are laid out in a readback buffer.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ReadbackLayout {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Rows in the buffer are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+    padded_bytes_per_row: u32,
+    swap_red_blue: bool,
+}
+
+impl ReadbackLayout {
+    fn new(texture: &Texture) -> Result<Self, Error> {
+        let swap_red_blue = match texture.format() {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            format => return Err(Error::UnsupportedCaptureFormat(format)),
+        };
+
+        Ok(Self {
+            width: texture.width(),
+            height: texture.height(),
+            padded_bytes_per_row: (texture.width() * 4)
+                .next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+            swap_red_blue,
+        })
+    }
+
+    /// Strips the row padding from `data` and converts it to tightly packed RGBA8.
+    pub(crate) fn to_rgba(self, data: &[u8]) -> Vec<u8> {
+        let unpadded_bytes_per_row = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+
+        for row in data.chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+        }
+
+        if self.swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        pixels
+    }
+}
+
+/// Records a copy of `texture` into a new mappable buffer.
+pub(crate) fn copy_texture_to_buffer(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture: &Texture,
+) -> Result<(Buffer, ReadbackLayout), Error> {
+    let layout = ReadbackLayout::new(texture)?;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture readback buffer"),
+        size: layout.padded_bytes_per_row as u64 * layout.height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(layout.padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+
+    Ok((buffer, layout))
+}
+
+/// A new file name for a screenshot in `directory`, based on the current time.
+pub fn screenshot_path<P: AsRef<Path>>(directory: P) -> PathBuf {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    directory.as_ref().join(format!(
+        "screenshot-{}-{:03}.png",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    ))
+}
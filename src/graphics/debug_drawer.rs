@@ -1,4 +1,8 @@
-use crate::GraphicsDevice;
+use crate::{
+    field_layout,
+    graphics::{check_vertex_layout, hot_reload::rebuild_pipeline, Camera},
+    GraphicsDevice,
+};
 use bytemuck::{Pod, Zeroable};
 use glam::{vec3, Mat4, Vec3};
 use wgpu::util::DeviceExt;
@@ -15,12 +19,18 @@ struct BindGroups {
     vertex_uniform: wgpu::BindGroup,
 }
 
+const LINE_SHADER: &str = "debug_lines.wgsl";
+const INSTANCED_SHAPE_SHADER: &str = "instanced_shape.wgsl";
+
 pub struct DebugDrawer {
     line_pipeline: wgpu::RenderPipeline,
     instanced_shape_pipeline: wgpu::RenderPipeline,
+    target_format: wgpu::TextureFormat,
+    shader_generation: u64,
     buffers: Buffers,
     bind_groups: BindGroups,
     projection: Mat4,
+    camera: Option<Mat4>,
 
     lines: Vec<LineVertex>,
     circles: Vec<CircleInstance>,
@@ -33,8 +43,16 @@ impl DebugDrawer {
         screen_width: u32,
         screen_height: u32,
     ) -> Self {
-        let line_pipeline = Self::build_line_pipeline(device, target_format);
-        let instanced_shape_pipeline = Self::build_intanced_shape_pipeline(device, target_format);
+        let line_pipeline = Self::build_line_pipeline(
+            device,
+            target_format,
+            include_str!("shaders/wgsl/debug_lines.wgsl"),
+        );
+        let instanced_shape_pipeline = Self::build_intanced_shape_pipeline(
+            device,
+            target_format,
+            include_str!("shaders/wgsl/instanced_shape.wgsl"),
+        );
         let buffers = Self::build_buffers(device);
         let bind_groups = Self::build_bind_groups(device, &line_pipeline, &buffers);
         let projection = Self::build_camera_matrix(screen_width, screen_height);
@@ -42,9 +60,12 @@ impl DebugDrawer {
         Self {
             line_pipeline,
             instanced_shape_pipeline,
+            target_format,
+            shader_generation: 0,
             buffers,
             bind_groups,
             projection,
+            camera: None,
             lines: Vec::new(),
             circles: Vec::new(),
         }
@@ -54,7 +75,60 @@ impl DebugDrawer {
         self.projection = Self::build_camera_matrix(screen_width, screen_height);
     }
 
-    pub fn begin(&mut self) -> ShapeRecorder {
+    /// Draws in the world space of `camera` instead of the default view, which is 20
+    /// units tall with y pointing up, until `reset_camera`. Takes a `Camera2D` or a
+    /// `Camera3D`, call it again whenever the camera moves.
+    pub fn set_camera<C: Camera>(&mut self, camera: &C) {
+        self.camera = Some(camera.matrix());
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera = None;
+    }
+
+    /// Rebuilds the pipelines whose shaders changed since the last call, see `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        let Some(shader_watcher) = graphics_device.shader_watcher() else {
+            return;
+        };
+
+        let device = graphics_device.device();
+
+        if let Some(source) = shader_watcher.changed_since(LINE_SHADER, self.shader_generation) {
+            if let Some(pipeline) = rebuild_pipeline(device, LINE_SHADER, || {
+                Self::build_line_pipeline(device, self.target_format, source)
+            }) {
+                self.bind_groups = Self::build_bind_groups(device, &pipeline, &self.buffers);
+                self.line_pipeline = pipeline;
+            }
+        }
+
+        if let Some(source) =
+            shader_watcher.changed_since(INSTANCED_SHAPE_SHADER, self.shader_generation)
+        {
+            if let Some(pipeline) = rebuild_pipeline(device, INSTANCED_SHAPE_SHADER, || {
+                Self::build_intanced_shape_pipeline(device, self.target_format, source)
+            }) {
+                self.instanced_shape_pipeline = pipeline;
+            }
+        }
+
+        self.shader_generation = shader_watcher.generation();
+    }
+
+    /// Compares the structs uploaded to the GPU with the layouts in the shaders.
+    pub(crate) fn layout_mismatches() -> Vec<String> {
+        // The center, radius and rotation are read as a single vec4.
+        check_vertex_layout(
+            INSTANCED_SHAPE_SHADER,
+            "CircleInstance",
+            std::mem::size_of::<CircleInstance>(),
+            &CircleInstance::ATTRIBUTES,
+            &[field_layout!(CircleInstance, center)],
+        )
+    }
+
+    pub fn begin(&mut self) -> ShapeRecorder<'_> {
         self.lines.clear();
         self.circles.clear();
 
@@ -64,9 +138,9 @@ impl DebugDrawer {
     fn build_line_pipeline(
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
+        shader_source: &str,
     ) -> wgpu::RenderPipeline {
-        let draw_shader =
-            GraphicsDevice::load_wgsl_shader(device, include_str!("shaders/wgsl/debug_lines.wgsl"));
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
         let vertex_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -128,11 +202,9 @@ impl DebugDrawer {
     fn build_intanced_shape_pipeline(
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
+        shader_source: &str,
     ) -> wgpu::RenderPipeline {
-        let draw_shader = GraphicsDevice::load_wgsl_shader(
-            device,
-            include_str!("shaders/wgsl/instanced_shape.wgsl"),
-        );
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
         let vertex_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -166,7 +238,7 @@ impl DebugDrawer {
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<CircleInstance>() as u64,
                         step_mode: wgpu::VertexStepMode::Instance,
-                        attributes: &wgpu::vertex_attr_array![0 => Float32x4],
+                        attributes: &CircleInstance::ATTRIBUTES,
                     },
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<LineVertex>() as u64,
@@ -340,7 +412,9 @@ impl ShapeRecorder<'_> {
         queue.write_buffer(
             &self.debug_drawer.buffers.vertex_uniform,
             0,
-            bytemuck::cast_slice(self.debug_drawer.projection.as_ref()),
+            bytemuck::cast_slice(
+                self.debug_drawer.camera.unwrap_or(self.debug_drawer.projection).as_ref(),
+            ),
         );
 
         render_pass.push_debug_group("Debug drawer");
@@ -378,3 +452,7 @@ struct CircleInstance {
     radius: f32,
     rotation: f32,
 }
+
+impl CircleInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x4];
+}
@@ -1,4 +1,4 @@
-use crate::GraphicsDevice;
+use crate::{graphics::hot_reload::rebuild_pipeline, GraphicsDevice};
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 
@@ -9,11 +9,15 @@ struct FullscreenQuadVertex {
     uv: [f32; 2],
 }
 
+const FULLSCREEN_QUAD_SHADER: &str = "fullscreen_quad.wgsl";
+
 pub struct FullscreenQuad {
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     pipeline: wgpu::RenderPipeline,
+    target_format: wgpu::TextureFormat,
+    shader_generation: u64,
 }
 
 impl FullscreenQuad {
@@ -39,6 +43,43 @@ impl FullscreenQuad {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        let pipeline = Self::build_pipeline(
+            device,
+            target_format,
+            include_str!("shaders/wgsl/fullscreen_quad.wgsl"),
+        );
+        let bind_group = Self::build_bind_group(device, &pipeline);
+
+        Self { vertex_buf, index_buf, bind_group, pipeline, target_format, shader_generation: 0 }
+    }
+
+    /// Rebuilds the pipeline if its shader changed since the last call, see `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        let Some(shader_watcher) = graphics_device.shader_watcher() else {
+            return;
+        };
+
+        let device = graphics_device.device();
+
+        if let Some(source) =
+            shader_watcher.changed_since(FULLSCREEN_QUAD_SHADER, self.shader_generation)
+        {
+            if let Some(pipeline) = rebuild_pipeline(device, FULLSCREEN_QUAD_SHADER, || {
+                Self::build_pipeline(device, self.target_format, source)
+            }) {
+                self.bind_group = Self::build_bind_group(device, &pipeline);
+                self.pipeline = pipeline;
+            }
+        }
+
+        self.shader_generation = shader_watcher.generation();
+    }
+
+    fn build_pipeline(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        shader_source: &str,
+    ) -> wgpu::RenderPipeline {
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("TexturedQuad bind group layout"),
             entries: &[],
@@ -50,12 +91,6 @@ impl FullscreenQuad {
             push_constant_ranges: &[],
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("TexturedQuad bind group"),
-            layout: &bind_group_layout,
-            entries: &[],
-        });
-
         let vertex_buffers = &[wgpu::VertexBufferLayout {
             array_stride: (std::mem::size_of::<FullscreenQuadVertex>()) as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -65,12 +100,9 @@ impl FullscreenQuad {
             ],
         }];
 
-        let draw_shader = GraphicsDevice::load_wgsl_shader(
-            device,
-            include_str!("shaders/wgsl/fullscreen_quad.wgsl"),
-        );
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("TexturedQuad render pipeline"),
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
@@ -109,9 +141,15 @@ impl FullscreenQuad {
             }),
             multiview: None,
             cache: None,
-        });
+        })
+    }
 
-        Self { vertex_buf, index_buf, pipeline, bind_group }
+    fn build_bind_group(device: &wgpu::Device, pipeline: &wgpu::RenderPipeline) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TexturedQuad bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[],
+        })
     }
 
     pub fn render(&self, render_pass: &mut wgpu::RenderPass) {
@@ -0,0 +1,292 @@
+use crate::graphics::WgslPreprocessor;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// The directory the built-in shaders are compiled from.
+pub const BUILTIN_SHADER_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/src/graphics/shaders/wgsl");
+
+/// How often `ShaderWatcher::poll` checks the watched directories.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+struct WatchedFile {
+    /// Files from directories watched later replace files with the same name from
+    /// directories watched earlier, and any directory replaces the built-in one.
+    precedence: usize,
+    source: String,
+}
+
+struct ReloadedShader {
+    source: String,
+    generation: u64,
+}
+
+/// Watches directories of WGSL shaders and keeps the latest valid source of every
+/// shader that changed since it started watching.
+///
+/// Shaders are identified by file name, so a file in a user shader directory named
+/// like one of the built-in shaders (`glyph.wgsl`, `image.wgsl`, ...) replaces it.
+/// Every accepted change bumps the watcher's generation. Drawers remember the
+/// generation they last saw and rebuild their pipelines in `reload_shaders` when one
+/// of their shaders changed after it.
+///
+/// Any watched file can be included by the others, and changing an included file
+/// reloads every shader which includes it. Reloaded sources have their includes
+/// expanded, so they can still be preprocessed with a drawer's own defines.
+///
+/// Changed shaders are preprocessed and validated with naga before they're accepted.
+/// Invalid sources are reported and ignored, so drawers keep their last good pipeline.
+pub struct ShaderWatcher {
+    directories: Vec<PathBuf>,
+    modified: HashMap<PathBuf, (SystemTime, u64)>,
+    files: HashMap<String, WatchedFile>,
+    /// Each file's source with its includes expanded, as of the last check.
+    expanded: HashMap<String, String>,
+    shaders: HashMap<String, ReloadedShader>,
+    errors: HashMap<String, String>,
+    generation: u64,
+    last_poll: Instant,
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        Self {
+            directories: Vec::new(),
+            modified: HashMap::new(),
+            files: HashMap::new(),
+            expanded: HashMap::new(),
+            shaders: HashMap::new(),
+            errors: HashMap::new(),
+            generation: 0,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Starts watching `directory`. Shaders already in a user directory are loaded right
+    /// away since they replace the built-in ones, for the built-in directory only later
+    /// changes are picked up.
+    pub fn watch<P: Into<PathBuf>>(&mut self, directory: P) {
+        let directory = directory.into();
+
+        if self.directories.contains(&directory) {
+            return;
+        }
+
+        let is_builtin = directory == Path::new(BUILTIN_SHADER_DIR);
+        let precedence = if is_builtin { 0 } else { self.directories.len() + 1 };
+
+        for (path, modified) in wgsl_files(&directory) {
+            self.modified.insert(path.clone(), modified);
+            self.read_file(&path, precedence);
+        }
+
+        self.directories.push(directory);
+        self.update_shaders(!is_builtin);
+    }
+
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    /// Incremented every time a changed shader is accepted.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The latest valid source of the shader named `name`, if it was reloaded.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.shaders.get(name).map(|shader| shader.source.as_str())
+    }
+
+    /// The latest valid source of the shader named `name`, if it was reloaded after
+    /// `generation`.
+    pub fn changed_since(&self, name: &str, generation: u64) -> Option<&str> {
+        self.shaders
+            .get(name)
+            .filter(|shader| shader.generation > generation)
+            .map(|shader| shader.source.as_str())
+    }
+
+    /// Why the most recent change to the shader named `name` was rejected, if it was.
+    pub fn error(&self, name: &str) -> Option<&str> {
+        self.errors.get(name).map(String::as_str)
+    }
+
+    /// Checks the watched directories for changes, at most every 250 milliseconds.
+    /// Returns true if any shader was reloaded.
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+
+        self.scan()
+    }
+
+    /// Checks the watched directories for changes right away. Returns true if any
+    /// shader was reloaded.
+    pub fn scan(&mut self) -> bool {
+        self.last_poll = Instant::now();
+        let generation = self.generation;
+        let mut changed = false;
+
+        for (index, directory) in self.directories.clone().iter().enumerate() {
+            let precedence = if directory == Path::new(BUILTIN_SHADER_DIR) { 0 } else { index + 1 };
+
+            for (path, modified) in wgsl_files(directory) {
+                if self.modified.insert(path.clone(), modified) != Some(modified) {
+                    changed |= self.read_file(&path, precedence);
+                }
+            }
+        }
+
+        if changed {
+            self.update_shaders(true);
+        }
+
+        self.generation != generation
+    }
+
+    /// Reads a watched file, unless a file with the same name from a directory with a
+    /// higher precedence replaces it. Returns true if the file was read.
+    fn read_file(&mut self, path: &Path, precedence: usize) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+
+        if self.files.get(name).is_some_and(|file| file.precedence > precedence) {
+            return false;
+        }
+
+        match std::fs::read_to_string(path) {
+            Ok(source) => {
+                self.files.insert(name.to_string(), WatchedFile { precedence, source });
+                true
+            },
+            Err(err) => {
+                println!("Failed to read shader {}: {}", path.display(), err);
+                false
+            },
+        }
+    }
+
+    /// Expands the includes of every watched file, and reloads the shaders whose
+    /// expanded source changed if `reload` is set.
+    fn update_shaders(&mut self, reload: bool) {
+        let preprocessor =
+            self.files.iter().fold(WgslPreprocessor::new(), |preprocessor, (name, file)| {
+                preprocessor.include(name.clone(), file.source.clone())
+            });
+
+        let mut names: Vec<String> = self.files.keys().cloned().collect();
+        names.sort();
+
+        for name in names {
+            let expanded = match preprocessor.expand_includes(&self.files[&name].source) {
+                Ok(expanded) => expanded,
+                Err(err) => {
+                    if reload {
+                        self.reject(name, err.to_string());
+                    }
+
+                    continue;
+                },
+            };
+
+            if self.expanded.get(&name) == Some(&expanded) {
+                continue;
+            }
+
+            self.expanded.insert(name.clone(), expanded.clone());
+
+            if !reload {
+                continue;
+            }
+
+            match validate_wgsl(&expanded) {
+                Ok(()) => {
+                    println!("Reloaded shader {}", name);
+                    self.generation += 1;
+                    self.errors.remove(&name);
+                    self.shaders.insert(
+                        name,
+                        ReloadedShader { source: expanded, generation: self.generation },
+                    );
+                },
+                Err(err) => self.reject(name, err),
+            }
+        }
+    }
+
+    fn reject(&mut self, name: String, err: String) {
+        println!("Failed to reload shader {}:\n{}", name, err);
+        self.errors.insert(name, err);
+    }
+}
+
+/// The WGSL files in `directory`, with when they were last modified and their size since
+/// modification times can be as coarse as a second.
+fn wgsl_files(directory: &Path) -> Vec<(PathBuf, (SystemTime, u64))> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let extension = path.extension()?.to_str()?;
+
+            if !extension.eq_ignore_ascii_case("wgsl") {
+                return None;
+            }
+
+            let metadata = std::fs::metadata(&path).ok()?;
+            Some((path, (metadata.modified().ok()?, metadata.len())))
+        })
+        .collect()
+}
+
+/// Preprocesses a WGSL shader without defines and validates it with naga, like `build.rs`
+/// does for the built-in shaders. Reloaded shaders replace built-in ones, so they may not
+/// use optional capabilities either.
+pub fn validate_wgsl(source: &str) -> Result<(), String> {
+    let source = WgslPreprocessor::new().process(source).map_err(|err| err.to_string())?;
+    let source = source.as_str();
+    let module = naga::front::wgsl::parse_str(source).map_err(|err| err.emit_to_string(source))?;
+
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|err| err.emit_to_string(source))?;
+
+    Ok(())
+}
+
+/// Builds a pipeline from a reloaded shader, returning `None` and reporting the error if
+/// wgpu rejects it, for example because its bindings no longer match the layout.
+pub(crate) fn rebuild_pipeline<T>(
+    device: &wgpu::Device,
+    name: &str,
+    build: impl FnOnce() -> T,
+) -> Option<T> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let pipeline = build();
+
+    match pollster::block_on(device.pop_error_scope()) {
+        None => Some(pipeline),
+        Some(err) => {
+            println!("Keeping the previous pipeline for {}: {}", name, err);
+            None
+        },
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{graphics::screen_projection_matrix, GraphicsDevice};
+use crate::{
+    field_layout,
+    graphics::{
+        check_vertex_layout, hot_reload::rebuild_pipeline, screen_projection_matrix, Camera,
+    },
+    GraphicsDevice,
+};
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec2};
 use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, RenderPipeline};
@@ -121,11 +127,16 @@ struct BindGroups {
     vertex_uniform: wgpu::BindGroup,
 }
 
+const IMAGE_SHADER: &str = "image.wgsl";
+
 pub struct ImageDrawer {
     image_pipeline: RenderPipeline,
+    target_format: wgpu::TextureFormat,
+    shader_generation: u64,
     buffers: Buffers,
     bind_groups: BindGroups,
     projection: Mat4,
+    camera: Option<Mat4>,
 }
 
 impl ImageDrawer {
@@ -135,25 +146,78 @@ impl ImageDrawer {
         screen_width: u32,
         screen_height: u32,
     ) -> Self {
-        let image_pipeline = Self::build_pipeline(device, target_format);
+        let image_pipeline =
+            Self::build_pipeline(device, target_format, include_str!("shaders/wgsl/image.wgsl"));
         let buffers = Self::build_buffers(device);
         let bind_groups = Self::build_bind_groups(device, &image_pipeline, &buffers);
         let projection = screen_projection_matrix(screen_width, screen_height);
 
-        Self { image_pipeline, buffers, bind_groups, projection }
+        Self {
+            image_pipeline,
+            target_format,
+            shader_generation: 0,
+            buffers,
+            bind_groups,
+            projection,
+            camera: None,
+        }
     }
 
     pub fn resize(&mut self, screen_width: u32, screen_height: u32) {
         self.projection = screen_projection_matrix(screen_width, screen_height);
     }
 
-    pub fn begin(&mut self) -> ImageRecorder {
+    /// Draws in the world space of `camera`, usually a `Camera2D`, instead of pixels,
+    /// until `reset_camera`. Call it again whenever the camera moves.
+    pub fn set_camera<C: Camera>(&mut self, camera: &C) {
+        self.camera = Some(camera.matrix());
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera = None;
+    }
+
+    /// Rebuilds the pipeline if its shader changed since the last call, see `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        let Some(shader_watcher) = graphics_device.shader_watcher() else {
+            return;
+        };
+
+        let device = graphics_device.device();
+
+        if let Some(source) = shader_watcher.changed_since(IMAGE_SHADER, self.shader_generation) {
+            if let Some(pipeline) = rebuild_pipeline(device, IMAGE_SHADER, || {
+                Self::build_pipeline(device, self.target_format, source)
+            }) {
+                self.bind_groups = Self::build_bind_groups(device, &pipeline, &self.buffers);
+                self.image_pipeline = pipeline;
+            }
+        }
+
+        self.shader_generation = shader_watcher.generation();
+    }
+
+    /// Compares the structs uploaded to the GPU with the layouts in the shader.
+    pub(crate) fn layout_mismatches() -> Vec<String> {
+        check_vertex_layout(
+            IMAGE_SHADER,
+            "ImageQuadVertex",
+            std::mem::size_of::<ImageQuadVertex>(),
+            &ImageQuadVertex::ATTRIBUTES,
+            &[field_layout!(ImageQuadVertex, pos), field_layout!(ImageQuadVertex, uv)],
+        )
+    }
+
+    pub fn begin(&mut self) -> ImageRecorder<'_> {
         ImageRecorder { image_drawer: self, images: vec![] }
     }
 
-    fn build_pipeline(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> RenderPipeline {
-        let draw_shader =
-            GraphicsDevice::load_wgsl_shader(device, include_str!("shaders/wgsl/image.wgsl"));
+    fn build_pipeline(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        shader_source: &str,
+    ) -> RenderPipeline {
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
         let vertex_uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -212,10 +276,7 @@ impl ImageDrawer {
                 buffers: &[wgpu::VertexBufferLayout {
                     array_stride: std::mem::size_of::<ImageQuadVertex>() as u64,
                     step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![
-                        0 => Float32x2,
-                        1 => Float32x2,
-                    ],
+                    attributes: &ImageQuadVertex::ATTRIBUTES,
                 }],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
@@ -309,7 +370,9 @@ impl<'a> ImageRecorder<'a> {
         queue.write_buffer(
             &self.image_drawer.buffers.vertex_uniform,
             0,
-            bytemuck::cast_slice(self.image_drawer.projection.as_ref()),
+            bytemuck::cast_slice(
+                self.image_drawer.camera.unwrap_or(self.image_drawer.projection).as_ref(),
+            ),
         );
 
         render_pass.set_pipeline(&self.image_drawer.image_pipeline);
@@ -333,3 +396,10 @@ struct ImageQuadVertex {
     /// UV coordinates of the image.
     uv: [f32; 2],
 }
+
+impl ImageQuadVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+    ];
+}
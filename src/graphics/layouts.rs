@@ -0,0 +1,233 @@
+use crate::graphics::{text, DebugDrawer, ImageDrawer, LineDrawer, LineDrawer2d};
+
+/// The layout of a struct declared in a built-in shader, as laid out by naga.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructLayout {
+    pub name: &'static str,
+    /// The size of the struct including any trailing padding.
+    pub size: u32,
+    pub members: &'static [MemberLayout],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemberLayout {
+    pub name: &'static str,
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// A `@location` input of a vertex entry point in a built-in shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexInputLayout {
+    pub entry_point: &'static str,
+    pub name: &'static str,
+    pub location: u32,
+    /// The vertex format matching the input's type, `None` for types vertex buffers
+    /// can't provide.
+    pub format: Option<wgpu::VertexFormat>,
+}
+
+/// The structs and vertex inputs of a built-in shader, reflected by `build.rs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderLayout {
+    /// The shader's file name, like `glyph.wgsl`.
+    pub file: &'static str,
+    pub structs: &'static [StructLayout],
+    pub vertex_inputs: &'static [VertexInputLayout],
+}
+
+include!(concat!(env!("OUT_DIR"), "/shader_layouts.rs"));
+
+impl ShaderLayout {
+    /// The layout of the built-in shader named `file`.
+    pub fn find(file: &str) -> Option<&'static ShaderLayout> {
+        SHADER_LAYOUTS.iter().find(|layout| layout.file == file)
+    }
+
+    pub fn struct_layout(&self, name: &str) -> Option<&'static StructLayout> {
+        self.structs.iter().find(|layout| layout.name == name)
+    }
+
+    pub fn vertex_input(&self, location: u32) -> Option<&'static VertexInputLayout> {
+        self.vertex_inputs.iter().find(|input| input.location == location)
+    }
+}
+
+/// The offset and size of a field of a Rust struct, see `field_layout!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// The size of the field `access` returns, without needing a value of `T`.
+#[doc(hidden)]
+pub fn field_size<T, F>(_access: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+/// Describes a field of a `#[repr(C)]` struct as a `FieldLayout`.
+///
+/// ```
+/// # use simple_game::field_layout;
+/// #[repr(C)]
+/// struct Vertex {
+///     pos: [f32; 2],
+///     uv: [f32; 2],
+/// }
+///
+/// assert_eq!(field_layout!(Vertex, uv).offset, 8);
+/// ```
+#[macro_export]
+macro_rules! field_layout {
+    ($ty:ty, $field:ident) => {
+        $crate::graphics::FieldLayout {
+            name: stringify!($field),
+            offset: std::mem::offset_of!($ty, $field),
+            size: $crate::graphics::field_size(|value: &$ty| &value.$field),
+        }
+    };
+}
+
+/// Compares a Rust struct uploaded to a uniform or storage buffer with the struct
+/// named `wgsl_struct` in a built-in shader. Members are matched up in order.
+///
+/// Returns a description of every mismatch, empty if the layouts match.
+pub fn check_uniform_layout(
+    shader: &str,
+    wgsl_struct: &str,
+    rust_name: &str,
+    size: usize,
+    fields: &[FieldLayout],
+) -> Vec<String> {
+    let Some(layout) = ShaderLayout::find(shader) else {
+        return vec![format!("{}: Unknown shader", shader)];
+    };
+
+    let Some(wgsl) = layout.struct_layout(wgsl_struct) else {
+        return vec![format!("{}: No struct named {}", shader, wgsl_struct)];
+    };
+
+    let mut mismatches = Vec::new();
+
+    if wgsl.size as usize != size {
+        mismatches.push(format!(
+            "{}: {} is {} bytes, but {} is {} bytes",
+            shader, rust_name, size, wgsl_struct, wgsl.size
+        ));
+    }
+
+    if wgsl.members.len() != fields.len() {
+        mismatches.push(format!(
+            "{}: {} has {} fields, but {} has {} members",
+            shader,
+            rust_name,
+            fields.len(),
+            wgsl_struct,
+            wgsl.members.len()
+        ));
+    }
+
+    for (field, member) in fields.iter().zip(wgsl.members) {
+        if field.offset != member.offset as usize || field.size != member.size as usize {
+            mismatches.push(format!(
+                "{}: {}.{} is at offset {} with size {}, but {}.{} is at offset {} with size {}",
+                shader,
+                rust_name,
+                field.name,
+                field.offset,
+                field.size,
+                wgsl_struct,
+                member.name,
+                member.offset,
+                member.size
+            ));
+        }
+    }
+
+    mismatches
+}
+
+/// Compares a Rust vertex struct and the attributes describing it with the vertex inputs
+/// of a built-in shader. `fields` are the fields each attribute starts at, in the same
+/// order as `attributes`. An attribute may span several fields, like a `Float32x4` made
+/// of a `[f32; 2]` and two `f32`s.
+///
+/// Returns a description of every mismatch, empty if the layouts match.
+pub fn check_vertex_layout(
+    shader: &str,
+    rust_name: &str,
+    size: usize,
+    attributes: &[wgpu::VertexAttribute],
+    fields: &[FieldLayout],
+) -> Vec<String> {
+    let Some(layout) = ShaderLayout::find(shader) else {
+        return vec![format!("{}: Unknown shader", shader)];
+    };
+
+    let mut mismatches = Vec::new();
+
+    if attributes.len() != fields.len() {
+        mismatches.push(format!(
+            "{}: {} has {} attributes but {} fields were given",
+            shader,
+            rust_name,
+            attributes.len(),
+            fields.len()
+        ));
+    }
+
+    for (attribute, field) in attributes.iter().zip(fields) {
+        let location = attribute.shader_location;
+        let end = attribute.offset + attribute.format.size();
+
+        match layout.vertex_input(location) {
+            None => mismatches.push(format!(
+                "{}: {}.{} is bound to location {}, which isn't a vertex input",
+                shader, rust_name, field.name, location
+            )),
+            Some(input) if input.format != Some(attribute.format) => mismatches.push(format!(
+                "{}: {}.{} is a {:?}, but {} at location {} is a {:?}",
+                shader, rust_name, field.name, attribute.format, input.name, location, input.format
+            )),
+            Some(_) => {},
+        }
+
+        if attribute.offset as usize != field.offset {
+            mismatches.push(format!(
+                "{}: The attribute at location {} has offset {}, but {}.{} is at offset {}",
+                shader, location, attribute.offset, rust_name, field.name, field.offset
+            ));
+        }
+
+        if (attribute.format.size() as usize) < field.size {
+            mismatches.push(format!(
+                "{}: The {:?} at location {} doesn't cover all {} bytes of {}.{}",
+                shader, attribute.format, location, field.size, rust_name, field.name
+            ));
+        }
+
+        if end as usize > size {
+            mismatches.push(format!(
+                "{}: The attribute at location {} ends at byte {}, past the end of {}",
+                shader, location, end, rust_name
+            ));
+        }
+    }
+
+    mismatches
+}
+
+/// Checks that the Rust structs the built-in drawers upload to the GPU match the layouts
+/// their shaders expect. Returns a description of every mismatch.
+pub fn shader_layout_mismatches() -> Vec<String> {
+    [
+        LineDrawer::layout_mismatches(),
+        LineDrawer2d::layout_mismatches(),
+        DebugDrawer::layout_mismatches(),
+        ImageDrawer::layout_mismatches(),
+        text::layout_mismatches(),
+    ]
+    .concat()
+}
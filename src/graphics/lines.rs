@@ -1,4 +1,8 @@
-use crate::GraphicsDevice;
+use crate::{
+    field_layout,
+    graphics::{check_uniform_layout, check_vertex_layout, hot_reload::rebuild_pipeline, Camera},
+    GraphicsDevice,
+};
 use bytemuck::{Pod, Zeroable};
 use glam::{vec4, Mat4, Vec3, Vec4};
 use wgpu::util::DeviceExt;
@@ -14,8 +18,14 @@ struct BindGroups {
     vertex_uniform: wgpu::BindGroup,
 }
 
+const ROUND_LINE_STRIP_SHADER: &str = "round_line_strip.wgsl";
+
 pub struct LineDrawer {
     round_line_strip_pipeline: wgpu::RenderPipeline,
+    target_format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    depth_compare: wgpu::CompareFunction,
+    shader_generation: u64,
     buffers: Buffers,
     bind_groups: BindGroups,
     round_line_strips: Vec<LineVertex3>,
@@ -32,14 +42,43 @@ impl LineDrawer {
         screen_width: u32,
         screen_height: u32,
     ) -> Self {
-        let round_line_strip_pipeline =
-            Self::build_round_line_strip_pipeline(device, target_format, depth_format);
+        Self::new_with_depth_compare(
+            device,
+            target_format,
+            depth_format,
+            wgpu::CompareFunction::Less,
+            screen_width,
+            screen_height,
+        )
+    }
+
+    /// Tests depth with `depth_compare` instead of `Less`, for example
+    /// `Camera3D::depth_compare` of a camera using reverse-Z.
+    pub fn new_with_depth_compare(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        depth_compare: wgpu::CompareFunction,
+        screen_width: u32,
+        screen_height: u32,
+    ) -> Self {
+        let round_line_strip_pipeline = Self::build_round_line_strip_pipeline(
+            device,
+            target_format,
+            depth_format,
+            depth_compare,
+            include_str!("shaders/wgsl/round_line_strip.wgsl"),
+        );
 
         let buffers = Self::build_buffers(device);
         let bind_groups = Self::build_bind_groups(device, &round_line_strip_pipeline, &buffers);
 
         Self {
             round_line_strip_pipeline,
+            target_format,
+            depth_format,
+            depth_compare,
+            shader_generation: 0,
             buffers,
             bind_groups,
             round_line_strips: Vec::new(),
@@ -54,7 +93,60 @@ impl LineDrawer {
         self.screen_height = screen_height;
     }
 
-    pub fn begin(&mut self) -> LineRecorder {
+    /// Rebuilds the pipeline if its shader changed since the last call, see `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        let Some(shader_watcher) = graphics_device.shader_watcher() else {
+            return;
+        };
+
+        let device = graphics_device.device();
+
+        if let Some(source) =
+            shader_watcher.changed_since(ROUND_LINE_STRIP_SHADER, self.shader_generation)
+        {
+            if let Some(pipeline) = rebuild_pipeline(device, ROUND_LINE_STRIP_SHADER, || {
+                Self::build_round_line_strip_pipeline(
+                    device,
+                    self.target_format,
+                    self.depth_format,
+                    self.depth_compare,
+                    source,
+                )
+            }) {
+                self.bind_groups = Self::build_bind_groups(device, &pipeline, &self.buffers);
+                self.round_line_strip_pipeline = pipeline;
+            }
+        }
+
+        self.shader_generation = shader_watcher.generation();
+    }
+
+    /// Compares the structs uploaded to the GPU with the layouts in the shader.
+    pub(crate) fn layout_mismatches() -> Vec<String> {
+        [
+            check_uniform_layout(
+                ROUND_LINE_STRIP_SHADER,
+                "Globals",
+                "LineUniforms",
+                std::mem::size_of::<LineUniforms>(),
+                &[
+                    field_layout!(LineUniforms, proj),
+                    field_layout!(LineUniforms, transform),
+                    field_layout!(LineUniforms, resolution),
+                ],
+            ),
+            check_vertex_layout(
+                ROUND_LINE_STRIP_SHADER,
+                "RoundLineStripVertex",
+                std::mem::size_of::<RoundLineStripVertex>(),
+                &RoundLineStripVertex::ATTRIBUTES,
+                &[field_layout!(RoundLineStripVertex, pos)],
+            ),
+        ]
+        .concat()
+    }
+
+    pub fn begin(&mut self) -> LineRecorder<'_> {
         self.round_line_strips.clear();
         self.round_line_strip_indices.clear();
 
@@ -65,11 +157,16 @@ impl LineDrawer {
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
         depth_format: wgpu::TextureFormat,
+        depth_compare: wgpu::CompareFunction,
+        shader_source: &str,
     ) -> wgpu::RenderPipeline {
-        let draw_shader = GraphicsDevice::load_wgsl_shader(
-            device,
-            include_str!("shaders/wgsl/round_line_strip.wgsl"),
-        );
+        // The bias pulls lines towards the camera, which is towards 1.0 with reverse-Z.
+        let depth_bias = match depth_compare {
+            wgpu::CompareFunction::Greater | wgpu::CompareFunction::GreaterEqual => 50,
+            _ => -50,
+        };
+
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
         let vertex_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -105,9 +202,7 @@ impl LineDrawer {
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<RoundLineStripVertex>() as u64,
                         step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![
-                            0 => Float32x3, // XY position of this particular vertex, with Z indicating sides.
-                        ],
+                        attributes: &RoundLineStripVertex::ATTRIBUTES,
                     },
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<LineVertex3>() as u64,
@@ -148,10 +243,10 @@ impl LineDrawer {
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: depth_format,
                 depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
+                depth_compare,
                 stencil: wgpu::StencilState::default(),
                 // TODO(bschwind) - Allow configuration of depth bias.
-                bias: wgpu::DepthBiasState { constant: -50, slope_scale: 0.0, clamp: 0.0 },
+                bias: wgpu::DepthBiasState { constant: depth_bias, slope_scale: 0.0, clamp: 0.0 },
             }),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
@@ -262,6 +357,17 @@ impl LineRecorder<'_> {
         self.line_drawer.round_line_strip_indices.push(positions.len());
     }
 
+    /// Like `end`, with the matrix of `camera`, for example a `Camera3D`.
+    pub fn end_with_camera<C: Camera>(
+        self,
+        render_pass: &mut wgpu::RenderPass,
+        queue: &wgpu::Queue,
+        camera: &C,
+        transform: Mat4,
+    ) {
+        self.end(render_pass, queue, camera.matrix(), transform);
+    }
+
     pub fn end(
         self,
         render_pass: &mut wgpu::RenderPass,
@@ -356,3 +462,9 @@ struct RoundLineStripVertex {
     /// 1: The right part of the line segment.
     pos: [f32; 3],
 }
+
+impl RoundLineStripVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![
+        0 => Float32x3, // XY position of this particular vertex, with Z indicating sides.
+    ];
+}
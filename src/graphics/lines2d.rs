@@ -1,4 +1,10 @@
-use crate::{graphics::screen_projection_matrix, GraphicsDevice};
+use crate::{
+    field_layout,
+    graphics::{
+        check_vertex_layout, hot_reload::rebuild_pipeline, screen_projection_matrix, Camera,
+    },
+    GraphicsDevice,
+};
 use bytemuck::{Pod, Zeroable};
 use glam::{vec3, Mat4, Vec2, Vec3};
 use wgpu::util::DeviceExt;
@@ -14,13 +20,18 @@ struct BindGroups {
     vertex_uniform: wgpu::BindGroup,
 }
 
+const ROUND_LINE_STRIP_SHADER: &str = "round_line_strip2d.wgsl";
+
 pub struct LineDrawer2d {
     round_line_strip_pipeline: wgpu::RenderPipeline,
+    target_format: wgpu::TextureFormat,
+    shader_generation: u64,
     buffers: Buffers,
     bind_groups: BindGroups,
     round_line_strips: Vec<LineVertex>,
     round_line_strip_indices: Vec<usize>,
     projection: Mat4,
+    camera: Option<Mat4>,
 }
 
 impl LineDrawer2d {
@@ -30,8 +41,11 @@ impl LineDrawer2d {
         screen_width: u32,
         screen_height: u32,
     ) -> Self {
-        let round_line_strip_pipeline =
-            Self::build_round_line_strip_pipeline(device, target_format);
+        let round_line_strip_pipeline = Self::build_round_line_strip_pipeline(
+            device,
+            target_format,
+            include_str!("shaders/wgsl/round_line_strip2d.wgsl"),
+        );
 
         let buffers = Self::build_buffers(device);
         let bind_groups = Self::build_bind_groups(device, &round_line_strip_pipeline, &buffers);
@@ -39,11 +53,14 @@ impl LineDrawer2d {
 
         Self {
             round_line_strip_pipeline,
+            target_format,
+            shader_generation: 0,
             buffers,
             bind_groups,
             round_line_strips: Vec::new(),
             round_line_strip_indices: Vec::new(),
             projection,
+            camera: None,
         }
     }
 
@@ -51,7 +68,50 @@ impl LineDrawer2d {
         self.projection = screen_projection_matrix(screen_width, screen_height);
     }
 
-    pub fn begin(&mut self) -> Line2dRecorder {
+    /// Draws in the world space of `camera`, usually a `Camera2D`, instead of pixels,
+    /// until `reset_camera`. Call it again whenever the camera moves.
+    pub fn set_camera<C: Camera>(&mut self, camera: &C) {
+        self.camera = Some(camera.matrix());
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera = None;
+    }
+
+    /// Rebuilds the pipeline if its shader changed since the last call, see `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        let Some(shader_watcher) = graphics_device.shader_watcher() else {
+            return;
+        };
+
+        let device = graphics_device.device();
+
+        if let Some(source) =
+            shader_watcher.changed_since(ROUND_LINE_STRIP_SHADER, self.shader_generation)
+        {
+            if let Some(pipeline) = rebuild_pipeline(device, ROUND_LINE_STRIP_SHADER, || {
+                Self::build_round_line_strip_pipeline(device, self.target_format, source)
+            }) {
+                self.bind_groups = Self::build_bind_groups(device, &pipeline, &self.buffers);
+                self.round_line_strip_pipeline = pipeline;
+            }
+        }
+
+        self.shader_generation = shader_watcher.generation();
+    }
+
+    /// Compares the structs uploaded to the GPU with the layouts in the shader.
+    pub(crate) fn layout_mismatches() -> Vec<String> {
+        check_vertex_layout(
+            ROUND_LINE_STRIP_SHADER,
+            "RoundLineStripVertex",
+            std::mem::size_of::<RoundLineStripVertex>(),
+            &RoundLineStripVertex::ATTRIBUTES,
+            &[field_layout!(RoundLineStripVertex, pos)],
+        )
+    }
+
+    pub fn begin(&mut self) -> Line2dRecorder<'_> {
         self.round_line_strips.clear();
         self.round_line_strip_indices.clear();
 
@@ -61,11 +121,9 @@ impl LineDrawer2d {
     fn build_round_line_strip_pipeline(
         device: &wgpu::Device,
         target_format: wgpu::TextureFormat,
+        shader_source: &str,
     ) -> wgpu::RenderPipeline {
-        let draw_shader = GraphicsDevice::load_wgsl_shader(
-            device,
-            include_str!("shaders/wgsl/round_line_strip2d.wgsl"),
-        );
+        let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
         let vertex_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -99,9 +157,7 @@ impl LineDrawer2d {
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<RoundLineStripVertex>() as u64,
                         step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![
-                            0 => Float32x3, // XY position of this particular vertex, with Z indicating sides.
-                        ],
+                        attributes: &RoundLineStripVertex::ATTRIBUTES,
                     },
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<LineVertex>() as u64,
@@ -258,7 +314,9 @@ impl Line2dRecorder<'_> {
         queue.write_buffer(
             &self.line_drawer.buffers.vertex_uniform,
             0,
-            bytemuck::cast_slice(self.line_drawer.projection.as_ref()),
+            bytemuck::cast_slice(
+                self.line_drawer.camera.unwrap_or(self.line_drawer.projection).as_ref(),
+            ),
         );
 
         render_pass.push_debug_group("Line drawer");
@@ -317,3 +375,9 @@ struct RoundLineStripVertex {
     /// 1: The right part of the line segment.
     pos: [f32; 3],
 }
+
+impl RoundLineStripVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![
+        0 => Float32x3, // XY position of this particular vertex, with Z indicating sides.
+    ];
+}
@@ -1,35 +1,70 @@
+use crate::{Error, GameConfig};
+use capture::{copy_texture_to_buffer, CaptureTarget, FrameCapture};
 use glam::Mat4;
+use std::{path::PathBuf, sync::Arc};
 use wgpu::{
     Adapter, Backends, CommandEncoder, CompositeAlphaMode, Device, Instance, InstanceDescriptor,
-    Queue, ShaderModuleDescriptor, Surface, SurfaceConfiguration, SurfaceTexture, TextureFormat,
-    TextureView,
+    Queue, ShaderModuleDescriptor, Surface, SurfaceConfiguration, SurfaceTexture, Texture,
+    TextureFormat, TextureView,
 };
-use winit::dpi::PhysicalSize;
+use winit::{dpi::PhysicalSize, window::Window};
 
+mod camera;
+mod camera2d;
+mod camera3d;
+mod capture;
 mod debug_drawer;
 mod fullscreen_quad;
+mod hot_reload;
 mod image;
+mod layouts;
 mod lines;
 mod lines2d;
+mod preprocessor;
+mod profiler;
+mod recording;
 pub mod text;
 mod textured_quad;
 
+pub use camera::*;
+pub use camera2d::*;
+pub use camera3d::*;
+pub use capture::screenshot_path;
 pub use debug_drawer::*;
 pub use fullscreen_quad::*;
+pub use hot_reload::*;
 pub use image::*;
+pub use layouts::*;
 pub use lines::*;
 pub use lines2d::*;
+pub use preprocessor::*;
+pub use profiler::*;
+pub(crate) use recording::FrameRecorder;
+pub use recording::RecordingMode;
+
+enum RenderTarget {
+    Surface {
+        surface: Surface<'static>,
+        window: Arc<Window>,
+    },
+    /// Used by headless devices, there is no window to present to.
+    Offscreen(Texture),
+}
 
-pub struct GraphicsDevice<'a> {
+pub struct GraphicsDevice {
     adapter: Adapter,
     device: Device,
     queue: Queue,
-    surface: Surface<'a>,
+    render_target: RenderTarget,
     surface_config: SurfaceConfiguration,
+    profiler: Option<GpuProfiler>,
+    shader_watcher: Option<ShaderWatcher>,
+    frame_capture: FrameCapture,
+    frames_ended: u64,
 }
 
-impl<'a> GraphicsDevice<'a> {
-    pub async fn new(window: &'a winit::window::Window) -> Self {
+impl GraphicsDevice {
+    pub async fn new(window: Arc<Window>, config: &GameConfig) -> Result<Self, Error> {
         let size = window.inner_size();
 
         // PRIMARY: All the apis that wgpu offers first tier of support for (Vulkan + Metal + DX12 + Browser WebGPU).
@@ -37,40 +72,55 @@ impl<'a> GraphicsDevice<'a> {
             backends: Backends::PRIMARY,
             ..Default::default()
         });
-        let surface = instance.create_surface(window).expect("Failed to create a surface");
+        let surface = instance.create_surface(window.clone())?;
         let swapchain_format = wgpu::TextureFormat::Bgra8Unorm;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                // Prefer low power when on battery, high performance when on mains.
-                power_preference: wgpu::PowerPreference::default(),
+                power_preference: config.power_preference,
                 // Indicates that only a fallback adapter can be returned.
                 force_fallback_adapter: false,
                 // Request an adapter which can render to our surface
                 compatible_surface: Some(&surface),
             })
             .await
-            .expect("Failed to find an appropiate adapter");
+            .ok_or(Error::NoSuitableAdapter)?;
+
+        let mut required_features = config.required_features;
+
+        if config.gpu_profiler {
+            required_features |= adapter.features() & GpuProfiler::FEATURES;
+        }
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
+                    required_features,
+                    required_limits: config.required_limits.clone(),
                     memory_hints: wgpu::MemoryHints::default(),
                 },
                 None,
             )
-            .await
-            .expect("Failed to create device");
+            .await?;
+
+        let present_mode =
+            if surface.get_capabilities(&adapter).present_modes.contains(&config.present_mode) {
+                config.present_mode
+            } else {
+                wgpu::PresentMode::Fifo
+            };
+
+        // Frames can only be captured if the surface can be copied from.
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface.get_capabilities(&adapter).usages & wgpu::TextureUsages::COPY_SRC);
 
         let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: swapchain_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode,
             alpha_mode: CompositeAlphaMode::Auto,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -78,38 +128,214 @@ impl<'a> GraphicsDevice<'a> {
 
         surface.configure(&device, &surface_config);
 
-        Self { adapter, device, queue, surface, surface_config }
+        let profiler = config.gpu_profiler.then(|| GpuProfiler::new(&device, &queue));
+
+        let shader_watcher = (cfg!(debug_assertions) && config.shader_hot_reload).then(|| {
+            let mut shader_watcher = ShaderWatcher::new();
+            shader_watcher.watch(BUILTIN_SHADER_DIR);
+
+            for directory in &config.shader_dirs {
+                shader_watcher.watch(directory);
+            }
+
+            shader_watcher
+        });
+
+        Ok(Self {
+            adapter,
+            device,
+            queue,
+            render_target: RenderTarget::Surface { surface, window },
+            surface_config,
+            profiler,
+            shader_watcher,
+            frame_capture: FrameCapture::new(),
+            frames_ended: 0,
+        })
+    }
+
+    /// Creates a device which renders into an offscreen color texture instead of a window
+    /// surface, using the software fallback adapter. Useful for running on machines with no
+    /// display, such as CI. Timestamp queries are enabled if the adapter supports them, so
+    /// `enable_profiler` can report GPU timings.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        let instance =
+            Instance::new(&InstanceDescriptor { backends: Backends::all(), ..Default::default() });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: true,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or(Error::NoSuitableAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: adapter.features() & GpuProfiler::FEATURES,
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::default(),
+                },
+                None,
+            )
+            .await?;
+
+        // Nothing is ever configured with this, but it keeps track of the
+        // offscreen texture's size and format the same way a surface would.
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8Unorm,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let texture = Self::build_offscreen_texture(&device, &surface_config);
+
+        Ok(Self {
+            adapter,
+            device,
+            queue,
+            render_target: RenderTarget::Offscreen(texture),
+            surface_config,
+            profiler: None,
+            shader_watcher: None,
+            frame_capture: FrameCapture::new(),
+            frames_ended: 0,
+        })
+    }
+
+    fn build_offscreen_texture(device: &Device, surface_config: &SurfaceConfiguration) -> Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen backbuffer"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
     }
 
+    /// Loads a WGSL shader after running it through a `WgslPreprocessor` with the
+    /// built-in includes and no defines. Meant for the built-in shaders, which `build.rs`
+    /// already preprocessed, so this panics if preprocessing fails. Use
+    /// `load_wgsl_shader_with` to handle the error instead.
     pub fn load_wgsl_shader(device: &Device, shader_src: &str) -> wgpu::ShaderModule {
-        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        match Self::load_wgsl_shader_with(device, shader_src, &WgslPreprocessor::new()) {
+            Ok(shader_module) => shader_module,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Loads a WGSL shader after running it through `preprocessor`, for building a
+    /// variant of a shader with defines or extra includes. Errors in the preprocessed
+    /// source itself are reported by wgpu like for any other shader.
+    pub fn load_wgsl_shader_with(
+        device: &Device,
+        shader_src: &str,
+        preprocessor: &WgslPreprocessor,
+    ) -> Result<wgpu::ShaderModule, Error> {
+        let source = preprocessor.process(shader_src)?;
+
+        Ok(device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(shader_src)),
-        })
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Owned(source)),
+        }))
     }
 
     pub fn load_spirv_shader(&self, shader_module: ShaderModuleDescriptor) -> wgpu::ShaderModule {
         self.device.create_shader_module(shader_module)
     }
 
-    pub fn begin_frame(&mut self) -> FrameEncoder {
-        let frame =
-            self.surface.get_current_texture().expect("Failed to acquire next swap chain texture");
+    /// Returns `Ok(None)` when there is no frame to render to right now, for example
+    /// while the window is minimized or when acquiring the next frame timed out.
+    /// The caller should skip rendering for this frame.
+    pub fn begin_frame(&mut self) -> Result<Option<FrameEncoder>, Error> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.new_frame(&self.device, &self.queue);
+        }
+
+        if let Some(shader_watcher) = &mut self.shader_watcher {
+            shader_watcher.poll();
+        }
+
+        self.frame_capture.poll(&self.device);
+
+        let frame = match &self.render_target {
+            RenderTarget::Surface { surface, .. } => {
+                if self.surface_config.width == 0 || self.surface_config.height == 0 {
+                    return Ok(None);
+                }
+
+                let surface_texture = match surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        // Reconfigure and try once more, if that fails too we'll skip
+                        // the frame and try again next time.
+                        surface.configure(&self.device, &self.surface_config);
+
+                        match surface.get_current_texture() {
+                            Ok(surface_texture) => surface_texture,
+                            Err(wgpu::SurfaceError::OutOfMemory) => {
+                                return Err(wgpu::SurfaceError::OutOfMemory.into())
+                            },
+                            Err(_) => return Ok(None),
+                        }
+                    },
+                    Err(wgpu::SurfaceError::Timeout | wgpu::SurfaceError::Other) => {
+                        return Ok(None)
+                    },
+                    Err(err @ wgpu::SurfaceError::OutOfMemory) => return Err(err.into()),
+                };
+
+                FrameTexture::Surface(surface_texture)
+            },
+            RenderTarget::Offscreen(texture) => FrameTexture::Offscreen(texture.clone()),
+        };
 
-        let backbuffer_view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let backbuffer_view = frame.texture().create_view(&wgpu::TextureViewDescriptor::default());
 
         let encoder =
             self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
         let surface_dimensions = self.surface_dimensions();
 
-        FrameEncoder { frame, backbuffer_view, encoder, surface_dimensions }
+        Ok(Some(FrameEncoder { frame, backbuffer_view, encoder, surface_dimensions }))
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.surface_config.width = new_size.width;
         self.surface_config.height = new_size.height;
-        self.surface.configure(&self.device, &self.surface_config);
+
+        // Windows report a size of 0x0 while minimized, which can't be configured.
+        // begin_frame() skips frames until we get a real size again.
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
+
+        match &mut self.render_target {
+            RenderTarget::Surface { surface, .. } => {
+                surface.configure(&self.device, &self.surface_config)
+            },
+            RenderTarget::Offscreen(texture) => {
+                *texture = Self::build_offscreen_texture(&self.device, &self.surface_config);
+            },
+        }
     }
 
     pub fn surface_dimensions(&self) -> (u32, u32) {
@@ -135,13 +361,231 @@ impl<'a> GraphicsDevice<'a> {
     pub fn surface_texture_format(&self) -> TextureFormat {
         self.surface_config.format
     }
+
+    /// Starts profiling frames, with GPU timings if the device was created with the
+    /// features in `GpuProfiler::FEATURES`. See `GameConfig::gpu_profiler`.
+    pub fn enable_profiler(&mut self) {
+        if self.profiler.is_none() {
+            self.profiler = Some(GpuProfiler::new(&self.device, &self.queue));
+        }
+    }
+
+    pub fn profiler(&self) -> Option<&GpuProfiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Opens a named profiler scope, does nothing if profiling isn't enabled.
+    pub fn begin_profiler_scope(&mut self, frame_encoder: &mut FrameEncoder, name: &str) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_scope(&mut frame_encoder.encoder, name);
+        }
+    }
+
+    /// Closes the most recently opened profiler scope.
+    pub fn end_profiler_scope(&mut self, frame_encoder: &mut FrameEncoder) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_scope(&mut frame_encoder.encoder);
+        }
+    }
+
+    /// Starts watching `directory` for shader changes, which drawers pick up in their
+    /// `reload_shaders` methods. See `GameConfig::shader_hot_reload`.
+    pub fn watch_shader_dir<P: Into<PathBuf>>(&mut self, directory: P) {
+        self.shader_watcher.get_or_insert_with(ShaderWatcher::new).watch(directory);
+    }
+
+    pub fn shader_watcher(&self) -> Option<&ShaderWatcher> {
+        self.shader_watcher.as_ref()
+    }
+
+    /// Checks the watched shader directories for changes right away instead of waiting
+    /// for the next `begin_frame`. Returns true if any shader was reloaded.
+    pub fn poll_shaders(&mut self) -> bool {
+        self.shader_watcher.as_mut().is_some_and(ShaderWatcher::scan)
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self.render_target, RenderTarget::Offscreen(_))
+    }
+
+    /// The window being rendered to, or `None` for headless devices.
+    pub fn window(&self) -> Option<&Window> {
+        match &self.render_target {
+            RenderTarget::Surface { window, .. } => Some(window.as_ref()),
+            RenderTarget::Offscreen(_) => None,
+        }
+    }
+
+    /// The texture headless devices render into, or `None` when rendering to a window.
+    pub fn offscreen_texture(&self) -> Option<&Texture> {
+        match &self.render_target {
+            RenderTarget::Surface { .. } => None,
+            RenderTarget::Offscreen(texture) => Some(texture),
+        }
+    }
+
+    /// Copies the contents of `texture` back to the CPU as tightly packed RGBA8 pixels.
+    /// This blocks until the GPU has finished all submitted work, so it's meant for
+    /// tests and tooling rather than every frame. Use `capture_frame` to save frames
+    /// while the game is running.
+    pub fn read_texture_rgba(&self, texture: &Texture) -> Vec<u8> {
+        let mut encoder =
+            self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        let (buffer, layout) = copy_texture_to_buffer(&self.device, &mut encoder, texture)
+            .unwrap_or_else(|err| panic!("Unsupported texture for readback: {}", err));
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = buffer.slice(..);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map texture readback buffer")
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let pixels = layout.to_rgba(&buffer_slice.get_mapped_range());
+        buffer.unmap();
+
+        pixels
+    }
+
+    /// Copies the frame into a buffer as part of `frame_encoder`, and saves it as a PNG
+    /// file at `path` once the frame has been submitted and the GPU has finished it.
+    /// The readback is checked at the start of later frames and the file is written on
+    /// a separate thread, so this doesn't stall rendering. Call it after recording the
+    /// frame's render passes.
+    ///
+    /// Fails if the surface doesn't support copies or has a format other than 8-bit
+    /// RGBA or BGRA.
+    pub fn capture_frame<P: Into<PathBuf>>(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        path: P,
+    ) -> Result<(), Error> {
+        self.frame_capture.capture(
+            &self.device,
+            &mut frame_encoder.encoder,
+            frame_encoder.frame.texture(),
+            CaptureTarget::File(path.into()),
+        )
+    }
+
+    /// Captures the next frame finished with `end_frame` to `path`. This is what the
+    /// screenshot hotkey does, see `GameConfig::screenshot_key`.
+    ///
+    /// Only `end_frame` services the request. Frames submitted some other way, like
+    /// `GoldenTest::run` does, leave it pending until the next `end_frame`. Call
+    /// `capture_frame` directly to capture one of those.
+    pub fn request_screenshot<P: Into<PathBuf>>(&mut self, path: P) {
+        self.frame_capture.request(path.into());
+    }
+
+    /// Submits the frame and presents it, capturing it first if a screenshot was
+    /// requested with `request_screenshot` or a recording is active.
+    pub fn end_frame(&mut self, mut frame_encoder: FrameEncoder) {
+        let recording = self.frame_capture.recording().is_some();
+
+        if recording {
+            if let Err(err) = self.frame_capture.capture(
+                &self.device,
+                &mut frame_encoder.encoder,
+                frame_encoder.frame.texture(),
+                CaptureTarget::Recording,
+            ) {
+                println!("Failed to record frame: {}", err);
+            }
+        }
+
+        if let Some(path) = self.frame_capture.take_request() {
+            if let Err(err) = self.capture_frame(&mut frame_encoder, path) {
+                println!("Failed to capture screenshot: {}", err);
+            }
+        }
+
+        self.queue.submit(Some(frame_encoder.encoder.finish()));
+        frame_encoder.frame.present();
+        self.frames_ended += 1;
+        self.frame_capture.poll(&self.device);
+
+        if recording {
+            self.frame_capture.throttle(&self.device);
+        }
+    }
+
+    /// Starts recording every frame finished with `end_frame`. The game loop advances
+    /// by the recording's fixed frame time instead of the real time between frames
+    /// while a recording is active, see `GameConfig::record_frames`.
+    pub fn start_recording(&mut self, mode: RecordingMode) -> Result<(), Error> {
+        let size = self.surface_dimensions();
+        self.frame_capture.start_recording(mode, size)
+    }
+
+    /// Waits for the recorded frames to be written and stops the recording. For piped
+    /// recordings this also waits for the encoder process to exit.
+    pub fn stop_recording(&mut self) -> Result<(), Error> {
+        self.frame_capture.stop_recording(&self.device)
+    }
+
+    /// Takes the active recording off this device without stopping it, once the frames
+    /// in flight have been read back. Used before the device is dropped on suspend, the
+    /// recording continues on the next device with `resume_recording`.
+    pub(crate) fn suspend_recording(&mut self) -> Option<FrameRecorder> {
+        self.frame_capture.suspend_recording(&self.device)
+    }
+
+    pub(crate) fn resume_recording(&mut self, recorder: FrameRecorder) -> Result<(), Error> {
+        self.frame_capture.resume_recording(recorder)
+    }
+
+    /// The active recording, if any.
+    pub fn recording(&self) -> Option<&RecordingMode> {
+        self.frame_capture.recording()
+    }
+
+    /// How many frames were finished with `end_frame` so far.
+    pub fn frames_ended(&self) -> u64 {
+        self.frames_ended
+    }
+
+    /// The number of captured frames which haven't been written to disk yet.
+    pub fn pending_captures(&self) -> usize {
+        self.frame_capture.pending_count()
+    }
+
+    /// Blocks until every captured frame has been written to disk. Frames passed to
+    /// `capture_frame` must have been submitted first. Returns the first error, if any.
+    pub fn finish_captures(&mut self) -> Result<(), Error> {
+        self.frame_capture.finish(&self.device)
+    }
+}
+
+/// The texture a frame is rendered into.
+pub enum FrameTexture {
+    Surface(SurfaceTexture),
+    Offscreen(Texture),
+}
+
+impl FrameTexture {
+    pub fn texture(&self) -> &Texture {
+        match self {
+            FrameTexture::Surface(surface_texture) => &surface_texture.texture,
+            FrameTexture::Offscreen(texture) => texture,
+        }
+    }
+
+    /// Presents the frame to the window. Does nothing for offscreen frames.
+    pub fn present(self) {
+        if let FrameTexture::Surface(surface_texture) = self {
+            surface_texture.present();
+        }
+    }
 }
 
 pub struct FrameEncoder {
     // The `backbuffer_view` field must be listed before the `frame` field.
     // https://github.com/gfx-rs/wgpu/issues/1797
     pub backbuffer_view: TextureView,
-    pub frame: SurfaceTexture,
+    pub frame: FrameTexture,
     pub encoder: CommandEncoder,
     surface_dimensions: (u32, u32),
 }
@@ -0,0 +1,328 @@
+// This module is also compiled into build.rs, so it may only use the standard library.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+/// Shaders other shaders can `#include` without registering them first.
+const BUILTIN_INCLUDES: &[(&str, &str)] =
+    &[("common.wgsl", include_str!("shaders/wgsl/common.wgsl"))];
+
+/// Marks where an included file starts and ends in expanded sources. The included file
+/// is skipped if it was already included.
+const INCLUDE_BEGIN: &str = "include_begin";
+const INCLUDE_END: &str = "include_end";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessError {
+    /// The included file the error is in, or `None` for the shader itself.
+    pub file: Option<String>,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}, line {}: {}", file, self.line, self.message),
+            None => write!(f, "Line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// A small C-style preprocessor for WGSL, run by `build.rs` before validating the
+/// built-in shaders and by `GraphicsDevice::load_wgsl_shader` when loading them.
+///
+/// Supported directives, each on its own line:
+///
+/// ```text
+/// #include "common.wgsl"  // Pastes in a registered include, at most once per shader.
+/// #define NAME            // Defines a flag for #ifdef.
+/// #define NAME value      // Replaces NAME with value from here on.
+/// #undef NAME
+/// #ifdef NAME / #ifndef NAME / #else / #endif
+/// ```
+///
+/// Defines set with `define` are injected before the first line, so one source can be
+/// built into variants, for example with and without a depth buffer. Sources should
+/// still be valid without any defines, since that's the variant which gets validated.
+#[derive(Debug, Clone)]
+pub struct WgslPreprocessor {
+    includes: HashMap<String, String>,
+    defines: BTreeMap<String, String>,
+}
+
+impl Default for WgslPreprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WgslPreprocessor {
+    /// A preprocessor with the built-in includes and no defines.
+    pub fn new() -> Self {
+        let includes = BUILTIN_INCLUDES
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect();
+
+        Self { includes, defines: BTreeMap::new() }
+    }
+
+    /// Registers a file for `#include "name"`, replacing any include with the same name.
+    pub fn include<N: Into<String>, S: Into<String>>(mut self, name: N, source: S) -> Self {
+        self.includes.insert(name.into(), source.into());
+        self
+    }
+
+    /// Defines `name` as `value`, which replaces `name` wherever it appears as a word.
+    pub fn define<N: Into<String>, V: ToString>(mut self, name: N, value: V) -> Self {
+        self.defines.insert(name.into(), value.to_string());
+        self
+    }
+
+    /// Defines `name` without a value, for `#ifdef`.
+    pub fn flag<N: Into<String>>(self, name: N) -> Self {
+        self.define(name, "")
+    }
+
+    pub fn defines(&self) -> &BTreeMap<String, String> {
+        &self.defines
+    }
+
+    /// Preprocesses `source` into plain WGSL. Lines taken up by directives or skipped by
+    /// conditionals are left empty, so line numbers in shader errors still match up for
+    /// shaders without includes.
+    pub fn process(&self, source: &str) -> Result<String, PreprocessError> {
+        let expanded = self.expand_includes(source)?;
+        self.evaluate(&expanded)
+    }
+
+    /// Pastes in the included files without evaluating any other directives, so the
+    /// result can still be processed with different defines.
+    pub fn expand_includes(&self, source: &str) -> Result<String, PreprocessError> {
+        let mut output = String::with_capacity(source.len());
+        self.expand(None, source, &mut Vec::new(), &mut output)?;
+        Ok(output)
+    }
+
+    fn expand(
+        &self,
+        file: Option<&str>,
+        source: &str,
+        stack: &mut Vec<String>,
+        output: &mut String,
+    ) -> Result<(), PreprocessError> {
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| PreprocessError {
+                file: file.map(str::to_string),
+                line: index + 1,
+                message,
+            };
+
+            match parse_directive(line) {
+                Some(("include", argument)) => {
+                    let name = parse_include_name(argument).ok_or_else(|| {
+                        error(format!("Expected a quoted file name, found '{}'", argument))
+                    })?;
+
+                    let included = self
+                        .includes
+                        .get(name)
+                        .ok_or_else(|| error(format!("Unknown include '{}'", name)))?;
+
+                    if stack.iter().any(|file| file == name) {
+                        return Err(error(format!("'{}' includes itself", name)));
+                    }
+
+                    output.push_str(&format!("#{} \"{}\"\n", INCLUDE_BEGIN, name));
+                    stack.push(name.to_string());
+                    self.expand(Some(name), included, stack, output)?;
+                    stack.pop();
+                    output.push_str(&format!("#{}\n", INCLUDE_END));
+                },
+                _ => {
+                    output.push_str(line);
+                    output.push('\n');
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn evaluate(&self, source: &str) -> Result<String, PreprocessError> {
+        enum Block {
+            Conditional { parent_active: bool, taken: bool, seen_else: bool },
+            Include { parent_active: bool },
+        }
+
+        let mut output = String::with_capacity(source.len());
+        let mut defines = self.defines.clone();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut included: HashSet<String> = HashSet::new();
+        let mut active = true;
+
+        // The file and line each line came from, for error messages.
+        let mut files: Vec<(Option<String>, usize)> = vec![(None, 0)];
+
+        for line in source.lines() {
+            let directive = parse_directive(line);
+
+            if !matches!(directive, Some((INCLUDE_END, _))) {
+                files.last_mut().unwrap().1 += 1;
+            }
+
+            let (file, line_number) = files.last().unwrap().clone();
+            let error = |message: String| PreprocessError { file, line: line_number, message };
+
+            let Some((name, argument)) = directive else {
+                if active {
+                    output.push_str(&substitute(line, &defines));
+                }
+
+                output.push('\n');
+                continue;
+            };
+
+            match name {
+                INCLUDE_BEGIN => {
+                    let file = parse_include_name(argument).unwrap_or(argument).to_string();
+                    blocks.push(Block::Include { parent_active: active });
+                    active = active && included.insert(file.clone());
+                    files.push((Some(file), 0));
+                    continue;
+                },
+                INCLUDE_END => {
+                    files.pop();
+
+                    match blocks.pop() {
+                        Some(Block::Include { parent_active }) => active = parent_active,
+                        _ => return Err(error("Unterminated #ifdef or #ifndef".to_string())),
+                    }
+
+                    continue;
+                },
+                "define" if active => {
+                    let (define, value) = match argument.split_once(char::is_whitespace) {
+                        Some((define, value)) => (define, value.trim()),
+                        None => (argument, ""),
+                    };
+
+                    if !is_identifier(define) {
+                        return Err(error(format!("Invalid name '{}' in #define", define)));
+                    }
+
+                    defines.insert(define.to_string(), value.to_string());
+                },
+                "undef" if active => {
+                    defines.remove(argument);
+                },
+                "define" | "undef" => {},
+                "ifdef" | "ifndef" => {
+                    if !is_identifier(argument) {
+                        return Err(error(format!("Invalid name '{}' in #{}", argument, name)));
+                    }
+
+                    let taken = defines.contains_key(argument) == (name == "ifdef");
+                    blocks.push(Block::Conditional {
+                        parent_active: active,
+                        taken,
+                        seen_else: false,
+                    });
+                    active = active && taken;
+                },
+                "else" => match blocks.last_mut() {
+                    Some(Block::Conditional {
+                        parent_active,
+                        taken,
+                        seen_else: seen_else @ false,
+                    }) => {
+                        *seen_else = true;
+                        active = *parent_active && !*taken;
+                    },
+                    Some(Block::Conditional { .. }) => {
+                        return Err(error("More than one #else in the same block".to_string()))
+                    },
+                    _ => return Err(error("#else without #ifdef or #ifndef".to_string())),
+                },
+                "endif" => match blocks.last() {
+                    Some(Block::Conditional { parent_active, .. }) => {
+                        active = *parent_active;
+                        blocks.pop();
+                    },
+                    _ => return Err(error("#endif without #ifdef or #ifndef".to_string())),
+                },
+                _ => return Err(error(format!("Unknown directive #{}", name))),
+            }
+
+            output.push('\n');
+        }
+
+        if !blocks.is_empty() {
+            let (file, line) = files.last().unwrap().clone();
+
+            return Err(PreprocessError {
+                file,
+                line,
+                message: "Unterminated #ifdef or #ifndef".to_string(),
+            });
+        }
+
+        Ok(output)
+    }
+}
+
+/// Splits a directive line like `#define NAME value` into `("define", "NAME value")`.
+fn parse_directive(line: &str) -> Option<(&str, &str)> {
+    let directive = line.trim().strip_prefix('#')?;
+
+    Some(match directive.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (directive, ""),
+    })
+}
+
+fn parse_include_name(argument: &str) -> Option<&str> {
+    argument.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces every word in `line` which is defined with a value.
+fn substitute(line: &str, defines: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        // Skip over numbers like 1e5 or 0x1f instead of treating their tails as words.
+        let word_start = rest[..start]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+            .map_or(0, |index| index + 1);
+
+        let end = rest[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(rest.len(), |index| start + index);
+
+        let word = &rest[start..end];
+        output.push_str(&rest[..start]);
+
+        match defines.get(word) {
+            Some(value) if word_start == start && !value.is_empty() => output.push_str(value),
+            _ => output.push_str(word),
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
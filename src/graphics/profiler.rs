@@ -0,0 +1,348 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use wgpu::{Buffer, CommandEncoder, Device, QuerySet, Queue};
+
+/// Each scope uses two timestamp queries, one at the start and one at the end.
+const MAX_SCOPES_PER_FRAME: u32 = 128;
+const MAX_QUERIES: u32 = MAX_SCOPES_PER_FRAME * 2;
+
+/// How many frames of timestamps can wait for their readback at once.
+const FRAMES_IN_FLIGHT: usize = 4;
+
+/// The timing of a named scope in the most recent profiled frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeTiming {
+    pub name: String,
+    /// How many scopes this one is nested in.
+    pub depth: usize,
+    /// Time between `begin_scope` and `end_scope` on the CPU, which is the time spent
+    /// recording commands rather than executing them.
+    pub cpu_ms: f64,
+    /// Time the GPU spent executing the scope's commands, if timestamp queries are
+    /// supported.
+    pub gpu_ms: Option<f64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TimestampWrites {
+    None,
+    /// Timestamps are written by empty compute passes, which works with just
+    /// `Features::TIMESTAMP_QUERY`.
+    Passes,
+    Encoder,
+}
+
+struct Scope {
+    name: String,
+    depth: usize,
+    cpu_start: Instant,
+    cpu_end: Option<Instant>,
+    /// Index of the start query, the end query follows it.
+    query_index: Option<u32>,
+}
+
+/// Query set and buffers for one frame's timestamps.
+struct QuerySlot {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    scopes: Vec<Scope>,
+    query_count: u32,
+    /// Set by the `map_async` callback, `Some(true)` once the readback buffer is mapped.
+    mapped: Arc<Mutex<Option<bool>>>,
+    in_flight: bool,
+}
+
+/// Measures how long named scopes of a frame take on the GPU, using timestamp
+/// queries when the device supports them and CPU timings otherwise.
+///
+/// Scopes are opened and closed on a command encoder, outside of render passes. To
+/// time a single drawer, give it its own render pass:
+///
+/// ```ignore
+/// graphics_device.begin_profiler_scope(&mut frame_encoder, "text");
+/// {
+///     let mut render_pass = frame_encoder.encoder.begin_render_pass(&descriptor);
+///     text_system.render_horizontal(/* ... */, &mut render_pass);
+/// }
+/// graphics_device.end_profiler_scope(&mut frame_encoder);
+/// ```
+///
+/// Timestamps are read back asynchronously, so `results` lags a few frames behind.
+pub struct GpuProfiler {
+    timestamp_writes: TimestampWrites,
+    timestamp_period: f32,
+    slots: Vec<QuerySlot>,
+    /// Slots waiting for their readback, oldest first.
+    in_flight: VecDeque<usize>,
+    /// The slot the current frame writes its timestamps to, if one was free.
+    current_slot: Option<usize>,
+    scopes: Vec<Scope>,
+    open_scopes: Vec<usize>,
+    query_count: u32,
+    results: Vec<ScopeTiming>,
+}
+
+impl GpuProfiler {
+    /// The features used for GPU timings. Request whichever of them the adapter supports
+    /// when creating the device, the profiler falls back to CPU timings without them.
+    pub const FEATURES: wgpu::Features =
+        wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS);
+
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let features = device.features();
+
+        let timestamp_writes = if features.contains(Self::FEATURES) {
+            TimestampWrites::Encoder
+        } else if features.contains(wgpu::Features::TIMESTAMP_QUERY) {
+            TimestampWrites::Passes
+        } else {
+            TimestampWrites::None
+        };
+
+        let slots = if timestamp_writes == TimestampWrites::None {
+            Vec::new()
+        } else {
+            (0..FRAMES_IN_FLIGHT).map(|_| QuerySlot::new(device)).collect()
+        };
+
+        Self {
+            timestamp_writes,
+            timestamp_period: queue.get_timestamp_period(),
+            current_slot: (!slots.is_empty()).then_some(0),
+            slots,
+            in_flight: VecDeque::new(),
+            scopes: Vec::new(),
+            open_scopes: Vec::new(),
+            query_count: 0,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn has_gpu_timestamps(&self) -> bool {
+        self.timestamp_writes != TimestampWrites::None
+    }
+
+    /// Timings of the most recent frame whose results are available, in the order the
+    /// scopes were opened.
+    pub fn results(&self) -> &[ScopeTiming] {
+        &self.results
+    }
+
+    pub fn begin_scope(&mut self, encoder: &mut CommandEncoder, name: &str) {
+        let query_index = match self.current_slot {
+            Some(slot) if self.query_count < MAX_QUERIES => {
+                let query_index = self.query_count;
+                self.query_count += 2;
+                self.write_timestamp(encoder, slot, query_index);
+                Some(query_index)
+            },
+            _ => None,
+        };
+
+        self.open_scopes.push(self.scopes.len());
+        self.scopes.push(Scope {
+            name: name.to_string(),
+            depth: self.open_scopes.len() - 1,
+            cpu_start: Instant::now(),
+            cpu_end: None,
+            query_index,
+        });
+    }
+
+    /// Closes the most recently opened scope.
+    pub fn end_scope(&mut self, encoder: &mut CommandEncoder) {
+        let Some(scope_index) = self.open_scopes.pop() else {
+            debug_assert!(false, "end_scope called without an open scope");
+            return;
+        };
+
+        let scope = &mut self.scopes[scope_index];
+        scope.cpu_end = Some(Instant::now());
+
+        if let (Some(slot), Some(query_index)) = (self.current_slot, scope.query_index) {
+            self.write_timestamp(encoder, slot, query_index + 1);
+        }
+    }
+
+    /// Finishes the current frame and starts a new one. Called by
+    /// `GraphicsDevice::begin_frame`, after the previous frame's commands were submitted.
+    pub fn new_frame(&mut self, device: &Device, queue: &Queue) {
+        let now = Instant::now();
+
+        // Scopes left open have no end timestamp, so only their CPU time is known.
+        for scope_index in self.open_scopes.drain(..) {
+            let scope = &mut self.scopes[scope_index];
+            scope.cpu_end = Some(now);
+            scope.query_index = None;
+        }
+
+        let scopes = std::mem::take(&mut self.scopes);
+        let query_count = std::mem::replace(&mut self.query_count, 0);
+
+        match self.current_slot.take() {
+            Some(slot_index) if query_count > 0 => {
+                let slot = &mut self.slots[slot_index];
+                slot.scopes = scopes;
+                slot.query_count = query_count;
+                slot.resolve(device, queue);
+                self.in_flight.push_back(slot_index);
+            },
+            // Without timestamps there's nothing to wait for.
+            _ if !self.has_gpu_timestamps() && !scopes.is_empty() => {
+                self.results = scopes.iter().map(|scope| scope.timing(None)).collect();
+            },
+            _ => {},
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+
+        while let Some(&slot_index) = self.in_flight.front() {
+            let slot = &mut self.slots[slot_index];
+
+            match slot.take_results(self.timestamp_period) {
+                Some(Some(results)) => self.results = results,
+                Some(None) => {},
+                None => break,
+            }
+
+            self.in_flight.pop_front();
+        }
+
+        self.current_slot = self.slots.iter().position(|slot| !slot.in_flight);
+    }
+
+    fn write_timestamp(&self, encoder: &mut CommandEncoder, slot: usize, query_index: u32) {
+        let query_set = &self.slots[slot].query_set;
+
+        match self.timestamp_writes {
+            TimestampWrites::None => {},
+            TimestampWrites::Passes => {
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Profiler timestamp"),
+                    timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                        query_set,
+                        beginning_of_pass_write_index: Some(query_index),
+                        end_of_pass_write_index: None,
+                    }),
+                });
+            },
+            TimestampWrites::Encoder => encoder.write_timestamp(query_set, query_index),
+        }
+    }
+}
+
+impl Scope {
+    fn timing(&self, gpu_ms: Option<f64>) -> ScopeTiming {
+        let cpu_end = self.cpu_end.unwrap_or(self.cpu_start);
+
+        ScopeTiming {
+            name: self.name.clone(),
+            depth: self.depth,
+            cpu_ms: (cpu_end - self.cpu_start).as_secs_f64() * 1000.0,
+            gpu_ms,
+        }
+    }
+}
+
+impl QuerySlot {
+    fn new(device: &Device) -> Self {
+        let buffer_size = MAX_QUERIES as u64 * wgpu::QUERY_SIZE as u64;
+
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Profiler query set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_QUERIES,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler resolve buffer"),
+                size: buffer_size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Profiler readback buffer"),
+                size: buffer_size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            scopes: Vec::new(),
+            query_count: 0,
+            mapped: Arc::new(Mutex::new(None)),
+            in_flight: false,
+        }
+    }
+
+    fn byte_len(&self) -> u64 {
+        self.query_count as u64 * wgpu::QUERY_SIZE as u64
+    }
+
+    /// Copies the frame's timestamps into the readback buffer and starts mapping it.
+    fn resolve(&mut self, device: &Device, queue: &Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Profiler resolve encoder"),
+        });
+
+        encoder.resolve_query_set(&self.query_set, 0..self.query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.byte_len(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        *self.mapped.lock().unwrap() = None;
+        let mapped = self.mapped.clone();
+
+        self.readback_buffer.slice(..self.byte_len()).map_async(
+            wgpu::MapMode::Read,
+            move |result| {
+                *mapped.lock().unwrap() = Some(result.is_ok());
+            },
+        );
+
+        self.in_flight = true;
+    }
+
+    /// `None` while the readback is still pending, `Some(None)` if it failed.
+    fn take_results(&mut self, timestamp_period: f32) -> Option<Option<Vec<ScopeTiming>>> {
+        let mapped = (*self.mapped.lock().unwrap())?;
+        self.in_flight = false;
+
+        if !mapped {
+            return Some(None);
+        }
+
+        let results = {
+            let data = self.readback_buffer.slice(..self.byte_len()).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+
+            self.scopes
+                .iter()
+                .map(|scope| {
+                    let gpu_ms = scope.query_index.and_then(|query_index| {
+                        let start = timestamps[query_index as usize];
+                        let end = timestamps[query_index as usize + 1];
+
+                        // Some drivers report zeroes for timestamps they didn't write.
+                        (start != 0 && end >= start)
+                            .then(|| (end - start) as f64 * timestamp_period as f64 / 1_000_000.0)
+                    });
+
+                    scope.timing(gpu_ms)
+                })
+                .collect()
+        };
+
+        self.readback_buffer.unmap();
+        self.scopes.clear();
+
+        Some(Some(results))
+    }
+}
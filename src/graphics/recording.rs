@@ -0,0 +1,229 @@
+use crate::{graphics::capture::ReadbackLayout, util::write_png_rgba, Error};
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{sync_channel, SyncSender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// How many recorded frames can be read back or waiting to be written at once. Rendering
+/// waits for the oldest frame when there are more, which is fine since recordings use a
+/// fixed frame time.
+pub(crate) const MAX_RECORDED_FRAMES_IN_FLIGHT: usize = 8;
+
+#[derive(Debug, Clone)]
+enum RecordingOutput {
+    Png { directory: PathBuf },
+    Pipe { program: String, args: Vec<String> },
+}
+
+/// Records every rendered frame with a fixed frame time, independent of how fast frames
+/// actually render. Pass it to `GameConfig::record_frames` or
+/// `GraphicsDevice::start_recording`.
+#[derive(Debug, Clone)]
+pub struct RecordingMode {
+    fps: u32,
+    output: RecordingOutput,
+}
+
+impl RecordingMode {
+    /// Writes frames to `frame_000000.png`, `frame_000001.png`, ... in `directory`.
+    pub fn png_sequence<P: Into<PathBuf>>(directory: P, fps: u32) -> Self {
+        assert!(fps > 0, "expecting a positive frame rate");
+        Self { fps, output: RecordingOutput::Png { directory: directory.into() } }
+    }
+
+    /// Starts `program` and writes frames to its stdin as raw, tightly packed RGBA8
+    /// pixels. `{width}`, `{height}` and `{fps}` in `args` are replaced with the size of
+    /// the frames and the frame rate, for example to encode with ffmpeg:
+    ///
+    /// ```
+    /// # use simple_game::graphics::RecordingMode;
+    /// RecordingMode::pipe(
+    ///     "ffmpeg",
+    ///     "-f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - -pix_fmt yuv420p out.mp4"
+    ///         .split(' '),
+    ///     60,
+    /// );
+    /// ```
+    ///
+    /// Every frame must have the same size, frames with a different size are skipped.
+    pub fn pipe<P, I, S>(program: P, args: I, fps: u32) -> Self
+    where
+        P: Into<String>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        assert!(fps > 0, "expecting a positive frame rate");
+
+        Self {
+            fps,
+            output: RecordingOutput::Pipe {
+                program: program.into(),
+                args: args.into_iter().map(Into::into).collect(),
+            },
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// The simulated time between two recorded frames.
+    pub fn frame_dt(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+}
+
+/// A process reading raw frames from its stdin, fed by a separate thread so encoding
+/// doesn't block rendering.
+struct EncoderPipe {
+    sender: SyncSender<(ReadbackLayout, Vec<u8>)>,
+    writer: JoinHandle<Result<(), Error>>,
+    child: Child,
+    size: (u32, u32),
+}
+
+enum RecordingSink {
+    Png { directory: PathBuf, writers: Vec<JoinHandle<Result<(), Error>>> },
+    Pipe(EncoderPipe),
+}
+
+/// Writes the frames of an active recording, in the order they were rendered.
+pub(crate) struct FrameRecorder {
+    mode: RecordingMode,
+    sink: RecordingSink,
+    frame_index: u64,
+}
+
+impl FrameRecorder {
+    pub(crate) fn start(mode: RecordingMode, size: (u32, u32)) -> Result<Self, Error> {
+        let sink = match &mode.output {
+            RecordingOutput::Png { directory } => {
+                std::fs::create_dir_all(directory)?;
+                RecordingSink::Png { directory: directory.clone(), writers: Vec::new() }
+            },
+            RecordingOutput::Pipe { program, args } => {
+                let args = args.iter().map(|arg| {
+                    arg.replace("{width}", &size.0.to_string())
+                        .replace("{height}", &size.1.to_string())
+                        .replace("{fps}", &mode.fps.to_string())
+                });
+
+                let mut child = Command::new(program).args(args).stdin(Stdio::piped()).spawn()?;
+                let mut stdin = child.stdin.take().expect("stdin should be piped");
+
+                let (sender, receiver) =
+                    sync_channel::<(ReadbackLayout, Vec<u8>)>(MAX_RECORDED_FRAMES_IN_FLIGHT);
+
+                let writer = std::thread::spawn(move || {
+                    for (layout, data) in receiver {
+                        stdin.write_all(&layout.to_rgba(&data))?;
+                    }
+
+                    Ok(())
+                });
+
+                RecordingSink::Pipe(EncoderPipe { sender, writer, child, size })
+            },
+        };
+
+        Ok(Self { mode, sink, frame_index: 0 })
+    }
+
+    pub(crate) fn mode(&self) -> &RecordingMode {
+        &self.mode
+    }
+
+    /// Frames which were read back but haven't been written yet, not counting frames
+    /// queued for an encoder process.
+    pub(crate) fn pending_writes(&self) -> usize {
+        match &self.sink {
+            RecordingSink::Png { writers, .. } => writers.len(),
+            RecordingSink::Pipe(_) => 0,
+        }
+    }
+
+    /// Writes a frame which was read back from the GPU. Blocks if too many frames are
+    /// still waiting to be written.
+    pub(crate) fn write_frame(&mut self, layout: ReadbackLayout, data: Vec<u8>) {
+        let frame_index = self.frame_index;
+        self.frame_index += 1;
+
+        match &mut self.sink {
+            RecordingSink::Png { directory, writers } => {
+                let path = directory.join(format!("frame_{:06}.png", frame_index));
+
+                writers.push(std::thread::spawn(move || {
+                    write_png_rgba(path, layout.width, layout.height, &layout.to_rgba(&data))?;
+                    Ok(())
+                }));
+            },
+            RecordingSink::Pipe(pipe) => {
+                if (layout.width, layout.height) != pipe.size {
+                    println!(
+                        "Skipping recorded frame {} with size {}x{}, expected {}x{}",
+                        frame_index, layout.width, layout.height, pipe.size.0, pipe.size.1
+                    );
+                    return;
+                }
+
+                // The encoder thread only stops receiving when writing failed, which is
+                // reported when the recording stops.
+                let _ = pipe.sender.send((layout, data));
+            },
+        }
+    }
+
+    /// Joins the PNG writers which finished, or all of them if `wait` is set.
+    pub(crate) fn join_writers(&mut self, wait: bool) -> Result<(), Error> {
+        let RecordingSink::Png { writers, .. } = &mut self.sink else {
+            return Ok(());
+        };
+
+        let mut result = Ok(());
+        let mut index = 0;
+
+        while index < writers.len() {
+            if !wait && !writers[index].is_finished() {
+                index += 1;
+                continue;
+            }
+
+            if let Err(err) = writers.remove(index).join().expect("Frame writer panicked") {
+                println!("Failed to write recorded frame: {}", err);
+
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Waits until every frame is written and the encoder process exited.
+    pub(crate) fn finish(mut self) -> Result<(), Error> {
+        let result = self.join_writers(true);
+
+        match self.sink {
+            RecordingSink::Png { .. } => result,
+            RecordingSink::Pipe(EncoderPipe { sender, writer, mut child, .. }) => {
+                // Closing stdin tells the encoder there are no more frames.
+                drop(sender);
+                let written = writer.join().expect("Frame writer panicked");
+                let status = child.wait()?;
+
+                written?;
+
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(Error::RecordingEncoderFailed(status))
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// Included by the shaders which project their vertices with a single matrix.
+
+struct Globals {
+    proj: mat4x4<f32>,
+};
+
+// Uniforms
+@group(0) @binding(0)
+var<uniform> globals: Globals;
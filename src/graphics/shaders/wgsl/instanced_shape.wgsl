@@ -1,10 +1,4 @@
-struct Globals {
-    proj: mat4x4<f32>,
-};
-
-// Uniforms
-@group(0) @binding(0)
-var<uniform> globals: Globals;
+#include "common.wgsl"
 
 struct VertexInput {
     @location(0)
@@ -1,4 +1,7 @@
-use crate::graphics::screen_projection_matrix;
+use crate::{
+    graphics::{screen_projection_matrix, Camera},
+    GraphicsDevice,
+};
 use fontdue::{
     layout::{CoordinateSystem, HorizontalAlign, Layout, LayoutSettings, TextStyle, VerticalAlign},
     Font as FontdueFont, FontSettings, Metrics,
@@ -258,6 +261,7 @@ pub struct TextSystem<F: Font = DefaultFont> {
 
     // The projection used to map pixel coordinates to normalized device coordinates.
     projection: Mat4,
+    camera: Option<Mat4>,
 
     screen_width: u32,
     screen_height: u32,
@@ -295,6 +299,7 @@ impl<F: Font> TextSystem<F> {
             layout,
             glpyh_painter,
             projection,
+            camera: None,
             screen_width,
             screen_height,
         }
@@ -306,6 +311,22 @@ impl<F: Font> TextSystem<F> {
         self.screen_height = screen_height;
     }
 
+    /// Renders text in the world space of `camera`, usually a `Camera2D`, instead of
+    /// pixels, until `reset_camera`. Call it again whenever the camera moves.
+    pub fn set_camera<C: Camera>(&mut self, camera: &C) {
+        self.camera = Some(camera.matrix());
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera = None;
+    }
+
+    /// Rebuilds the glyph pipeline if its shader changed since the last call, see
+    /// `ShaderWatcher`.
+    pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+        self.glpyh_painter.reload_shaders(graphics_device);
+    }
+
     /// Rasterizes and caches this character in the glyph texture.
     /// Returns Some(RasterizeResult) if the character is packed into the texture,
     /// otherwise None.
@@ -473,7 +494,7 @@ impl<F: Font> TextSystem<F> {
             &position_data,
             render_pass,
             queue,
-            (self.screen_width, self.screen_height),
+            self.camera.unwrap_or(self.projection),
         );
     }
 }
@@ -492,17 +513,25 @@ impl Color {
     }
 }
 
+/// Compares the structs the glyph painter uploads to the GPU with the layouts in its
+/// shader.
+pub(crate) fn layout_mismatches() -> Vec<String> {
+    GlyphPainter::layout_mismatches()
+}
+
 mod gpu {
     use super::{BITMAP_HEIGHT, BITMAP_WIDTH};
     use crate::{
-        graphics::{screen_projection_matrix, text::PositionedGlyph},
+        field_layout,
+        graphics::{check_vertex_layout, hot_reload::rebuild_pipeline, text::PositionedGlyph},
         GraphicsDevice,
     };
     use bytemuck::{Pod, Zeroable};
     use glam::Mat4;
-    use wgpu::{util::DeviceExt, BindGroup, Buffer, RenderPipeline, Texture};
+    use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, RenderPipeline, Texture};
 
     const MAX_INSTANCE_COUNT: usize = 40_000;
+    const GLYPH_SHADER: &str = "glyph.wgsl";
 
     /// Vertex attributes for instanced glyph data.
     #[repr(C)]
@@ -522,6 +551,15 @@ mod gpu {
         color: [f32; 4],
     }
 
+    impl GlyphInstanceData {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+            1 => Float32x2, // pos
+            2 => Float32x2, // size
+            3 => Float32x4, // uv_extents
+            4 => Float32x4, // color
+        ];
+    }
+
     impl Default for GlyphInstanceData {
         fn default() -> Self {
             GlyphInstanceData {
@@ -555,8 +593,12 @@ mod gpu {
         index_buffer: Buffer,
         instance_buffer: Buffer,
         uniform_buffer: wgpu::Buffer,
+        bind_group_layout: BindGroupLayout,
         bind_group: BindGroup,
         pipeline: RenderPipeline,
+        target_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        shader_generation: u64,
     }
 
     impl GlyphPainter {
@@ -606,12 +648,6 @@ mod gpu {
                     ],
                 });
 
-            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("GlyphPainter pipeline layout"),
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
             let texture_view = glyph_texture.create_view(&wgpu::TextureViewDescriptor::default());
             let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -642,6 +678,85 @@ mod gpu {
                 ],
             });
 
+            let pipeline = Self::build_pipeline(
+                device,
+                &bind_group_layout,
+                target_format,
+                depth_format,
+                include_str!("shaders/wgsl/glyph.wgsl"),
+            );
+
+            Self {
+                glyph_texture,
+                glyph_vertex_buffer,
+                index_buffer,
+                instance_buffer,
+                uniform_buffer,
+                bind_group_layout,
+                bind_group,
+                pipeline,
+                target_format,
+                depth_format,
+                shader_generation: 0,
+            }
+        }
+
+        /// Compares the structs uploaded to the GPU with the layouts in the shader.
+        pub fn layout_mismatches() -> Vec<String> {
+            check_vertex_layout(
+                GLYPH_SHADER,
+                "GlyphInstanceData",
+                std::mem::size_of::<GlyphInstanceData>(),
+                &GlyphInstanceData::ATTRIBUTES,
+                &[
+                    field_layout!(GlyphInstanceData, pos),
+                    field_layout!(GlyphInstanceData, size),
+                    field_layout!(GlyphInstanceData, uv_extents),
+                    field_layout!(GlyphInstanceData, color),
+                ],
+            )
+        }
+
+        /// Rebuilds the pipeline if its shader changed since the last call, see
+        /// `ShaderWatcher`.
+        pub fn reload_shaders(&mut self, graphics_device: &GraphicsDevice) {
+            let Some(shader_watcher) = graphics_device.shader_watcher() else {
+                return;
+            };
+
+            let device = graphics_device.device();
+
+            if let Some(source) = shader_watcher.changed_since(GLYPH_SHADER, self.shader_generation)
+            {
+                if let Some(pipeline) = rebuild_pipeline(device, GLYPH_SHADER, || {
+                    Self::build_pipeline(
+                        device,
+                        &self.bind_group_layout,
+                        self.target_format,
+                        self.depth_format,
+                        source,
+                    )
+                }) {
+                    self.pipeline = pipeline;
+                }
+            }
+
+            self.shader_generation = shader_watcher.generation();
+        }
+
+        fn build_pipeline(
+            device: &wgpu::Device,
+            bind_group_layout: &BindGroupLayout,
+            target_format: wgpu::TextureFormat,
+            depth_format: Option<wgpu::TextureFormat>,
+            shader_source: &str,
+        ) -> RenderPipeline {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("GlyphPainter pipeline layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
             let vertex_buffers = &[
                 wgpu::VertexBufferLayout {
                     array_stride: (std::mem::size_of::<GlyphQuadVertex>()) as wgpu::BufferAddress,
@@ -653,19 +768,13 @@ mod gpu {
                 wgpu::VertexBufferLayout {
                     array_stride: (std::mem::size_of::<GlyphInstanceData>()) as wgpu::BufferAddress,
                     step_mode: wgpu::VertexStepMode::Instance,
-                    attributes: &wgpu::vertex_attr_array![
-                        1 => Float32x2, // pos
-                        2 => Float32x2, // size
-                        3 => Float32x4, // uv_extents
-                        4 => Float32x4, // color
-                    ],
+                    attributes: &GlyphInstanceData::ATTRIBUTES,
                 },
             ];
 
-            let draw_shader =
-                GraphicsDevice::load_wgsl_shader(device, include_str!("shaders/wgsl/glyph.wgsl"));
+            let draw_shader = GraphicsDevice::load_wgsl_shader(device, shader_source);
 
-            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some("GlyphPainter render pipeline"),
                 layout: Some(&pipeline_layout),
                 vertex: wgpu::VertexState {
@@ -718,17 +827,7 @@ mod gpu {
                 }),
                 multiview: None,
                 cache: None,
-            });
-
-            Self {
-                glyph_texture,
-                glyph_vertex_buffer,
-                index_buffer,
-                instance_buffer,
-                uniform_buffer,
-                bind_group,
-                pipeline,
-            }
+            })
         }
 
         pub fn render(
@@ -736,7 +835,7 @@ mod gpu {
             glyph_positions: &[PositionedGlyph],
             render_pass: &mut wgpu::RenderPass,
             queue: &wgpu::Queue,
-            (width, height): (u32, u32),
+            projection: Mat4,
         ) {
             if glyph_positions.len() > MAX_INSTANCE_COUNT {
                 println!("Trying to render more glyphs than the maximum. Max = {}, attempted render count = {}", MAX_INSTANCE_COUNT, glyph_positions.len());
@@ -760,9 +859,8 @@ mod gpu {
 
             queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
 
-            // TODO(bschwind) - Only write to the uniform buffer when the window resizes.
-            let proj = screen_projection_matrix(width, height);
-            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(proj.as_ref()));
+            // TODO(bschwind) - Only write to the uniform buffer when the projection changes.
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(projection.as_ref()));
 
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(0, &self.bind_group, &[]);
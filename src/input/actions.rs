@@ -0,0 +1,383 @@
+use crate::{
+    input::{
+        button_name, parse_button, parse_gamepad_axis, Button, GamepadAxis, GamepadButton, Input,
+        GAMEPAD_PREFIX,
+    },
+    Error,
+};
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+/// One or more buttons which all have to be held down at the same time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    buttons: Vec<Button>,
+}
+
+impl Chord {
+    pub fn new<I: IntoIterator<Item = Button>>(buttons: I) -> Self {
+        let buttons: Vec<_> = buttons.into_iter().collect();
+        assert!(!buttons.is_empty(), "expecting at least one button in a chord");
+
+        Self { buttons }
+    }
+
+    pub fn buttons(&self) -> &[Button] {
+        &self.buttons
+    }
+
+    fn is_down(&self, input: &Input) -> bool {
+        self.buttons.iter().all(|button| input.is_down(*button))
+    }
+
+    fn was_down(&self, input: &Input) -> bool {
+        self.buttons.iter().all(|button| input.was_down(*button))
+    }
+}
+
+impl From<Button> for Chord {
+    fn from(button: Button) -> Self {
+        Self { buttons: vec![button] }
+    }
+}
+
+impl From<KeyCode> for Chord {
+    fn from(key: KeyCode) -> Self {
+        Button::Key(key).into()
+    }
+}
+
+impl From<MouseButton> for Chord {
+    fn from(button: MouseButton) -> Self {
+        Button::Mouse(button).into()
+    }
+}
+
+impl From<GamepadButton> for Chord {
+    fn from(button: GamepadButton) -> Self {
+        Button::Gamepad(button).into()
+    }
+}
+
+/// A source of values for a named axis.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisBinding {
+    /// -1.0 while `negative` is held, 1.0 while `positive` is held, 0.0 for both or neither.
+    Buttons {
+        negative: Button,
+        positive: Button,
+    },
+    Gamepad {
+        axis: GamepadAxis,
+        inverted: bool,
+    },
+}
+
+impl AxisBinding {
+    fn value(&self, input: &Input, dead_zone: f32) -> f32 {
+        match self {
+            AxisBinding::Buttons { negative, positive } => {
+                let negative = if input.is_down(*negative) { -1.0 } else { 0.0 };
+                let positive = if input.is_down(*positive) { 1.0 } else { 0.0 };
+
+                negative + positive
+            },
+            AxisBinding::Gamepad { axis, inverted } => {
+                let value = apply_dead_zone(input.gamepad_axis(*axis), dead_zone);
+
+                if *inverted {
+                    -value
+                } else {
+                    value
+                }
+            },
+        }
+    }
+}
+
+/// Zeroes out values within the dead zone and rescales the rest so the output
+/// still covers the full range.
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let magnitude = value.abs();
+
+    if magnitude <= dead_zone {
+        0.0
+    } else {
+        (value.signum() * (magnitude - dead_zone) / (1.0 - dead_zone)).clamp(-1.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AxisBindings {
+    bindings: Vec<AxisBinding>,
+    dead_zone: f32,
+}
+
+impl Default for AxisBindings {
+    fn default() -> Self {
+        Self { bindings: Vec::new(), dead_zone: DEFAULT_DEAD_ZONE }
+    }
+}
+
+/// Maps named actions ("jump") and axes ("move_x") to physical inputs, so games can
+/// query `actions.just_pressed(input, "jump")` instead of matching on key codes.
+///
+/// Bindings can be changed at runtime and saved to or loaded from a text file, with
+/// one action or axis per line:
+///
+/// ```text
+/// # Chords are joined with '+', alternative bindings with '|'.
+/// action jump = Space | Gamepad:South
+/// action quick_save = ControlLeft+KeyS
+/// action shoot = Mouse:Left
+///
+/// # Axes take negative/positive button pairs or gamepad axes, '-' inverts an axis.
+/// axis move_x dead_zone=0.2 = KeyA/KeyD | Gamepad:LeftStickX
+/// axis move_y = KeyS/KeyW | -Gamepad:RightStickY
+/// ```
+///
+/// Key names are winit's `KeyCode` variant names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Chord>>,
+    axes: BTreeMap<String, AxisBindings>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Adds `chord` as another way to trigger `action`.
+    pub fn bind_action<C: Into<Chord>>(&mut self, action: &str, chord: C) {
+        self.actions.entry(action.to_string()).or_default().push(chord.into());
+    }
+
+    /// Replaces all bindings of `action`, for example when the player rebinds it.
+    pub fn set_action_bindings(&mut self, action: &str, chords: Vec<Chord>) {
+        self.actions.insert(action.to_string(), chords);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Chord] {
+        self.actions.get(action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Adds `binding` as another source for `axis`.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.entry(axis.to_string()).or_default().bindings.push(binding);
+    }
+
+    /// Replaces all bindings of `axis`, keeping its dead zone.
+    pub fn set_axis_bindings(&mut self, axis: &str, bindings: Vec<AxisBinding>) {
+        self.axes.entry(axis.to_string()).or_default().bindings = bindings;
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(|axis| axis.bindings.as_slice()).unwrap_or_default()
+    }
+
+    /// The dead zone applied to gamepad axes bound to `axis`, from 0.0 to 1.0.
+    pub fn set_dead_zone(&mut self, axis: &str, dead_zone: f32) {
+        self.axes.entry(axis.to_string()).or_default().dead_zone = dead_zone.clamp(0.0, 0.99);
+    }
+
+    pub fn dead_zone(&self, axis: &str) -> f32 {
+        self.axes.get(axis).map(|axis| axis.dead_zone).unwrap_or(DEFAULT_DEAD_ZONE)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+
+    /// True while any of the action's chords is held down.
+    /// Actions without bindings are never down.
+    pub fn is_down(&self, input: &Input, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|chord| chord.is_down(input))
+    }
+
+    /// True if the action went from up to down since the previous tick.
+    pub fn just_pressed(&self, input: &Input, action: &str) -> bool {
+        let chords = self.action_bindings(action);
+
+        chords.iter().any(|chord| chord.is_down(input))
+            && !chords.iter().any(|chord| chord.was_down(input))
+    }
+
+    /// True if the action went from down to up since the previous tick.
+    pub fn just_released(&self, input: &Input, action: &str) -> bool {
+        let chords = self.action_bindings(action);
+
+        chords.iter().any(|chord| chord.was_down(input))
+            && !chords.iter().any(|chord| chord.is_down(input))
+    }
+
+    /// The value of `axis` from -1.0 to 1.0. When several bindings are active,
+    /// the one with the largest magnitude wins.
+    pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+        let Some(axis) = self.axes.get(axis) else {
+            return 0.0;
+        };
+
+        axis.bindings
+            .iter()
+            .map(|binding| binding.value(input, axis.dead_zone))
+            .fold(0.0f32, |max, value| if value.abs() > max.abs() { value } else { max })
+            .clamp(-1.0, 1.0)
+    }
+}
+
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (action, chords) in &self.actions {
+            write!(f, "action {} =", action)?;
+
+            for (i, chord) in chords.iter().enumerate() {
+                let separator = if i == 0 { " " } else { " | " };
+                write!(f, "{}", separator)?;
+
+                for (j, button) in chord.buttons.iter().enumerate() {
+                    if j > 0 {
+                        write!(f, "+")?;
+                    }
+
+                    write!(f, "{}", button_name(button))?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        for (axis, axis_bindings) in &self.axes {
+            write!(f, "axis {} dead_zone={} =", axis, axis_bindings.dead_zone)?;
+
+            for (i, binding) in axis_bindings.bindings.iter().enumerate() {
+                let separator = if i == 0 { " " } else { " | " };
+                write!(f, "{}", separator)?;
+
+                match binding {
+                    AxisBinding::Buttons { negative, positive } => {
+                        write!(f, "{}/{}", button_name(negative), button_name(positive))?
+                    },
+                    AxisBinding::Gamepad { axis, inverted } => {
+                        let sign = if *inverted { "-" } else { "" };
+                        write!(f, "{}{}{:?}", sign, GAMEPAD_PREFIX, axis)?
+                    },
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ActionMap {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut action_map = ActionMap::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error =
+                |message: String| Error::BindingsParseError { line: line_index + 1, message };
+
+            // Options like "dead_zone=0.2" contain an '=' too, bindings never do.
+            let (declaration, bindings) =
+                line.rsplit_once('=').ok_or_else(|| parse_error("expected '='".to_string()))?;
+
+            let mut words = declaration.split_whitespace();
+            let kind = words.next().unwrap_or_default();
+            let name = words.next().ok_or_else(|| parse_error("missing name".to_string()))?;
+            let bindings = bindings.split('|').map(str::trim).filter(|b| !b.is_empty());
+
+            match kind {
+                "action" => {
+                    if let Some(option) = words.next() {
+                        return Err(parse_error(format!("unexpected '{}'", option)));
+                    }
+
+                    let chords = bindings
+                        .map(|chord| {
+                            chord
+                                .split('+')
+                                .map(|button| parse_button(button.trim()))
+                                .collect::<Option<Vec<_>>>()
+                                .map(Chord::new)
+                                .ok_or_else(|| parse_error(format!("invalid chord '{}'", chord)))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    action_map.set_action_bindings(name, chords);
+                },
+                "axis" => {
+                    let axis_bindings = bindings
+                        .map(|binding| {
+                            parse_axis_binding(binding).ok_or_else(|| {
+                                parse_error(format!("invalid axis binding '{}'", binding))
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    action_map.set_axis_bindings(name, axis_bindings);
+
+                    for option in words {
+                        let dead_zone = option
+                            .strip_prefix("dead_zone=")
+                            .and_then(|dead_zone| dead_zone.parse().ok())
+                            .ok_or_else(|| parse_error(format!("unexpected '{}'", option)))?;
+
+                        action_map.set_dead_zone(name, dead_zone);
+                    }
+                },
+                _ => {
+                    return Err(parse_error(format!(
+                        "expected 'action' or 'axis', found '{}'",
+                        kind
+                    )))
+                },
+            }
+        }
+
+        Ok(action_map)
+    }
+}
+
+fn parse_axis_binding(binding: &str) -> Option<AxisBinding> {
+    if let Some((negative, positive)) = binding.split_once('/') {
+        return Some(AxisBinding::Buttons {
+            negative: parse_button(negative.trim())?,
+            positive: parse_button(positive.trim())?,
+        });
+    }
+
+    let (inverted, name) = match binding.strip_prefix('-') {
+        Some(name) => (true, name),
+        None => (false, binding),
+    };
+
+    let axis = parse_gamepad_axis(name.strip_prefix(GAMEPAD_PREFIX)?)?;
+
+    Some(AxisBinding::Gamepad { axis, inverted })
+}
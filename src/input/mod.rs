@@ -0,0 +1,443 @@
+use glam::{vec2, Vec2};
+use serde::{de::IntoDeserializer, Deserialize};
+use std::collections::{HashMap, HashSet};
+use winit::{
+    event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+mod actions;
+mod recording;
+
+pub use actions::*;
+pub use recording::*;
+
+/// Used to convert pixel-based scroll deltas (trackpads) into lines.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+const MOUSE_PREFIX: &str = "Mouse:";
+const GAMEPAD_PREFIX: &str = "Gamepad:";
+
+const GAMEPAD_BUTTONS: [GamepadButton; 16] = [
+    GamepadButton::South,
+    GamepadButton::East,
+    GamepadButton::West,
+    GamepadButton::North,
+    GamepadButton::LeftBumper,
+    GamepadButton::RightBumper,
+    GamepadButton::LeftTrigger,
+    GamepadButton::RightTrigger,
+    GamepadButton::Select,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+];
+
+const GAMEPAD_AXES: [GamepadAxis; 6] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+    GamepadAxis::LeftTrigger,
+    GamepadAxis::RightTrigger,
+];
+
+/// Gamepad buttons, named by their position on the controller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Gamepad axes. Sticks range from -1.0 to 1.0 with positive Y pointing up,
+/// triggers range from 0.0 to 1.0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// A keyboard key, mouse button or gamepad button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl From<KeyCode> for Button {
+    fn from(key: KeyCode) -> Self {
+        Button::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(button: MouseButton) -> Self {
+        Button::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for Button {
+    fn from(button: GamepadButton) -> Self {
+        Button::Gamepad(button)
+    }
+}
+
+/// A single change to the input state. Window and device events are reduced to these
+/// before being applied to `Input`, which is also what gets recorded and replayed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputEvent {
+    ButtonPressed(Button),
+    ButtonReleased(Button),
+    /// In physical pixels, relative to the top left of the window.
+    CursorMoved(Vec2),
+    CursorLeft,
+    /// Raw mouse motion.
+    MouseMotion(Vec2),
+    /// In lines.
+    Scroll(Vec2),
+    GamepadAxisMoved(GamepadAxis, f32),
+    ScaleFactorChanged(f64),
+    FocusLost,
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
+                PhysicalKey::Code(key) if !event.repeat => {
+                    Some(Self::from_button_state(Button::Key(key), event.state))
+                },
+                _ => None,
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                Some(Self::from_button_state(Button::Mouse(*button), *state))
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                Some(InputEvent::CursorMoved(vec2(position.x as f32, position.y as f32)))
+            },
+            WindowEvent::CursorLeft { .. } => Some(InputEvent::CursorLeft),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => vec2(*x, *y),
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        vec2(pos.x as f32, pos.y as f32) / PIXELS_PER_SCROLL_LINE
+                    },
+                };
+
+                Some(InputEvent::Scroll(delta))
+            },
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                Some(InputEvent::ScaleFactorChanged(*scale_factor))
+            },
+            WindowEvent::Focused(false) => Some(InputEvent::FocusLost),
+            _ => None,
+        }
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            DeviceEvent::MouseMotion { delta: (x, y) } => {
+                Some(InputEvent::MouseMotion(vec2(*x as f32, *y as f32)))
+            },
+            _ => None,
+        }
+    }
+
+    fn from_button_state(button: Button, state: ElementState) -> Self {
+        match state {
+            ElementState::Pressed => InputEvent::ButtonPressed(button),
+            ElementState::Released => InputEvent::ButtonReleased(button),
+        }
+    }
+}
+
+/// Polled input state, updated from window events by the game loop and passed to
+/// `GameApp::tick`. winit doesn't report gamepads, so gamepad state has to be fed in
+/// with `set_gamepad_button` and `set_gamepad_axis`.
+///
+/// The "just pressed", "just released" and delta values accumulate until the end of
+/// the next tick, so each tick sees every press exactly once even when a frame runs
+/// several ticks or none at all.
+#[derive(Debug, Clone)]
+pub struct Input {
+    down: HashSet<Button>,
+    just_pressed: HashSet<Button>,
+    just_released: HashSet<Button>,
+    cursor_position: Option<Vec2>,
+    mouse_delta: Vec2,
+    scroll_delta: Vec2,
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    scale_factor: f64,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            down: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            cursor_position: None,
+            mouse_delta: Vec2::ZERO,
+            scroll_delta: Vec2::ZERO,
+            gamepad_axes: HashMap::new(),
+            scale_factor: 1.0,
+        }
+    }
+
+    pub fn is_down<B: Into<Button>>(&self, button: B) -> bool {
+        self.down.contains(&button.into())
+    }
+
+    /// True if the button was pressed since the previous tick.
+    pub fn just_pressed<B: Into<Button>>(&self, button: B) -> bool {
+        self.just_pressed.contains(&button.into())
+    }
+
+    /// True if the button was released since the previous tick.
+    pub fn just_released<B: Into<Button>>(&self, button: B) -> bool {
+        self.just_released.contains(&button.into())
+    }
+
+    /// True if the button was held down at the end of the previous tick.
+    pub fn was_down<B: Into<Button>>(&self, button: B) -> bool {
+        let button = button.into();
+
+        (self.is_down(button) && !self.just_pressed(button)) || self.just_released(button)
+    }
+
+    /// All buttons pressed since the previous tick, for example to let players pick a
+    /// new binding in a settings menu.
+    pub fn just_pressed_buttons(&self) -> impl Iterator<Item = Button> + '_ {
+        self.just_pressed.iter().copied()
+    }
+
+    /// Cursor position in physical pixels, relative to the top left of the window.
+    /// `None` if the cursor isn't over the window.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// Cursor position in logical pixels, relative to the top left of the window.
+    pub fn cursor_position_logical(&self) -> Option<Vec2> {
+        self.cursor_position.map(|pos| pos / self.scale_factor as f32)
+    }
+
+    /// Raw mouse motion since the previous tick. This keeps reporting motion while
+    /// the cursor is grabbed or hidden.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// Scroll wheel motion since the previous tick, in lines.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    pub fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        self.gamepad_axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Applies the input part of a window event, ignoring everything else.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.apply_event(&event);
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let Some(event) = InputEvent::from_device_event(event) {
+            self.apply_event(&event);
+        }
+    }
+
+    pub fn apply_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::ButtonPressed(button) => {
+                self.set_button_state(button, ElementState::Pressed);
+            },
+            InputEvent::ButtonReleased(button) => {
+                self.set_button_state(button, ElementState::Released);
+            },
+            InputEvent::CursorMoved(position) => {
+                self.cursor_position = Some(position);
+            },
+            InputEvent::CursorLeft => {
+                self.cursor_position = None;
+            },
+            InputEvent::MouseMotion(delta) => {
+                self.mouse_delta += delta;
+            },
+            InputEvent::Scroll(delta) => {
+                self.scroll_delta += delta;
+            },
+            InputEvent::GamepadAxisMoved(axis, value) => {
+                self.gamepad_axes.insert(axis, value);
+            },
+            InputEvent::ScaleFactorChanged(scale_factor) => {
+                self.scale_factor = scale_factor;
+            },
+            InputEvent::FocusLost => {
+                // We won't get release events for anything held while the window is
+                // unfocused, so release everything now.
+                for button in self.down.drain() {
+                    self.just_released.insert(button);
+                }
+            },
+        }
+    }
+
+    /// Applies a gamepad button change right away. Games run by `run_game_app` should
+    /// push gamepad input through `GameApp::poll_input` instead, which also records it.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        let button = Button::Gamepad(button);
+
+        if pressed {
+            self.apply_event(&InputEvent::ButtonPressed(button));
+        } else {
+            self.apply_event(&InputEvent::ButtonReleased(button));
+        }
+    }
+
+    /// Applies a gamepad axis change right away, see `set_gamepad_button`.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.apply_event(&InputEvent::GamepadAxisMoved(axis, value));
+    }
+
+    /// Clears the per-tick state. Called by the game loop after every tick.
+    pub fn end_tick(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll_delta = Vec2::ZERO;
+    }
+
+    fn set_button_state(&mut self, button: Button, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.down.insert(button) {
+                    self.just_pressed.insert(button);
+                }
+            },
+            ElementState::Released => {
+                if self.down.remove(&button) {
+                    self.just_released.insert(button);
+                }
+            },
+        }
+    }
+}
+
+/// Collects input events from sources winit doesn't cover, like a gamepad library, see
+/// `GameApp::poll_input`. The game loop applies them like window events, so they're
+/// recorded and replayed too.
+#[derive(Debug, Clone, Default)]
+pub struct InputSink {
+    events: Vec<InputEvent>,
+}
+
+impl InputSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    pub fn gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        let button = Button::Gamepad(button);
+
+        if pressed {
+            self.push(InputEvent::ButtonPressed(button));
+        } else {
+            self.push(InputEvent::ButtonReleased(button));
+        }
+    }
+
+    pub fn gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.push(InputEvent::GamepadAxisMoved(axis, value));
+    }
+
+    /// Takes the collected events, in the order they were pushed.
+    pub fn drain(&mut self) -> impl Iterator<Item = InputEvent> + '_ {
+        self.events.drain(..)
+    }
+}
+
+fn button_name(button: &Button) -> String {
+    match button {
+        Button::Key(key) => format!("{:?}", key),
+        Button::Mouse(MouseButton::Other(id)) => format!("{}{}", MOUSE_PREFIX, id),
+        Button::Mouse(button) => format!("{}{:?}", MOUSE_PREFIX, button),
+        Button::Gamepad(button) => format!("{}{:?}", GAMEPAD_PREFIX, button),
+    }
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+    if let Some(name) = name.strip_prefix(MOUSE_PREFIX) {
+        let button = match name {
+            "Left" => MouseButton::Left,
+            "Right" => MouseButton::Right,
+            "Middle" => MouseButton::Middle,
+            "Back" => MouseButton::Back,
+            "Forward" => MouseButton::Forward,
+            other => MouseButton::Other(other.parse().ok()?),
+        };
+
+        return Some(Button::Mouse(button));
+    }
+
+    if let Some(name) = name.strip_prefix(GAMEPAD_PREFIX) {
+        return GAMEPAD_BUTTONS
+            .iter()
+            .find(|button| format!("{:?}", button) == name)
+            .map(|button| Button::Gamepad(*button));
+    }
+
+    // KeyCode's serde representation is its variant name.
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        name.into_deserializer();
+
+    KeyCode::deserialize(deserializer).ok().map(Button::Key)
+}
+
+fn parse_gamepad_axis(name: &str) -> Option<GamepadAxis> {
+    GAMEPAD_AXES.iter().find(|axis| format!("{:?}", axis) == name).copied()
+}
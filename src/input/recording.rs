@@ -0,0 +1,244 @@
+use crate::{
+    graphics::GraphicsDevice,
+    input::{button_name, parse_button, parse_gamepad_axis, Input, InputEvent},
+    timestep::FixedTimestep,
+    Error, GameApp,
+};
+use glam::vec2;
+use std::{fmt, path::Path, str::FromStr};
+
+const HEADER: &str = "simple-game input recording v1";
+
+/// Every input event the game loop applied, tagged with the tick it was applied
+/// before, plus the `GameApp::checksum` after each tick.
+///
+/// Replaying a recording against a fresh `GameApp` feeds it the same events at the
+/// same ticks with the same `dt`, so a deterministic simulation ends up in the same
+/// state. Record one by passing a path to `GameConfig::record_input`.
+///
+/// The file is plain text with one event per line, prefixed with its tick:
+///
+/// ```text
+/// simple-game input recording v1
+/// ticks_per_second 60
+/// 12 press KeyW
+/// 12 cursor 301.5 180
+/// 40 release KeyW
+/// 40 checksum 9f3a6c01d2e4b857
+/// ticks 41
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRecording {
+    ticks_per_second: u32,
+    tick_count: u64,
+    events: Vec<(u64, InputEvent)>,
+    checksums: Vec<(u64, u64)>,
+}
+
+impl InputRecording {
+    pub fn new(ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0, "expecting a positive tick rate");
+
+        Self { ticks_per_second, tick_count: 0, events: Vec::new(), checksums: Vec::new() }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn ticks_per_second(&self) -> u32 {
+        self.ticks_per_second
+    }
+
+    /// The number of ticks recorded so far.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    pub fn events(&self) -> &[(u64, InputEvent)] {
+        &self.events
+    }
+
+    /// Records `event` as applied before the current tick.
+    pub fn record_event(&mut self, event: InputEvent) {
+        self.events.push((self.tick_count, event));
+    }
+
+    /// Ends the current tick, with the game's state checksum after it if it has one.
+    pub fn end_tick(&mut self, checksum: Option<u64>) {
+        if let Some(checksum) = checksum {
+            self.checksums.push((self.tick_count, checksum));
+        }
+
+        self.tick_count += 1;
+    }
+
+    /// Initializes `G` on `graphics_device` and runs every recorded tick on it with the
+    /// recorded input. Nothing is rendered.
+    ///
+    /// Returns the game in its final state, or `Error::ReplayDiverged` at the first
+    /// tick where `G::checksum` doesn't match the recorded one.
+    pub fn replay<G: GameApp>(&self, graphics_device: &mut GraphicsDevice) -> Result<G, Error> {
+        let mut game_app = G::init(graphics_device);
+        let mut input = Input::new();
+        let dt = FixedTimestep::new(self.ticks_per_second).tick_dt().as_secs_f32();
+
+        let mut events = self.events.iter().peekable();
+        let mut checksums = self.checksums.iter().peekable();
+
+        for tick in 0..self.tick_count {
+            while let Some((_, event)) = events.next_if(|(event_tick, _)| *event_tick == tick) {
+                input.apply_event(event);
+            }
+
+            game_app.tick(dt, &input);
+            input.end_tick();
+
+            if let Some((_, expected)) =
+                checksums.next_if(|(checksum_tick, _)| *checksum_tick == tick)
+            {
+                let actual = game_app.checksum();
+
+                if actual != Some(*expected) {
+                    return Err(Error::ReplayDiverged { tick, expected: *expected, actual });
+                }
+            }
+        }
+
+        Ok(game_app)
+    }
+}
+
+impl fmt::Display for InputRecording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "ticks_per_second {}", self.ticks_per_second)?;
+
+        let mut checksums = self.checksums.iter().peekable();
+
+        for (tick, event) in &self.events {
+            // Checksums are taken after a tick, so they go before events of later ticks.
+            while let Some((checksum_tick, checksum)) = checksums.next_if(|(t, _)| t < tick) {
+                writeln!(f, "{} checksum {:016x}", checksum_tick, checksum)?;
+            }
+
+            write!(f, "{} ", tick)?;
+
+            match event {
+                InputEvent::ButtonPressed(button) => writeln!(f, "press {}", button_name(button))?,
+                InputEvent::ButtonReleased(button) => {
+                    writeln!(f, "release {}", button_name(button))?
+                },
+                InputEvent::CursorMoved(pos) => writeln!(f, "cursor {} {}", pos.x, pos.y)?,
+                InputEvent::CursorLeft => writeln!(f, "cursor_left")?,
+                InputEvent::MouseMotion(delta) => {
+                    writeln!(f, "mouse_motion {} {}", delta.x, delta.y)?
+                },
+                InputEvent::Scroll(delta) => writeln!(f, "scroll {} {}", delta.x, delta.y)?,
+                InputEvent::GamepadAxisMoved(axis, value) => {
+                    writeln!(f, "axis {:?} {}", axis, value)?
+                },
+                InputEvent::ScaleFactorChanged(scale_factor) => {
+                    writeln!(f, "scale_factor {}", scale_factor)?
+                },
+                InputEvent::FocusLost => writeln!(f, "focus_lost")?,
+            }
+        }
+
+        for (checksum_tick, checksum) in checksums {
+            writeln!(f, "{} checksum {:016x}", checksum_tick, checksum)?;
+        }
+
+        writeln!(f, "ticks {}", self.tick_count)
+    }
+}
+
+impl FromStr for InputRecording {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        let parse_error = |line: usize, message: &str| Error::RecordingParseError {
+            line,
+            message: message.to_string(),
+        };
+
+        match lines.next() {
+            Some((_, HEADER)) => {},
+            _ => return Err(parse_error(1, "not an input recording")),
+        }
+
+        let mut recording = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("ticks_per_second "))
+            .and_then(|ticks_per_second| ticks_per_second.parse().ok())
+            .filter(|ticks_per_second| *ticks_per_second > 0)
+            .map(InputRecording::new)
+            .ok_or_else(|| parse_error(2, "expected 'ticks_per_second <n>'"))?;
+
+        for (line_number, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let first = words.next().unwrap_or_default();
+
+            if first == "ticks" {
+                recording.tick_count = words
+                    .next()
+                    .and_then(|ticks| ticks.parse().ok())
+                    .ok_or_else(|| parse_error(line_number, "expected 'ticks <n>'"))?;
+
+                return Ok(recording);
+            }
+
+            let tick: u64 =
+                first.parse().map_err(|_| parse_error(line_number, "expected a tick number"))?;
+            let kind = words.next().unwrap_or_default();
+            let args: Vec<&str> = words.collect();
+
+            if kind == "checksum" {
+                let checksum = args
+                    .first()
+                    .and_then(|checksum| u64::from_str_radix(checksum, 16).ok())
+                    .ok_or_else(|| parse_error(line_number, "invalid checksum"))?;
+
+                recording.checksums.push((tick, checksum));
+                continue;
+            }
+
+            let event = parse_event(kind, &args)
+                .ok_or_else(|| parse_error(line_number, &format!("invalid event '{}'", line)))?;
+
+            recording.events.push((tick, event));
+        }
+
+        Err(parse_error(text.lines().count(), "missing 'ticks <n>', the recording is truncated"))
+    }
+}
+
+fn parse_event(kind: &str, args: &[&str]) -> Option<InputEvent> {
+    let f32_arg = |i: usize| args.get(i).and_then(|arg| arg.parse::<f32>().ok());
+
+    let event = match (kind, args.len()) {
+        ("press", 1) => InputEvent::ButtonPressed(parse_button(args[0])?),
+        ("release", 1) => InputEvent::ButtonReleased(parse_button(args[0])?),
+        ("cursor", 2) => InputEvent::CursorMoved(vec2(f32_arg(0)?, f32_arg(1)?)),
+        ("cursor_left", 0) => InputEvent::CursorLeft,
+        ("mouse_motion", 2) => InputEvent::MouseMotion(vec2(f32_arg(0)?, f32_arg(1)?)),
+        ("scroll", 2) => InputEvent::Scroll(vec2(f32_arg(0)?, f32_arg(1)?)),
+        ("axis", 2) => InputEvent::GamepadAxisMoved(parse_gamepad_axis(args[0])?, f32_arg(1)?),
+        ("scale_factor", 1) => InputEvent::ScaleFactorChanged(args[0].parse().ok()?),
+        ("focus_lost", 0) => InputEvent::FocusLost,
+        _ => return None,
+    };
+
+    Some(event)
+}
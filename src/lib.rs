@@ -1,19 +1,36 @@
-use crate::graphics::GraphicsDevice;
+use crate::{
+    graphics::{screenshot_path, FrameRecorder, GraphicsDevice},
+    input::{Input, InputEvent, InputRecording, InputSink},
+    pacing::FramePacer,
+    stats::{FrameStats, FrameTiming},
+    timestep::FixedTimestep,
+};
 use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
 use thiserror::Error;
 use winit::{
+    application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::{Event, WindowEvent},
-    event_loop::{ActiveEventLoop, EventLoop},
-    window::{Fullscreen, Window},
+    event::{DeviceEvent, DeviceId, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::{Fullscreen, Window, WindowId},
 };
 
+pub mod audio;
+mod config;
+pub mod golden;
 pub mod graphics;
+pub mod input;
+pub mod pacing;
+pub mod state;
+pub mod stats;
+pub mod timestep;
 pub mod util;
 
+pub use config::GameConfig;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Event loop error: {0}")]
@@ -21,27 +38,80 @@ pub enum Error {
 
     #[error("Window building error: {0}")]
     WindowBuilderError(#[from] winit::error::OsError),
+
+    #[error("Surface creation error: {0}")]
+    CreateSurfaceError(#[from] wgpu::CreateSurfaceError),
+
+    #[error("No suitable graphics adapter found")]
+    NoSuitableAdapter,
+
+    #[error("Device request error: {0}")]
+    RequestDeviceError(#[from] wgpu::RequestDeviceError),
+
+    #[error("Surface error: {0}")]
+    SurfaceError(#[from] wgpu::SurfaceError),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Bindings parse error on line {line}: {message}")]
+    BindingsParseError { line: usize, message: String },
+
+    #[error("Input recording parse error on line {line}: {message}")]
+    RecordingParseError { line: usize, message: String },
+
+    #[error("Shader preprocessing error: {0}")]
+    ShaderPreprocessError(#[from] graphics::PreprocessError),
+
+    #[error("The surface doesn't support copying frames")]
+    CaptureNotSupported,
+
+    #[error("Frames in the {0:?} format can't be captured")]
+    UnsupportedCaptureFormat(wgpu::TextureFormat),
+
+    #[error("A recording is already active")]
+    AlreadyRecording,
+
+    #[error("The recording's encoder process failed: {0}")]
+    RecordingEncoderFailed(std::process::ExitStatus),
+
+    #[error("PNG encoding error: {0}")]
+    PngEncodingError(#[from] png::EncodingError),
+
+    #[error("Unsupported audio format, expected WAV or Ogg Vorbis")]
+    UnsupportedAudioFormat,
+
+    #[error("Invalid audio file header: {sample_rate} Hz with {channels} channels")]
+    InvalidAudioHeader { sample_rate: u32, channels: u16 },
+
+    #[error("WAV error: {0}")]
+    WavError(#[from] hound::Error),
+
+    #[error("Ogg Vorbis error: {0}")]
+    VorbisError(#[from] lewton::VorbisError),
+
+    #[error("Audio backend error: {0}")]
+    AudioBackendError(String),
+
+    #[error("Sound synth parse error on line {line}: {message}")]
+    SynthParseError { line: usize, message: String },
+
+    #[error(
+        "Replay diverged at tick {tick}: expected checksum {expected:016x}, got {actual:016x?}"
+    )]
+    ReplayDiverged { tick: u64, expected: u64, actual: Option<u64> },
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum WindowDimensions {
     Windowed(u32, u32),
+    /// Borderless fullscreen on the current monitor.
     FullScreen,
+    /// Exclusive fullscreen using the current monitor's largest video mode.
+    ExclusiveFullScreen,
 }
 
 pub trait GameApp {
-    fn window_title() -> &'static str {
-        "Simple Game"
-    }
-
-    fn window_dimensions() -> WindowDimensions {
-        WindowDimensions::Windowed(1280, 720)
-    }
-
-    // TODO(bschwind) - Separate tick rate from render rate.
-    fn desired_fps() -> RefreshRate {
-        RefreshRate::Monitor
-    }
-
     fn handle_window_event(&mut self, event: &WindowEvent, event_loop: &ActiveEventLoop) {
         if let WindowEvent::CloseRequested = event {
             event_loop.exit();
@@ -51,94 +121,448 @@ pub trait GameApp {
     fn init(graphics_device: &mut GraphicsDevice) -> Self;
 
     fn resize(&mut self, _graphics_device: &mut GraphicsDevice, _width: u32, _height: u32) {}
-    fn tick(&mut self, dt: f32);
-    fn render(&mut self, graphics_device: &mut GraphicsDevice, window: &Window);
+
+    /// Called when the app is suspended, right before the window and `graphics_device` are
+    /// dropped. Any GPU resources created from `graphics_device` should be released here.
+    fn suspended(&mut self, _graphics_device: &mut GraphicsDevice) {}
+
+    /// Called when the app resumes after being suspended, with a newly created
+    /// `graphics_device`. GPU resources released in `suspended` should be rebuilt here.
+    fn resumed(&mut self, _graphics_device: &mut GraphicsDevice) {}
+
+    /// Called at the start of every frame, before the ticks run. Push input from sources
+    /// winit doesn't cover here, such as gamepads, so it gets applied, recorded and
+    /// replayed like any other input.
+    fn poll_input(&mut self, _input: &mut InputSink) {}
+
+    /// Called zero or more times per frame, always with the same `dt`.
+    fn tick(&mut self, dt: f32, input: &Input);
+
+    /// A hash of the simulation state, taken after every tick while recording input and
+    /// compared against the recorded value while replaying to catch determinism breaks.
+    /// Use a hasher with a stable output, `DefaultHasher` may change between Rust versions.
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+
+    /// `alpha` is how far we are between the last tick and the next one, in the range
+    /// [0.0, 1.0). Use it to interpolate between the previous and current simulation state.
+    fn render(&mut self, graphics_device: &mut GraphicsDevice, alpha: f32);
+
+    /// Called after every frame with timings of the most recent frames.
+    fn frame_finished(&mut self, _frame_stats: &FrameStats) {}
+
+    /// Checked after every frame, the game loop exits once this returns true.
+    fn should_exit(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RefreshRate {
+    /// Render once per monitor refresh. Paced by vsync with the `Fifo` present modes,
+    /// otherwise by a frame limiter.
     Monitor,
+    /// Render at a fixed rate using a frame limiter.
     Fps(usize),
+    /// Only render after input, a resize or when the OS asks for a redraw. Meant for
+    /// tools and editors which sit idle most of the time. Time spent idle isn't ticked,
+    /// each frame runs at most one tick.
+    Reactive,
 }
 
-async fn run<G: 'static + GameApp>() -> Result<(), Error> {
-    let event_loop = EventLoop::new()?;
+/// How the game loop decides when to render the next frame.
+enum Pacing {
+    /// Presenting blocks until the next vblank, so frames are requested back to back.
+    VSync,
+    Limited(FramePacer),
+    Reactive,
+}
+
+/// The window and graphics device only exist between `resumed` and `suspended`.
+struct Running {
+    window: Arc<Window>,
+    graphics_device: GraphicsDevice,
+    pacing: Pacing,
+}
+
+struct App<G: GameApp> {
+    config: GameConfig,
+    game_app: Option<G>,
+    running: Option<Running>,
+    input: Input,
+    input_sink: InputSink,
+    recording: Option<InputRecording>,
+    timestep: FixedTimestep,
+    last_frame_time: Instant,
+    /// Set when the last frame wasn't presented, see `RedrawRequested`.
+    hold_ticks: bool,
+    /// The frame recording between `suspended` and `resumed`, it continues on the new
+    /// graphics device.
+    suspended_recording: Option<FrameRecorder>,
+    frame_stats: FrameStats,
+    error: Option<Error>,
+}
+
+impl<G: GameApp> App<G> {
+    fn new(config: GameConfig) -> Self {
+        let timestep = FixedTimestep::new(config.ticks_per_second);
+        let recording =
+            config.record_input.as_ref().map(|_| InputRecording::new(config.ticks_per_second));
+
+        Self {
+            config,
+            game_app: None,
+            running: None,
+            input: Input::new(),
+            input_sink: InputSink::new(),
+            recording,
+            timestep,
+            last_frame_time: Instant::now(),
+            hold_ticks: false,
+            suspended_recording: None,
+            frame_stats: FrameStats::default(),
+            error: None,
+        }
+    }
 
-    let window = {
-        let window_attributes = Window::default_attributes().with_title(G::window_title());
+    fn create_window(&self, event_loop: &ActiveEventLoop) -> Result<Window, Error> {
+        let config = &self.config;
 
-        let window_attributes = match G::window_dimensions() {
+        let mut window_attributes = Window::default_attributes()
+            .with_title(config.title.as_str())
+            .with_resizable(config.resizable);
+
+        if let Some((width, height)) = config.min_size {
+            window_attributes =
+                window_attributes.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+
+        if let Some((width, height)) = config.max_size {
+            window_attributes =
+                window_attributes.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+
+        let window_attributes = match config.dimensions {
             WindowDimensions::Windowed(width, height) => {
                 window_attributes.with_inner_size(PhysicalSize::new(width, height))
             },
             WindowDimensions::FullScreen => {
                 window_attributes.with_fullscreen(Some(Fullscreen::Borderless(None)))
             },
+            // Video modes come from a monitor, which we only have once the window exists.
+            WindowDimensions::ExclusiveFullScreen => window_attributes,
         };
 
-        #[allow(deprecated)]
-        event_loop.create_window(window_attributes).unwrap()
-    };
+        let window = event_loop.create_window(window_attributes)?;
 
-    let window = Arc::new(window);
+        if config.dimensions == WindowDimensions::ExclusiveFullScreen {
+            let video_mode = window.current_monitor().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    (mode.size().width * mode.size().height, mode.refresh_rate_millihertz())
+                })
+            });
 
-    let frame_dt = match G::desired_fps() {
-        RefreshRate::Monitor => {
-            let monitor = window
-                .current_monitor()
-                .expect("Requested monitor refresh rate, but can't fetch window.current_monitor()");
-            let refresh_rate_millihertz = monitor.refresh_rate_millihertz().unwrap_or(60_000);
+            match video_mode {
+                Some(video_mode) => window.set_fullscreen(Some(Fullscreen::Exclusive(video_mode))),
+                None => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+            }
+        }
 
-            Duration::from_micros((1000000000.0 / refresh_rate_millihertz as f64) as u64)
-        },
-        RefreshRate::Fps(fps) => Duration::from_micros((1000000.0 / fps as f64) as u64),
-    };
+        Ok(window)
+    }
 
-    let graphics_device_window = window.clone();
-    let mut graphics_device = GraphicsDevice::new(&graphics_device_window).await;
+    fn resume(&mut self, event_loop: &ActiveEventLoop) -> Result<(), Error> {
+        let window = Arc::new(self.create_window(event_loop)?);
 
-    let mut game_app = G::init(&mut graphics_device);
+        let mut graphics_device =
+            pollster::block_on(GraphicsDevice::new(window.clone(), &self.config))?;
 
-    let mut last_frame_time = Instant::now();
+        let vsync = matches!(
+            graphics_device.surface_config().present_mode,
+            wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed | wgpu::PresentMode::AutoVsync
+        );
 
-    #[allow(deprecated)]
-    event_loop.run(move |event, window_target| {
-        match event {
-            Event::AboutToWait => {
-                window.request_redraw();
+        let (pacing, target_frame_dt) = match self.config.refresh_rate {
+            RefreshRate::Monitor => {
+                let monitor = window.current_monitor().expect(
+                    "Requested monitor refresh rate, but can't fetch window.current_monitor()",
+                );
+                let refresh_rate_millihertz = monitor.refresh_rate_millihertz().unwrap_or(60_000);
+                let frame_dt =
+                    Duration::from_micros((1000000000.0 / refresh_rate_millihertz as f64) as u64);
+
+                if vsync {
+                    (Pacing::VSync, Some(frame_dt))
+                } else {
+                    (Pacing::Limited(FramePacer::new(frame_dt)), Some(frame_dt))
+                }
+            },
+            RefreshRate::Fps(fps) => {
+                let frame_dt = Duration::from_micros((1000000.0 / fps as f64) as u64);
+                (Pacing::Limited(FramePacer::new(frame_dt)), Some(frame_dt))
             },
-            Event::WindowEvent { event: WindowEvent::Resized(new_size), .. } => {
-                graphics_device.resize(new_size);
-                game_app.resize(&mut graphics_device, new_size.width, new_size.height);
+            RefreshRate::Reactive => (Pacing::Reactive, None),
+        };
+
+        self.frame_stats.set_budget(self.config.frame_budget.or(target_frame_dt));
+
+        match &mut self.game_app {
+            Some(game_app) => {
+                if let Some(recorder) = self.suspended_recording.take() {
+                    graphics_device.resume_recording(recorder)?;
+                }
+
+                game_app.resumed(&mut graphics_device)
+            },
+            None => {
+                if let Some(mode) = &self.config.record_frames {
+                    graphics_device.start_recording(mode.clone())?;
+                }
+
+                self.game_app = Some(G::init(&mut graphics_device));
+            },
+        }
+
+        apply_input_event(
+            &mut self.input,
+            &mut self.recording,
+            InputEvent::ScaleFactorChanged(window.scale_factor()),
+        );
+        self.last_frame_time = Instant::now();
+        window.request_redraw();
+        self.running = Some(Running { window, graphics_device, pacing });
+
+        Ok(())
+    }
+}
+
+impl<G: GameApp> ApplicationHandler for App<G> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.running.is_some() {
+            return;
+        }
+
+        if let Err(err) = self.resume(event_loop) {
+            self.error = Some(err);
+            event_loop.exit();
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let (Some(game_app), Some(mut running)) = (&mut self.game_app, self.running.take()) {
+            // Recordings aren't stopped, they continue on the device created in `resume`.
+            self.suspended_recording = running.graphics_device.suspend_recording();
+
+            if let Err(err) = finish_captures(&mut running.graphics_device) {
+                self.error.get_or_insert(err);
+            }
+
+            game_app.suspended(&mut running.graphics_device);
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let (Some(game_app), Some(running)) = (&mut self.game_app, &mut self.running) else {
+            return;
+        };
+
+        if let Some(input_event) = InputEvent::from_window_event(&event) {
+            apply_input_event(&mut self.input, &mut self.recording, input_event);
+
+            if let (InputEvent::ButtonPressed(button), Some(screenshot_key)) =
+                (input_event, self.config.screenshot_key)
+            {
+                if button == screenshot_key {
+                    let path = screenshot_path(&self.config.screenshot_dir);
+                    running.graphics_device.request_screenshot(path);
+                    running.window.request_redraw();
+                }
+            }
+
+            if let Pacing::Reactive = running.pacing {
+                running.window.request_redraw();
+            }
+        }
+
+        match event {
+            WindowEvent::Resized(new_size) => {
+                running.graphics_device.resize(new_size);
+                game_app.resize(&mut running.graphics_device, new_size.width, new_size.height);
+
+                if let Pacing::Reactive = running.pacing {
+                    running.window.request_redraw();
+                }
             },
-            Event::WindowEvent { event: WindowEvent::RedrawRequested, .. } => {
-                if last_frame_time.elapsed() >= frame_dt {
-                    let now = Instant::now();
-                    last_frame_time = now;
-
-                    // TODO(bschwind) - Decouple game update ticks and rendering ticks.
-                    game_app.tick(frame_dt.as_secs_f32());
-                    game_app.render(&mut graphics_device, &window);
+            WindowEvent::RedrawRequested => {
+                let frame_start = Instant::now();
+                let elapsed = frame_start - self.last_frame_time;
+                self.last_frame_time = frame_start;
+
+                if let Pacing::Limited(frame_pacer) = &mut running.pacing {
+                    frame_pacer.frame_started(frame_start);
+                }
+
+                // Recordings advance by a fixed time per frame so they don't depend on how
+                // fast frames actually render. When the last frame wasn't presented, and so
+                // not recorded either, this one shows its ticks instead of running more.
+                let frame_dt = match running.graphics_device.recording() {
+                    Some(_) if self.hold_ticks => Duration::ZERO,
+                    Some(mode) => mode.frame_dt(),
+                    // Idle time between input events isn't simulated, otherwise the first
+                    // frame after it would run a burst of ticks.
+                    None if matches!(running.pacing, Pacing::Reactive) => {
+                        elapsed.min(self.timestep.tick_dt())
+                    },
+                    None => elapsed,
+                };
+
+                game_app.poll_input(&mut self.input_sink);
+
+                for input_event in self.input_sink.drain() {
+                    apply_input_event(&mut self.input, &mut self.recording, input_event);
+                }
+
+                let tick_dt = self.timestep.tick_dt().as_secs_f32();
+                let ticks = self.timestep.advance(frame_dt);
+
+                let mut tick_time = Duration::ZERO;
+
+                for _ in 0..ticks {
+                    let tick_start = Instant::now();
+                    game_app.tick(tick_dt, &self.input);
+                    tick_time += tick_start.elapsed();
+                    self.input.end_tick();
+
+                    if let Some(recording) = &mut self.recording {
+                        recording.end_tick(game_app.checksum());
+                    }
                 }
 
-                window.request_redraw();
+                let frames_ended = running.graphics_device.frames_ended();
+                let render_start = Instant::now();
+                game_app.render(&mut running.graphics_device, self.timestep.alpha());
+                let render_end = Instant::now();
+                self.hold_ticks = running.graphics_device.frames_ended() == frames_ended;
+
+                self.frame_stats.record(FrameTiming {
+                    frame_time: elapsed,
+                    tick_time,
+                    render_time: render_end - render_start,
+                    ticks,
+                });
+                game_app.frame_finished(&self.frame_stats);
+
+                if game_app.should_exit() {
+                    event_loop.exit();
+                }
             },
-            Event::WindowEvent { event, .. } => {
+            event => {
                 if let WindowEvent::CloseRequested = event {
-                    window_target.exit();
+                    event_loop.exit();
                 }
 
-                game_app.handle_window_event(&event, window_target);
+                game_app.handle_window_event(&event, event_loop);
             },
-            _ => (),
         }
-    })?;
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let Some(input_event) = InputEvent::from_device_event(&event) {
+            apply_input_event(&mut self.input, &mut self.recording, input_event);
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(running) = &self.running else {
+            return;
+        };
+
+        match &running.pacing {
+            Pacing::VSync => {
+                // A minimized window skips presenting, so nothing blocks until the next
+                // vblank anymore. Wake up once per tick instead of spinning.
+                let (width, height) = running.graphics_device.surface_dimensions();
+                let next_tick = self.last_frame_time + self.timestep.tick_dt();
 
-    Ok(())
+                if (width == 0 || height == 0) && Instant::now() < next_tick {
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(next_tick));
+                } else {
+                    event_loop.set_control_flow(ControlFlow::Wait);
+                    running.window.request_redraw();
+                }
+            },
+            Pacing::Limited(frame_pacer) => {
+                if Instant::now() >= frame_pacer.wake_time() {
+                    frame_pacer.wait_for_next_frame();
+                    event_loop.set_control_flow(ControlFlow::Wait);
+                    running.window.request_redraw();
+                } else {
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(frame_pacer.wake_time()));
+                }
+            },
+            Pacing::Reactive => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
 }
 
-pub fn run_game_app<G: 'static + GameApp>() -> Result<(), Error> {
-    pollster::block_on(run::<G>())?;
+fn apply_input_event(
+    input: &mut Input,
+    recording: &mut Option<InputRecording>,
+    input_event: InputEvent,
+) {
+    if let Some(recording) = recording {
+        recording.record_event(input_event);
+    }
 
-    Ok(())
+    input.apply_event(&input_event);
+}
+
+/// Writes any pending screenshots and the rest of the active recording. Failed
+/// screenshots were already reported and don't fail the game, a failed recording does.
+fn finish_captures(graphics_device: &mut GraphicsDevice) -> Result<(), Error> {
+    if graphics_device.recording().is_some() {
+        graphics_device.stop_recording()
+    } else {
+        let _ = graphics_device.finish_captures();
+        Ok(())
+    }
+}
+
+pub fn run_game_app<G: 'static + GameApp>(config: GameConfig) -> Result<(), Error> {
+    let event_loop = EventLoop::new()?;
+
+    let mut app = App::<G>::new(config);
+    event_loop.run_app(&mut app)?;
+
+    if let Some(running) = &mut app.running {
+        if let Err(err) = finish_captures(&mut running.graphics_device) {
+            app.error.get_or_insert(err);
+        }
+    }
+
+    if let Some(recorder) = app.suspended_recording.take() {
+        if let Err(err) = recorder.finish() {
+            app.error.get_or_insert(err);
+        }
+    }
+
+    if let (Some(recording), Some(path)) = (&app.recording, &app.config.record_input) {
+        recording.save(path)?;
+    }
+
+    match app.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+/// How long before a frame is due the event loop is woken up. OS timers, and with
+/// them `ControlFlow::WaitUntil`, can oversleep by a millisecond or more.
+const DEFAULT_WAKE_MARGIN: Duration = Duration::from_millis(2);
+
+/// The last stretch before a frame is due is spun instead of slept, since a sleep
+/// can't be trusted to wake up on time.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(500);
+
+/// Frame limiter which schedules frames `frame_dt` apart without pinning a CPU core.
+///
+/// The game loop waits on the event loop until `wake_time`, then calls
+/// `wait_for_next_frame` to precisely cover the remaining time by sleeping most of
+/// it and spinning the rest.
+pub struct FramePacer {
+    frame_dt: Duration,
+    wake_margin: Duration,
+    next_frame: Instant,
+}
+
+impl FramePacer {
+    pub fn new(frame_dt: Duration) -> Self {
+        Self { frame_dt, wake_margin: DEFAULT_WAKE_MARGIN, next_frame: Instant::now() }
+    }
+
+    pub fn with_wake_margin(mut self, wake_margin: Duration) -> Self {
+        self.wake_margin = wake_margin;
+        self
+    }
+
+    pub fn frame_dt(&self) -> Duration {
+        self.frame_dt
+    }
+
+    /// When the next frame should start.
+    pub fn next_frame(&self) -> Instant {
+        self.next_frame
+    }
+
+    /// When the event loop should wake up to start waiting for the next frame.
+    pub fn wake_time(&self) -> Instant {
+        self.next_frame.checked_sub(self.wake_margin).unwrap_or(self.next_frame)
+    }
+
+    /// Blocks until the next frame is due: sleeps until shortly before it, then spins.
+    pub fn wait_for_next_frame(&self) {
+        let now = Instant::now();
+
+        if self.next_frame <= now {
+            return;
+        }
+
+        let remaining = self.next_frame - now;
+
+        if remaining > SPIN_THRESHOLD {
+            std::thread::sleep(remaining - SPIN_THRESHOLD);
+        }
+
+        while Instant::now() < self.next_frame {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Schedules the next frame after one that started at `frame_start`.
+    pub fn frame_started(&mut self, frame_start: Instant) {
+        self.next_frame += self.frame_dt;
+
+        // If we've fallen more than a frame behind (a slow frame, or the window was
+        // hidden), start over instead of catching up with a burst of frames.
+        if self.next_frame < frame_start {
+            self.next_frame = frame_start + self.frame_dt;
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! A stack of game states, such as a title screen, gameplay and a pause overlay on
+//! top of it. `StateStack` implements `GameApp`, so it can be passed straight to
+//! `run_game_app`:
+//!
+//! ```ignore
+//! run_game_app::<StateStack<TitleScreen>>(GameConfig::new())?;
+//! ```
+
+use crate::{
+    graphics::{FrameEncoder, GraphicsDevice},
+    input::{Input, InputSink},
+    GameApp,
+};
+use std::{marker::PhantomData, time::Duration};
+use winit::{event::WindowEvent, event_loop::ActiveEventLoop};
+
+/// Returned by `GameState::tick` and `GameState::handle_event` to change the stack.
+pub enum Transition {
+    None,
+    /// Pauses the current state and puts a new one on top of it.
+    Push(Box<dyn GameState>),
+    /// Exits the current state and resumes the one below it. Popping the last state
+    /// exits the game.
+    Pop,
+    /// Exits the current state and replaces it with a new one.
+    Switch(Box<dyn GameState>),
+    /// Exits all states and the game.
+    Quit,
+}
+
+/// Transitions are applied by the tick which finishes the fade out, so they happen at the
+/// same tick no matter the frame rate, and in `InputRecording::replay` too. That's when
+/// `on_enter` and `on_exit` are called. GPU resources are handled in `enter` and `exit`
+/// instead, which are called before the next render.
+pub trait GameState {
+    /// Called when the state is pushed onto the stack, from the tick applying the
+    /// transition. Simulation setup goes here.
+    fn on_enter(&mut self) {}
+
+    /// Called when the state is popped off the stack or switched out, from the tick
+    /// applying the transition.
+    fn on_exit(&mut self) {}
+
+    /// Called before the first render after the state was pushed, to create its GPU
+    /// resources. The state may already have been ticked by then. Never called while
+    /// replaying, which doesn't render.
+    fn enter(&mut self, _graphics_device: &mut GraphicsDevice) {}
+
+    /// Called before the first render after the state was popped off the stack or
+    /// switched out, to release its GPU resources. Only called if `enter` was.
+    fn exit(&mut self, _graphics_device: &mut GraphicsDevice) {}
+
+    /// Called when the app is suspended, for every state whose `enter` was called,
+    /// right before `graphics_device` is dropped. Releases the GPU resources with `exit`
+    /// by default.
+    fn suspended(&mut self, graphics_device: &mut GraphicsDevice) {
+        self.exit(graphics_device);
+    }
+
+    /// Called when the app resumes with a newly created `graphics_device`, for every
+    /// state `suspended` was called for. Recreates the GPU resources with `enter` by
+    /// default.
+    fn resumed(&mut self, graphics_device: &mut GraphicsDevice) {
+        self.enter(graphics_device);
+    }
+
+    /// Called for every state on the stack, not just the top one.
+    fn resize(&mut self, _graphics_device: &mut GraphicsDevice, _width: u32, _height: u32) {}
+
+    /// Only called for the top state.
+    fn handle_event(&mut self, _event: &WindowEvent) -> Transition {
+        Transition::None
+    }
+
+    /// Called for every state on the stack at the start of every frame, see
+    /// `GameApp::poll_input`.
+    fn poll_input(&mut self, _input: &mut InputSink) {}
+
+    /// Only called for the top state, and not while it fades out.
+    fn tick(&mut self, dt: f32, input: &Input) -> Transition;
+
+    /// A hash of the simulation state, see `GameApp::checksum`. The stack combines the
+    /// checksums of all its states.
+    fn checksum(&self) -> Option<u64> {
+        None
+    }
+
+    /// Renders into a frame shared by all visible states, bottom to top.
+    /// `fade` goes from 0.0 to 1.0 while this state fades out in a transition and back
+    /// to 0.0 while the next state fades in, and is always 0.0 for states below the top.
+    fn render(
+        &mut self,
+        graphics_device: &GraphicsDevice,
+        frame_encoder: &mut FrameEncoder,
+        alpha: f32,
+        fade: f32,
+    );
+
+    /// Whether the state below this one should still be rendered, for overlays such as
+    /// a pause menu. The state below isn't ticked either way.
+    fn render_below(&self) -> bool {
+        false
+    }
+}
+
+/// The state a `StateStack` starts with.
+pub trait InitialState: GameState + Sized + 'static {
+    fn init(graphics_device: &mut GraphicsDevice) -> Self;
+
+    /// How long the outgoing state fades out for, and then how long the incoming
+    /// state fades in for.
+    fn transition_duration() -> Duration {
+        Duration::ZERO
+    }
+}
+
+enum Phase {
+    Idle,
+    /// The top state fades out, the transition is applied by the tick that finishes it.
+    Leaving {
+        transition: Transition,
+        elapsed: f32,
+    },
+    Entering {
+        elapsed: f32,
+    },
+}
+
+/// A state on the stack, and whether `GameState::enter` has been called for it yet.
+struct Entry {
+    state: Box<dyn GameState>,
+    entered: bool,
+}
+
+pub struct StateStack<I: InitialState> {
+    states: Vec<Entry>,
+    /// States which left the stack, waiting for the next render to call
+    /// `GameState::exit`.
+    exited: Vec<Entry>,
+    phase: Phase,
+    transition_duration: f32,
+    exit: bool,
+    _initial_state: PhantomData<I>,
+}
+
+impl<I: InitialState> StateStack<I> {
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// True while a state is fading out or in.
+    pub fn in_transition(&self) -> bool {
+        !matches!(self.phase, Phase::Idle)
+    }
+
+    /// See `GameState::render` for how this value changes.
+    pub fn fade(&self) -> f32 {
+        let progress = |elapsed: f32| {
+            if self.transition_duration > 0.0 {
+                (elapsed / self.transition_duration).min(1.0)
+            } else {
+                1.0
+            }
+        };
+
+        match self.phase {
+            Phase::Idle => 0.0,
+            Phase::Leaving { elapsed, .. } => progress(elapsed),
+            Phase::Entering { elapsed } => 1.0 - progress(elapsed),
+        }
+    }
+
+    fn request(&mut self, transition: Transition) {
+        // The first transition wins until it has been applied.
+        if matches!(transition, Transition::None) || matches!(self.phase, Phase::Leaving { .. }) {
+            return;
+        }
+
+        self.phase = Phase::Leaving { transition, elapsed: 0.0 };
+    }
+
+    /// Applies the pending transition once the fade out is done, then fades the new
+    /// top state in.
+    fn finish_leaving(&mut self) {
+        match self.phase {
+            Phase::Leaving { elapsed, .. } if elapsed >= self.transition_duration => {},
+            _ => return,
+        }
+
+        if let Phase::Leaving { transition, .. } = std::mem::replace(&mut self.phase, Phase::Idle) {
+            self.apply(transition);
+        }
+
+        if self.transition_duration > 0.0 && !self.exit {
+            self.phase = Phase::Entering { elapsed: 0.0 };
+        }
+    }
+
+    fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {},
+            Transition::Push(state) => self.push(state),
+            Transition::Pop => self.pop(),
+            Transition::Switch(state) => {
+                self.pop();
+                self.push(state);
+            },
+            Transition::Quit => {
+                while !self.states.is_empty() {
+                    self.pop();
+                }
+            },
+        }
+
+        if self.states.is_empty() {
+            self.exit = true;
+        }
+    }
+
+    fn push(&mut self, mut state: Box<dyn GameState>) {
+        state.on_enter();
+        self.states.push(Entry { state, entered: false });
+    }
+
+    fn pop(&mut self) {
+        if let Some(mut entry) = self.states.pop() {
+            entry.state.on_exit();
+            self.exited.push(entry);
+        }
+    }
+
+    /// Catches up on the `GameState::exit` and `GameState::enter` calls of the
+    /// transitions applied since the last render.
+    fn update_graphics(&mut self, graphics_device: &mut GraphicsDevice) {
+        for mut entry in self.exited.drain(..) {
+            if entry.entered {
+                entry.state.exit(graphics_device);
+            }
+        }
+
+        for entry in self.states.iter_mut().filter(|entry| !entry.entered) {
+            entry.state.enter(graphics_device);
+            entry.entered = true;
+        }
+    }
+}
+
+impl<I: InitialState> GameApp for StateStack<I> {
+    fn handle_window_event(&mut self, event: &WindowEvent, _event_loop: &ActiveEventLoop) {
+        if let Some(entry) = self.states.last_mut() {
+            let transition = entry.state.handle_event(event);
+            self.request(transition);
+        }
+    }
+
+    fn init(graphics_device: &mut GraphicsDevice) -> Self {
+        let mut initial_state = I::init(graphics_device);
+        initial_state.on_enter();
+        initial_state.enter(graphics_device);
+
+        Self {
+            states: vec![Entry { state: Box::new(initial_state), entered: true }],
+            exited: Vec::new(),
+            phase: Phase::Idle,
+            transition_duration: I::transition_duration().as_secs_f32(),
+            exit: false,
+            _initial_state: PhantomData,
+        }
+    }
+
+    fn resize(&mut self, graphics_device: &mut GraphicsDevice, width: u32, height: u32) {
+        for entry in &mut self.states {
+            entry.state.resize(graphics_device, width, height);
+        }
+    }
+
+    fn suspended(&mut self, graphics_device: &mut GraphicsDevice) {
+        // States which already left the stack exit on the device they entered on.
+        for mut entry in self.exited.drain(..) {
+            if entry.entered {
+                entry.state.exit(graphics_device);
+            }
+        }
+
+        for entry in self.states.iter_mut().rev().filter(|entry| entry.entered) {
+            entry.state.suspended(graphics_device);
+        }
+    }
+
+    fn resumed(&mut self, graphics_device: &mut GraphicsDevice) {
+        // States which were never entered are entered by the next render as usual.
+        for entry in self.states.iter_mut().filter(|entry| entry.entered) {
+            entry.state.resumed(graphics_device);
+        }
+    }
+
+    fn poll_input(&mut self, input: &mut InputSink) {
+        for entry in &mut self.states {
+            entry.state.poll_input(input);
+        }
+    }
+
+    fn tick(&mut self, dt: f32, input: &Input) {
+        match &mut self.phase {
+            Phase::Idle => {},
+            Phase::Leaving { elapsed, .. } => {
+                *elapsed += dt;
+                self.finish_leaving();
+                return;
+            },
+            Phase::Entering { elapsed } => {
+                *elapsed += dt;
+
+                if *elapsed >= self.transition_duration {
+                    self.phase = Phase::Idle;
+                }
+            },
+        }
+
+        if let Some(entry) = self.states.last_mut() {
+            let transition = entry.state.tick(dt, input);
+            self.request(transition);
+
+            // Transitions without a fade are applied right away.
+            self.finish_leaving();
+        }
+    }
+
+    fn render(&mut self, graphics_device: &mut GraphicsDevice, alpha: f32) {
+        self.update_graphics(graphics_device);
+
+        let Some(top) = self.states.len().checked_sub(1) else {
+            return;
+        };
+
+        let mut bottom = top;
+        while bottom > 0 && self.states[bottom].state.render_below() {
+            bottom -= 1;
+        }
+
+        let mut frame_encoder = match graphics_device.begin_frame() {
+            Ok(Some(frame_encoder)) => frame_encoder,
+            Ok(None) => return,
+            Err(err) => {
+                println!("Failed to begin frame: {}", err);
+                return;
+            },
+        };
+
+        let fade = self.fade();
+
+        for (i, entry) in self.states[bottom..].iter_mut().enumerate() {
+            let fade = if bottom + i == top { fade } else { 0.0 };
+            entry.state.render(graphics_device, &mut frame_encoder, alpha, fade);
+        }
+
+        graphics_device.end_frame(frame_encoder);
+    }
+
+    fn checksum(&self) -> Option<u64> {
+        let checksums: Vec<_> = self.states.iter().map(|entry| entry.state.checksum()).collect();
+
+        if checksums.iter().all(Option::is_none) {
+            return None;
+        }
+
+        // FNV style over the checksum of every state, so pushing or popping one changes it
+        // too.
+        let combined = checksums.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, checksum| {
+            (hash ^ checksum.unwrap_or(0)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        Some(combined)
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// How many frames `FrameStats` keeps by default, 4 seconds at 60 FPS.
+const DEFAULT_CAPACITY: usize = 240;
+
+/// CPU timings of a single frame.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FrameTiming {
+    /// Time since the previous frame started.
+    pub frame_time: Duration,
+    /// Time spent in all of this frame's `GameApp::tick` calls.
+    pub tick_time: Duration,
+    /// Time spent in `GameApp::render`, including waiting for the next surface texture.
+    pub render_time: Duration,
+    /// How many ticks ran this frame.
+    pub ticks: usize,
+}
+
+impl FrameTiming {
+    /// The time spent on the frame's own work, excluding time spent waiting for it to start.
+    pub fn work_time(&self) -> Duration {
+        self.tick_time + self.render_time
+    }
+}
+
+/// Statistics for a set of durations.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct TimingSummary {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl TimingSummary {
+    /// Summarizes `durations`, all zero if it's empty. Percentiles use the nearest rank.
+    pub fn new<I: IntoIterator<Item = Duration>>(durations: I) -> Self {
+        let mut durations: Vec<Duration> = durations.into_iter().collect();
+
+        if durations.is_empty() {
+            return Self::default();
+        }
+
+        durations.sort_unstable();
+
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * durations.len() as f64).ceil() as usize;
+            durations[rank.clamp(1, durations.len()) - 1]
+        };
+
+        Self {
+            min: durations[0],
+            max: durations[durations.len() - 1],
+            mean: durations.iter().sum::<Duration>() / durations.len() as u32,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        }
+    }
+}
+
+/// Collects `FrameTiming`s for the most recent frames in a ring buffer.
+///
+/// The game loop records every frame and passes the stats to `GameApp::frame_finished`.
+/// A frame is over budget when its `work_time` exceeds the budget, which by default is
+/// the target frame time of the configured `RefreshRate`.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    history: VecDeque<FrameTiming>,
+    capacity: usize,
+    budget: Option<Duration>,
+    total_frames: u64,
+    total_frames_over_budget: u64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "expecting a positive capacity");
+
+        Self {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            budget: None,
+            total_frames: 0,
+            total_frames_over_budget: 0,
+        }
+    }
+
+    pub fn record(&mut self, timing: FrameTiming) {
+        if self.history.len() >= self.capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(timing);
+        self.total_frames += 1;
+
+        if self.is_over_budget(&timing) {
+            self.total_frames_over_budget += 1;
+        }
+    }
+
+    /// The most recent frames, oldest first.
+    pub fn history(&self) -> &VecDeque<FrameTiming> {
+        &self.history
+    }
+
+    pub fn last(&self) -> Option<&FrameTiming> {
+        self.history.back()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn budget(&self) -> Option<Duration> {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: Option<Duration>) {
+        self.budget = budget;
+    }
+
+    pub fn is_over_budget(&self, timing: &FrameTiming) -> bool {
+        self.budget.is_some_and(|budget| timing.work_time() > budget)
+    }
+
+    pub fn frame_time(&self) -> TimingSummary {
+        TimingSummary::new(self.history.iter().map(|timing| timing.frame_time))
+    }
+
+    pub fn tick_time(&self) -> TimingSummary {
+        TimingSummary::new(self.history.iter().map(|timing| timing.tick_time))
+    }
+
+    pub fn render_time(&self) -> TimingSummary {
+        TimingSummary::new(self.history.iter().map(|timing| timing.render_time))
+    }
+
+    /// Mean frames per second over the recorded frames.
+    pub fn fps(&self) -> f64 {
+        let mean = self.frame_time().mean;
+
+        if mean.is_zero() {
+            0.0
+        } else {
+            1.0 / mean.as_secs_f64()
+        }
+    }
+
+    /// Frames over budget among the recorded frames.
+    pub fn frames_over_budget(&self) -> usize {
+        self.history.iter().filter(|timing| self.is_over_budget(timing)).count()
+    }
+
+    /// Frames over budget since the stats were created or cleared.
+    pub fn total_frames_over_budget(&self) -> u64 {
+        self.total_frames_over_budget
+    }
+
+    /// Frames recorded since the stats were created or cleared.
+    pub fn total_frames(&self) -> u64 {
+        self.total_frames
+    }
+
+    /// Starts over, for example when a new level is loaded. Keeps the budget.
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.total_frames = 0;
+        self.total_frames_over_budget = 0;
+    }
+}
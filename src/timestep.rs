@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+/// The longest frame time fed into the accumulator. Anything longer (a debugger
+/// pause, a window drag, a slow frame) is clamped so the simulation doesn't try
+/// to catch up with an ever-growing number of ticks.
+const DEFAULT_MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// Fixed-timestep accumulator which decouples the simulation tick rate from
+/// the render rate.
+/// Reference: https://gafferongames.com/post/fix_your_timestep/
+pub struct FixedTimestep {
+    tick_dt: Duration,
+    max_frame_time: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0, "expecting a positive tick rate");
+
+        Self {
+            tick_dt: Duration::from_secs(1) / ticks_per_second,
+            max_frame_time: DEFAULT_MAX_FRAME_TIME,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub fn with_max_frame_time(mut self, max_frame_time: Duration) -> Self {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    /// Adds the elapsed frame time to the accumulator and returns how many
+    /// ticks should be run this frame. Can return 0.
+    pub fn advance(&mut self, frame_time: Duration) -> usize {
+        self.accumulator += frame_time.min(self.max_frame_time);
+
+        let mut ticks = 0;
+
+        while self.accumulator >= self.tick_dt {
+            self.accumulator -= self.tick_dt;
+            ticks += 1;
+        }
+
+        ticks
+    }
+
+    /// How far along we are between the previous tick and the next one,
+    /// in the range [0.0, 1.0). Used to interpolate state when rendering.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick_dt.as_secs_f32()
+    }
+
+    pub fn tick_dt(&self) -> Duration {
+        self.tick_dt
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fs::File, io::BufWriter, path::Path};
 
 struct RollingAverage {
     items: VecDeque<f64>,
@@ -49,3 +49,21 @@ impl FPSCounter {
         self.rolling_avg.avg().ceil() as usize
     }
 }
+
+/// Writes tightly packed RGBA8 pixels to a PNG file.
+pub fn write_png_rgba<P: AsRef<Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), png::EncodingError> {
+    let file = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()
+}
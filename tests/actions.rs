@@ -0,0 +1,145 @@
+use simple_game::{
+    input::{
+        ActionMap, AxisBinding, Button, Chord, GamepadAxis, GamepadButton, Input, InputEvent,
+        InputSink,
+    },
+    Error,
+};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+const BINDINGS: &str = "
+# Chords are joined with '+', alternative bindings with '|'.
+action jump = Space | Gamepad:South
+action quick_save = ControlLeft+KeyS
+action shoot = Mouse:Left
+
+axis move_x dead_zone=0.2 = KeyA/KeyD | Gamepad:LeftStickX
+axis move_y = KeyS/KeyW | -Gamepad:RightStickY
+";
+
+fn press(input: &mut Input, button: impl Into<Button>) {
+    input.apply_event(&InputEvent::ButtonPressed(button.into()));
+}
+
+fn release(input: &mut Input, button: impl Into<Button>) {
+    input.apply_event(&InputEvent::ButtonReleased(button.into()));
+}
+
+#[test]
+fn bindings_round_trip_through_text() {
+    let actions: ActionMap = BINDINGS.parse().unwrap();
+
+    assert_eq!(actions.actions().collect::<Vec<_>>(), ["jump", "quick_save", "shoot"]);
+    assert_eq!(
+        actions.action_bindings("jump"),
+        [Chord::from(KeyCode::Space), Chord::from(GamepadButton::South)]
+    );
+    assert_eq!(
+        actions.action_bindings("quick_save"),
+        [Chord::new([Button::Key(KeyCode::ControlLeft), Button::Key(KeyCode::KeyS)])]
+    );
+    assert_eq!(actions.action_bindings("shoot"), [Chord::from(MouseButton::Left)]);
+    assert_eq!(
+        actions.axis_bindings("move_y"),
+        [
+            AxisBinding::Buttons {
+                negative: Button::Key(KeyCode::KeyS),
+                positive: Button::Key(KeyCode::KeyW)
+            },
+            AxisBinding::Gamepad { axis: GamepadAxis::RightStickY, inverted: true },
+        ]
+    );
+    assert_eq!(actions.dead_zone("move_x"), 0.2);
+    assert_eq!(actions.dead_zone("move_y"), 0.15);
+
+    let saved = actions.to_string();
+    assert_eq!(saved.parse::<ActionMap>().unwrap(), actions);
+
+    // Rebinding at runtime survives a save too.
+    let mut rebound = actions.clone();
+    rebound.set_action_bindings("jump", vec![Chord::from(MouseButton::Other(4))]);
+    rebound.set_dead_zone("move_y", 0.3);
+    assert_eq!(rebound.to_string().parse::<ActionMap>().unwrap(), rebound);
+    assert_ne!(rebound, actions);
+}
+
+#[test]
+fn parse_errors_report_their_line() {
+    let line_of = |text: &str| match text.parse::<ActionMap>() {
+        Err(Error::BindingsParseError { line, .. }) => line,
+        other => panic!("expected a parse error, got {:?}", other),
+    };
+
+    assert_eq!(line_of("action jump = Space\naction shoot Mouse:Left"), 2);
+    assert_eq!(line_of("# comment\n\naction jump = NotAKey"), 3);
+    assert_eq!(line_of("action jump = Space+"), 1);
+    assert_eq!(line_of("button jump = Space"), 1);
+    assert_eq!(line_of("action jump fast = Space"), 1);
+    assert_eq!(line_of("axis move = KeyA/KeyD\naxis look = Gamepad:Nope"), 2);
+    assert_eq!(line_of("axis move dead_zone=lots = KeyA/KeyD"), 1);
+    assert_eq!(line_of("action = Space"), 1);
+}
+
+#[test]
+fn chords_need_every_button() {
+    let actions: ActionMap = BINDINGS.parse().unwrap();
+    let mut input = Input::new();
+
+    press(&mut input, KeyCode::ControlLeft);
+    assert!(!actions.is_down(&input, "quick_save"));
+    input.end_tick();
+
+    press(&mut input, KeyCode::KeyS);
+    assert!(actions.is_down(&input, "quick_save"));
+    assert!(actions.just_pressed(&input, "quick_save"));
+    input.end_tick();
+
+    assert!(actions.is_down(&input, "quick_save"));
+    assert!(!actions.just_pressed(&input, "quick_save"));
+
+    release(&mut input, KeyCode::ControlLeft);
+    assert!(actions.just_released(&input, "quick_save"));
+    assert!(!actions.is_down(&input, "quick_save"));
+    input.end_tick();
+
+    // Holding one alternative while pressing another isn't a new press. Gamepads
+    // reach the game loop through an `InputSink`.
+    press(&mut input, KeyCode::Space);
+    input.end_tick();
+
+    let mut sink = InputSink::new();
+    sink.gamepad_button(GamepadButton::South, true);
+    sink.drain().for_each(|event| input.apply_event(&event));
+    assert!(actions.is_down(&input, "jump"));
+    assert!(!actions.just_pressed(&input, "jump"));
+
+    assert!(!actions.is_down(&input, "unbound"));
+}
+
+#[test]
+fn axes_apply_their_dead_zone() {
+    let actions: ActionMap = BINDINGS.parse().unwrap();
+    let mut input = Input::new();
+
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.19);
+    assert_eq!(actions.axis(&input, "move_x"), 0.0);
+
+    // The rest of the range is rescaled to start at zero.
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, -0.6);
+    assert!((actions.axis(&input, "move_x") + 0.5).abs() < 1e-6);
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 1.0);
+    assert_eq!(actions.axis(&input, "move_x"), 1.0);
+
+    // The largest magnitude wins, ties go to the first binding.
+    press(&mut input, KeyCode::KeyA);
+    assert_eq!(actions.axis(&input, "move_x"), -1.0);
+    input.set_gamepad_axis(GamepadAxis::LeftStickX, 0.6);
+    assert_eq!(actions.axis(&input, "move_x"), -1.0);
+    press(&mut input, KeyCode::KeyD);
+    assert!((actions.axis(&input, "move_x") - 0.5).abs() < 1e-6);
+
+    input.set_gamepad_axis(GamepadAxis::RightStickY, 0.575);
+    assert!((actions.axis(&input, "move_y") + 0.5).abs() < 1e-6);
+
+    assert_eq!(actions.axis(&input, "unbound"), 0.0);
+}
@@ -0,0 +1,149 @@
+use simple_game::{
+    audio::{Audio, BusId, Mixer, NullBackend, PlaybackParams, Sound, WavFileBackend},
+    Error,
+};
+use std::io::Cursor;
+
+const SAMPLE_RATE: u32 = 48_000;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-4, "expected {}, got {}", expected, actual);
+}
+
+fn mix(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+    let mut output = vec![0.0; frames * 2];
+    mixer.mix(&mut output);
+    output
+}
+
+#[test]
+fn wav_files_are_decoded_to_floats() {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 22_050,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+
+    for sample in [16384i16, -16384, 32767, i16::MIN] {
+        writer.write_sample(sample).unwrap();
+    }
+
+    writer.finalize().unwrap();
+
+    let sound = Sound::from_bytes(bytes.get_ref()).unwrap();
+    assert_eq!((sound.sample_rate(), sound.channels(), sound.frame_count()), (22_050, 2, 2));
+    assert_eq!(sound.samples()[..2], [0.5, -0.5]);
+    assert_eq!(sound.samples()[3], -1.0);
+
+    assert!(Sound::from_bytes(b"OggS not really").is_err());
+    assert!(Sound::from_bytes(b"ID3").is_err());
+
+    // Broken headers are errors instead of panics. The byte rate is zeroed along with
+    // the sample rate to keep the fmt chunk consistent.
+    let mut zero_rate = bytes.into_inner();
+    zero_rate[24..32].fill(0);
+    assert!(matches!(
+        Sound::from_bytes(&zero_rate),
+        Err(Error::InvalidAudioHeader { sample_rate: 0, channels: 2 })
+    ));
+}
+
+#[test]
+fn voices_are_panned_mixed_and_removed_when_they_end() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let sound = Sound::from_samples(SAMPLE_RATE, 1, vec![0.5; 100]);
+
+    // Equal power panning, a centered mono voice plays at -3 dB on each side.
+    let voice = mixer.play(&sound, PlaybackParams::new());
+    let output = mix(&mut mixer, 10);
+    assert_close(output[0], 0.5 * std::f32::consts::FRAC_1_SQRT_2);
+    assert_close(output[1], 0.5 * std::f32::consts::FRAC_1_SQRT_2);
+
+    mixer.set_pan(voice, -1.0);
+    mixer.set_volume(voice, 0.5);
+    let output = mix(&mut mixer, 10);
+    assert_close(output[0], 0.25);
+    assert_close(output[1], 0.0);
+
+    // 80 frames are left, the rest of the buffer is silent.
+    let output = mix(&mut mixer, 100);
+    assert_close(output[79 * 2], 0.25);
+    assert_close(output[80 * 2], 0.0);
+    assert!(!mixer.is_playing(voice));
+    assert_eq!(mixer.voice_count(), 0);
+}
+
+#[test]
+fn pitch_and_sample_rate_change_the_playback_speed() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+
+    // A ramp at half the mixer's sample rate, played twice as fast.
+    let ramp: Vec<f32> = (0..100).map(|i| i as f32 / 100.0).collect();
+    let sound = Sound::from_samples(SAMPLE_RATE / 2, 1, ramp);
+    let voice = mixer.play(&sound, PlaybackParams::new().pitch(2.0).pan(1.0));
+
+    let output = mix(&mut mixer, 50);
+    assert_close(output[10 * 2 + 1], 0.1);
+    assert_close(output[49 * 2 + 1], 0.49);
+    assert!(mixer.is_playing(voice));
+
+    // Half speed interpolates between the samples.
+    mixer.set_pitch(voice, 0.5);
+    let output = mix(&mut mixer, 2);
+    assert_close(output[1], 0.50);
+    assert_close(output[3], 0.5025);
+}
+
+#[test]
+fn buses_apply_their_volume_to_their_children() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let sfx = mixer.add_bus("sfx", BusId::MASTER);
+    let ui = mixer.add_bus("ui", sfx);
+    assert_eq!(mixer.bus("ui"), Some(ui));
+
+    mixer.set_bus_volume(BusId::MASTER, 0.5);
+    mixer.set_bus_volume(sfx, 0.5);
+
+    let sound = Sound::from_samples(SAMPLE_RATE, 2, vec![1.0; 20]);
+    mixer.play(&sound, PlaybackParams::new().bus(ui).looping(true));
+    mixer.play(&sound, PlaybackParams::new().looping(true));
+
+    // The master bus plays at 0.5, the ui bus at 0.25.
+    let output = mix(&mut mixer, 100);
+    assert!(output.iter().all(|&sample| (sample - 0.75).abs() < 1e-6));
+    assert_eq!(mixer.voice_count(), 2);
+
+    mixer.stop_all();
+    assert!(mix(&mut mixer, 10).iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn offline_backends_render_on_demand() {
+    let sound = Sound::from_samples(SAMPLE_RATE, 1, vec![0.25; 1000]);
+
+    let mut audio = Audio::new(NullBackend::new(SAMPLE_RATE)).unwrap();
+    let voice = audio.play(&sound, PlaybackParams::new().pan(-1.0));
+    audio.render(600).unwrap();
+    assert!(audio.mixer().is_playing(voice));
+    audio.render(600).unwrap();
+    assert!(!audio.mixer().is_playing(voice));
+
+    let path = std::env::temp_dir().join(format!("simple-game-audio-{}.wav", std::process::id()));
+    let mut audio = Audio::new(WavFileBackend::create(&path, SAMPLE_RATE).unwrap()).unwrap();
+    audio.play(&sound, PlaybackParams::new().pan(-1.0));
+    audio.render(1500).unwrap();
+    audio.finish().unwrap();
+
+    let written = Sound::load(&path).unwrap();
+    assert_eq!((written.sample_rate(), written.channels()), (SAMPLE_RATE, 2));
+    assert_eq!(written.frame_count(), 1500);
+    assert_close(written.samples()[0], 0.25);
+    assert_close(written.samples()[1], 0.0);
+    assert_close(written.samples()[1000 * 2], 0.0);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,108 @@
+use glam::{vec2, Vec2};
+use simple_game::graphics::{screen_projection_matrix, Camera2D, Viewport};
+use std::f32::consts::FRAC_PI_2;
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(actual.abs_diff_eq(expected, 1e-3), "expected {}, got {}", expected, actual);
+}
+
+#[test]
+fn fresh_cameras_match_the_screen_projection() {
+    let camera = Camera2D::new(800, 600);
+
+    assert!(camera.matrix().abs_diff_eq(screen_projection_matrix(800, 600), 1e-6));
+    assert_near(camera.world_to_screen(vec2(12.0, 34.0)), vec2(12.0, 34.0));
+    assert_near(camera.screen_to_world(vec2(12.0, 34.0)), vec2(12.0, 34.0));
+}
+
+#[test]
+fn screen_and_world_positions_convert_both_ways() {
+    let mut camera = Camera2D::new(800, 600).with_position(vec2(100.0, 50.0)).with_zoom(2.0);
+    assert_near(camera.world_to_screen(vec2(100.0, 50.0)), vec2(400.0, 300.0));
+    assert_near(camera.world_to_screen(vec2(110.0, 50.0)), vec2(420.0, 300.0));
+
+    // A clockwise camera turns the world counter-clockwise, +x now points up the screen.
+    camera.set_rotation(FRAC_PI_2);
+    assert_near(camera.world_to_screen(vec2(110.0, 50.0)), vec2(400.0, 280.0));
+
+    // Viewports offset the screen position.
+    camera.set_viewport(Viewport { x: 100.0, y: 50.0, width: 800.0, height: 600.0 });
+    assert_near(camera.world_to_screen(vec2(110.0, 50.0)), vec2(500.0, 330.0));
+
+    for screen_position in [vec2(0.0, 0.0), vec2(500.0, 330.0), vec2(123.0, 456.0)] {
+        let world_position = camera.screen_to_world(screen_position);
+        assert_near(camera.world_to_screen(world_position), screen_position);
+    }
+
+    // The matrix maps the viewport's center to the center of clip space.
+    let clip = camera.matrix().project_point3(vec2(100.0, 50.0).extend(0.0));
+    assert_near(clip.truncate(), Vec2::ZERO);
+
+    // 20 world units tall, like the default `DebugDrawer` view.
+    let camera = Camera2D::new(800, 600).with_position(Vec2::ZERO).with_view_height(20.0);
+    assert_near(camera.world_to_screen(vec2(0.0, 10.0)), vec2(400.0, 600.0));
+}
+
+#[test]
+fn bounds_keep_the_view_inside() {
+    let mut camera = Camera2D::new(800, 600)
+        .with_bounds(Vec2::ZERO, vec2(1000.0, 1000.0))
+        .with_position(Vec2::ZERO);
+    assert_near(camera.position(), vec2(400.0, 300.0));
+
+    camera.set_position(vec2(2000.0, 2000.0));
+    assert_near(camera.position(), vec2(600.0, 700.0));
+
+    // Zoomed out beyond the bounds, the view is centered on them.
+    camera.set_zoom(0.5);
+    assert_near(camera.position(), vec2(500.0, 500.0));
+
+    camera.set_zoom(1.0);
+    camera.follow(vec2(-500.0, 500.0));
+    camera.update(1.0 / 60.0);
+    assert_near(camera.position(), vec2(400.0, 500.0));
+}
+
+#[test]
+fn following_is_smoothed() {
+    let mut camera = Camera2D::new(800, 600).with_position(Vec2::ZERO).with_smoothing(0.5);
+    camera.follow(vec2(100.0, 0.0));
+
+    camera.update(0.5);
+    assert_near(camera.position(), vec2(50.0, 0.0));
+    camera.update(0.25);
+    camera.update(0.25);
+    assert_near(camera.position(), vec2(75.0, 0.0));
+
+    // Without smoothing the camera snaps to the target.
+    let mut camera = Camera2D::new(800, 600);
+    camera.follow(vec2(100.0, 0.0));
+    camera.update(1.0 / 60.0);
+    assert_near(camera.position(), vec2(100.0, 0.0));
+}
+
+#[test]
+fn trauma_shakes_the_camera_until_it_wears_off() {
+    let steady = Camera2D::new(800, 600).with_shake(10.0, 0.1, 2.0);
+    let mut camera = steady.clone();
+    camera.add_trauma(0.4);
+    camera.add_trauma(0.4);
+    assert_eq!(camera.trauma(), 0.8);
+
+    camera.update(0.1);
+    assert!((camera.trauma() - 0.6).abs() < 1e-6);
+    assert!(!camera.matrix().abs_diff_eq(steady.matrix(), 1e-4));
+
+    // The shake only depends on the updates, so replays shake the same way.
+    let mut replay = steady.clone();
+    replay.add_trauma(0.8);
+    replay.update(0.1);
+    assert_eq!(replay.matrix(), camera.matrix());
+
+    // Shaking never moves the camera itself.
+    assert_eq!(camera.position(), steady.position());
+
+    camera.update(0.5);
+    assert_eq!(camera.trauma(), 0.0);
+    assert!(camera.matrix().abs_diff_eq(steady.matrix(), 1e-6));
+}
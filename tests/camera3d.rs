@@ -0,0 +1,159 @@
+use glam::{vec2, vec3, Vec3};
+use simple_game::{
+    graphics::{Camera3D, FlyController, OrbitController, Projection, Viewport},
+    input::{Button, Input, InputEvent},
+};
+use std::f32::consts::FRAC_PI_2;
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!(actual.abs_diff_eq(expected, 1e-3), "expected {}, got {}", expected, actual);
+}
+
+fn depth(camera: &Camera3D, position: Vec3) -> f32 {
+    camera.matrix().project_point3(position).z
+}
+
+fn input(events: &[InputEvent]) -> Input {
+    let mut input = Input::new();
+
+    for event in events {
+        input.apply_event(event);
+    }
+
+    input
+}
+
+#[test]
+fn reverse_z_flips_the_depth_range() {
+    let projection = Projection::Perspective { fov_y: FRAC_PI_2, near: 1.0, far: 100.0 };
+    let camera = Camera3D::new(800, 600, projection);
+    assert!((depth(&camera, vec3(0.0, 0.0, -1.0))).abs() < 1e-5);
+    assert!((depth(&camera, vec3(0.0, 0.0, -100.0)) - 1.0).abs() < 1e-5);
+    assert_eq!(camera.depth_compare(), wgpu::CompareFunction::Less);
+
+    let camera = camera.with_reverse_z(true);
+    assert!((depth(&camera, vec3(0.0, 0.0, -1.0)) - 1.0).abs() < 1e-5);
+    assert!((depth(&camera, vec3(0.0, 0.0, -100.0))).abs() < 1e-5);
+    assert_eq!(camera.depth_compare(), wgpu::CompareFunction::Greater);
+    assert_eq!(camera.depth_clear_value(), 0.0);
+
+    // Without a far plane, depth approaches zero in the distance.
+    let infinite = Projection::Perspective { fov_y: FRAC_PI_2, near: 1.0, far: f32::INFINITY };
+    let camera = camera.with_projection(infinite);
+    assert!((depth(&camera, vec3(0.0, 0.0, -1.0)) - 1.0).abs() < 1e-5);
+    assert!(depth(&camera, vec3(0.0, 0.0, -1e6)) < 1e-5);
+
+    let projection = Projection::Orthographic { height: 10.0, near: 0.0, far: 10.0 };
+    let camera = Camera3D::new(800, 600, projection).with_reverse_z(true);
+    assert!((depth(&camera, vec3(0.0, 5.0, -2.5)) - 0.75).abs() < 1e-5);
+    assert!((camera.matrix().project_point3(vec3(0.0, 5.0, -2.5)).y - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn screen_rays_pass_through_their_pixels() {
+    let camera = Camera3D::perspective(800, 600, 1.0)
+        .with_position(vec3(3.0, 4.0, 5.0))
+        .with_target(Vec3::ZERO)
+        .with_viewport(Viewport { x: 100.0, y: 50.0, width: 800.0, height: 600.0 });
+    assert_near(camera.forward(), -vec3(3.0, 4.0, 5.0).normalize());
+    assert!(camera.right().y.abs() < 1e-6);
+
+    let center = camera.screen_ray(vec2(500.0, 350.0));
+    assert_near(center.intersect_plane(Vec3::ZERO, Vec3::Y).unwrap(), Vec3::ZERO);
+    assert!(center.intersect_plane(vec3(0.0, 10.0, 0.0), Vec3::Y).is_none());
+
+    for screen_position in [vec2(100.0, 50.0), vec2(321.0, 456.0), vec2(899.0, 649.0)] {
+        let ray = camera.screen_ray(screen_position);
+        let projected = camera.world_to_screen(ray.at(7.0)).unwrap();
+        assert!(projected.abs_diff_eq(screen_position, 1e-2), "got {}", projected);
+    }
+
+    assert!(camera.world_to_screen(vec3(6.0, 8.0, 10.0)).is_none());
+
+    // Orthographic rays are parallel, and start on the camera's plane.
+    let camera = Camera3D::orthographic(800, 600, 6.0).with_position(vec3(0.0, 0.0, 10.0));
+    let ray = camera.screen_ray(vec2(800.0, 0.0));
+    assert_near(ray.origin, vec3(4.0, 3.0, 10.0));
+    assert_near(ray.direction, Vec3::NEG_Z);
+    assert_eq!(camera.world_units_per_pixel(1.0), camera.world_units_per_pixel(100.0));
+}
+
+#[test]
+fn orbiting_keeps_the_camera_on_the_target() {
+    let mut camera = Camera3D::perspective(800, 600, 1.0).with_position(vec3(0.0, 0.0, 10.0));
+    let mut orbit = OrbitController::new(&camera, Vec3::ZERO);
+    assert_eq!((orbit.yaw(), orbit.pitch(), orbit.distance()), (0.0, 0.0, 10.0));
+
+    // Dragging a quarter turn to the left moves the camera around to +x.
+    let drag = input(&[
+        InputEvent::ButtonPressed(Button::Mouse(MouseButton::Left)),
+        InputEvent::MouseMotion(vec2(-FRAC_PI_2 / 0.005, 0.0)),
+    ]);
+    orbit.update(&drag, &mut camera);
+    assert_near(camera.position(), vec3(10.0, 0.0, 0.0));
+    assert_near(camera.forward(), Vec3::NEG_X);
+
+    // Motion without the button held does nothing, and pitch stops short of the poles.
+    orbit.update(&input(&[InputEvent::MouseMotion(vec2(0.0, 1000.0))]), &mut camera);
+    assert_near(camera.position(), vec3(10.0, 0.0, 0.0));
+    orbit.update(
+        &input(&[
+            InputEvent::ButtonPressed(Button::Mouse(MouseButton::Left)),
+            InputEvent::MouseMotion(vec2(0.0, 1000.0)),
+        ]),
+        &mut camera,
+    );
+    assert!(orbit.pitch() < FRAC_PI_2 && camera.position().y > 9.99);
+    assert!(camera.forward().is_finite());
+
+    orbit.set_angles(0.0, 0.0);
+    orbit.update(&input(&[InputEvent::Scroll(vec2(0.0, 2.0))]), &mut camera);
+    assert!((orbit.distance() - 8.1).abs() < 1e-4);
+
+    // Panning drags the target along, keeping what's under the cursor under it.
+    let pixels = 100.0;
+    let pan = input(&[
+        InputEvent::ButtonPressed(Button::Mouse(MouseButton::Middle)),
+        InputEvent::MouseMotion(vec2(pixels, 0.0)),
+    ]);
+    let expected = -pixels * camera.world_units_per_pixel(orbit.distance());
+    orbit.update(&pan, &mut camera);
+    assert_near(orbit.target(), vec3(expected, 0.0, 0.0));
+    assert_near(camera.position(), vec3(expected, 0.0, 8.1));
+}
+
+#[test]
+fn flying_moves_where_the_camera_looks() {
+    let mut camera = Camera3D::perspective(800, 600, 1.0)
+        .with_position(vec3(0.0, 0.0, 10.0))
+        .with_target(vec3(-10.0, 0.0, 10.0));
+    let mut fly = FlyController::new(&camera).with_speed(2.0);
+    assert!((fly.yaw() - FRAC_PI_2).abs() < 1e-5);
+
+    let forward = input(&[InputEvent::ButtonPressed(Button::Key(KeyCode::KeyW))]);
+    fly.update(&forward, &mut camera, 0.5);
+    assert_near(camera.position(), vec3(-1.0, 0.0, 10.0));
+
+    let boosted = input(&[
+        InputEvent::ButtonPressed(Button::Key(KeyCode::KeyE)),
+        InputEvent::ButtonPressed(Button::Key(KeyCode::ShiftLeft)),
+    ]);
+    fly.update(&boosted, &mut camera, 0.5);
+    assert_near(camera.position(), vec3(-1.0, 4.0, 10.0));
+
+    // Looking around needs the right mouse button.
+    let look = [InputEvent::MouseMotion(vec2(0.0, 0.1 / 0.003))];
+    fly.update(&input(&look), &mut camera, 0.0);
+    assert_eq!(fly.pitch(), 0.0);
+
+    let look = [InputEvent::ButtonPressed(Button::Mouse(MouseButton::Right)), look[0]];
+    fly.update(&input(&look), &mut camera, 0.0);
+    assert!((fly.pitch() + 0.1).abs() < 1e-5);
+    assert!(camera.forward().y < 0.0);
+
+    // Looking down doesn't tilt sideways movement.
+    let strafe = input(&[InputEvent::ButtonPressed(Button::Key(KeyCode::KeyD))]);
+    fly.update(&strafe, &mut camera, 1.0);
+    assert_near(camera.position(), vec3(-1.0, 4.0, 8.0));
+}
@@ -0,0 +1,139 @@
+use simple_game::graphics::{FrameEncoder, GraphicsDevice, RecordingMode};
+use std::{path::Path, time::Duration};
+
+fn clear(frame_encoder: &mut FrameEncoder, color: wgpu::Color) {
+    frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Capture test render pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &frame_encoder.backbuffer_view,
+            resolve_target: None,
+            ops: wgpu::Operations { load: wgpu::LoadOp::Clear(color), store: wgpu::StoreOp::Store },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+}
+
+fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+    let bytes = std::fs::read(path).expect("Screenshot should have been written");
+    let (header, pixels) = png_decoder::decode(&bytes).expect("Invalid screenshot PNG");
+
+    (header.width, header.height, pixels)
+}
+
+#[test]
+fn captured_frames_are_written_as_rgba_pngs() {
+    let dir = std::env::temp_dir().join(format!("simple-game-capture-{}", std::process::id()));
+    let capture_path = dir.join("captured.png");
+    let requested_path = dir.join("requested.png");
+
+    // An odd width, so rows need padding in the readback buffer.
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(37, 20))
+        .expect("Failed to create a headless graphics device");
+
+    let mut frame_encoder = graphics_device.begin_frame().unwrap().unwrap();
+    clear(&mut frame_encoder, wgpu::Color::RED);
+    graphics_device.capture_frame(&mut frame_encoder, &capture_path).unwrap();
+    graphics_device.end_frame(frame_encoder);
+
+    // Later frames don't affect a capture which is still being read back.
+    graphics_device.request_screenshot(&requested_path);
+    let mut frame_encoder = graphics_device.begin_frame().unwrap().unwrap();
+    clear(&mut frame_encoder, wgpu::Color::BLUE);
+    graphics_device.end_frame(frame_encoder);
+
+    graphics_device.finish_captures().expect("Failed to write screenshots");
+    assert_eq!(graphics_device.pending_captures(), 0);
+    assert_eq!(graphics_device.frames_ended(), 2);
+
+    let (width, height, pixels) = read_png(&capture_path);
+    assert_eq!((width, height), (37, 20));
+    assert!(pixels.chunks_exact(4).all(|pixel| pixel == [255, 0, 0, 255]));
+
+    let (_, _, pixels) = read_png(&requested_path);
+    assert!(pixels.chunks_exact(4).all(|pixel| pixel == [0, 0, 255, 255]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Renders `colors` as frames finished with `end_frame`.
+fn render_frames(graphics_device: &mut GraphicsDevice, colors: &[wgpu::Color]) {
+    for &color in colors {
+        let mut frame_encoder = graphics_device.begin_frame().unwrap().unwrap();
+        clear(&mut frame_encoder, color);
+        graphics_device.end_frame(frame_encoder);
+    }
+}
+
+#[test]
+fn recordings_write_every_frame_as_a_numbered_png() {
+    let dir = std::env::temp_dir().join(format!("simple-game-recording-{}", std::process::id()));
+
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(16, 8))
+        .expect("Failed to create a headless graphics device");
+
+    let mode = RecordingMode::png_sequence(&dir, 30);
+    assert_eq!(mode.frame_dt(), Duration::from_secs(1) / 30);
+    graphics_device.start_recording(mode).unwrap();
+    assert!(graphics_device.start_recording(RecordingMode::png_sequence(&dir, 30)).is_err());
+
+    // More frames than can be in flight at once, none of them may be dropped.
+    let colors: Vec<_> =
+        (0..12).map(|i| wgpu::Color { r: i as f64 / 11.0, g: 0.0, b: 0.0, a: 1.0 }).collect();
+    render_frames(&mut graphics_device, &colors);
+
+    graphics_device.stop_recording().expect("Failed to write the recording");
+    assert!(graphics_device.recording().is_none());
+
+    // Frames after the recording stopped aren't recorded.
+    render_frames(&mut graphics_device, &[wgpu::Color::WHITE]);
+    graphics_device.finish_captures().unwrap();
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), colors.len());
+
+    for (i, file) in files.iter().enumerate() {
+        assert_eq!(file, &format!("frame_{:06}.png", i));
+
+        let (_, _, pixels) = read_png(&dir.join(file));
+        let red = (i as f64 / 11.0 * 255.0).round() as i32;
+        assert!((pixels[0] as i32 - red).abs() <= 1, "Frame {} has red {}", i, pixels[0]);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn recordings_can_be_piped_to_an_encoder_process() {
+    let path = std::env::temp_dir().join(format!("simple-game-pipe-{}.raw", std::process::id()));
+
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(16, 8))
+        .expect("Failed to create a headless graphics device");
+
+    let command =
+        format!("echo {{width}}x{{height}}@{{fps}} > {0}.txt && cat > {0}", path.display());
+    graphics_device.start_recording(RecordingMode::pipe("sh", ["-c", &command], 24)).unwrap();
+    render_frames(&mut graphics_device, &[wgpu::Color::RED, wgpu::Color::GREEN]);
+    graphics_device.stop_recording().expect("Failed to pipe the recording");
+
+    let info_path = path.with_extension("raw.txt");
+    assert_eq!(std::fs::read_to_string(&info_path).unwrap().trim(), "16x8@24");
+
+    let frames = std::fs::read(&path).unwrap();
+    assert_eq!(frames.len(), 2 * 16 * 8 * 4);
+    assert_eq!(frames[..4], [255, 0, 0, 255]);
+    assert_eq!(frames[16 * 8 * 4..][..4], [0, 255, 0, 255]);
+
+    // A failing encoder fails the recording.
+    graphics_device.start_recording(RecordingMode::pipe("sh", ["-c", "exit 3"], 24)).unwrap();
+    assert!(graphics_device.stop_recording().is_err());
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&info_path).unwrap();
+}
@@ -0,0 +1,145 @@
+use simple_game::audio::{
+    BiquadFilter, BusId, Compressor, Delay, Mixer, PlaybackParams, Reverb, Sound,
+};
+use std::{f32::consts::TAU, time::Duration};
+
+const SAMPLE_RATE: u32 = 48_000;
+
+fn mix(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+    let mut output = vec![0.0; frames * 2];
+    mixer.mix(&mut output);
+    output
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// One second of a sine wave at `frequency`, played on a loop.
+fn sine(frequency: f32) -> Sound {
+    let samples =
+        (0..SAMPLE_RATE).map(|i| (TAU * frequency * i as f32 / SAMPLE_RATE as f32).sin()).collect();
+    Sound::from_samples(SAMPLE_RATE, 1, samples)
+}
+
+fn impulse(sample_rate: u32) -> Sound {
+    let mut samples = vec![0.0; 2];
+    samples[0] = 1.0;
+    Sound::from_samples(sample_rate, 2, samples)
+}
+
+/// The loudness of a looping `sound` through `bus` once the effects settled.
+fn level(mixer: &mut Mixer, bus: BusId, sound: &Sound) -> f32 {
+    mixer.stop_all();
+    mixer.play(sound, PlaybackParams::new().bus(bus).looping(true));
+    mix(mixer, 4800);
+    rms(&mix(mixer, 4800))
+}
+
+#[test]
+fn filters_pass_or_cut_frequencies_and_can_be_automated() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let world = mixer.add_bus("world", BusId::MASTER);
+    let low = sine(100.0);
+    let high = sine(8000.0);
+
+    // A centered mono voice plays at -3 dB, so an unfiltered sine has an RMS of 0.5.
+    assert!((level(&mut mixer, world, &high) - 0.5).abs() < 0.01);
+
+    let underwater = mixer.add_effect(world, BiquadFilter::low_pass(20_000.0));
+    assert!(level(&mut mixer, world, &high) > 0.49);
+
+    // Dive, then muffle everything.
+    mixer.effect_mut::<BiquadFilter>(underwater).unwrap().cutoff = 500.0;
+    assert!(level(&mut mixer, world, &high) < 0.01);
+    assert!(level(&mut mixer, world, &low) > 0.49);
+    assert!(mixer.effect_mut::<Delay>(underwater).is_none());
+
+    mixer.clear_effects(world);
+    mixer.add_effect(world, BiquadFilter::high_pass(2000.0));
+    assert!(level(&mut mixer, world, &low) < 0.01);
+    assert!(level(&mut mixer, world, &high) > 0.49);
+}
+
+#[test]
+fn delays_repeat_the_input() {
+    let mut mixer = Mixer::new(1000);
+    mixer.add_effect(BusId::MASTER, Delay::new(Duration::from_millis(10), 0.5, 0.5));
+    mixer.play(&impulse(1000), PlaybackParams::new());
+
+    let output = mix(&mut mixer, 40);
+    let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+    let echoes: Vec<(usize, f32)> =
+        left.iter().copied().enumerate().filter(|(_, sample)| *sample != 0.0).collect();
+
+    assert_eq!(echoes, [(0, 0.5), (10, 0.5), (20, 0.25), (30, 0.125)]);
+}
+
+#[test]
+fn delay_times_can_be_automated_without_losing_echoes() {
+    let mut mixer = Mixer::new(1000);
+    let delay = mixer.add_effect(
+        BusId::MASTER,
+        Delay::new(Duration::from_millis(10), 0.0, 0.5).with_max_time(Duration::from_millis(30)),
+    );
+    mixer.play(&impulse(1000), PlaybackParams::new());
+
+    let mut output = mix(&mut mixer, 5);
+    mixer.effect_mut::<Delay>(delay).unwrap().time = Duration::from_millis(20);
+    output.extend(mix(&mut mixer, 35));
+
+    // Past the maximum, the delay is as long as it gets.
+    mixer.effect_mut::<Delay>(delay).unwrap().time = Duration::from_secs(1);
+    mixer.play(&impulse(1000), PlaybackParams::new());
+    output.extend(mix(&mut mixer, 40));
+
+    let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+    let echoes: Vec<(usize, f32)> =
+        left.iter().copied().enumerate().filter(|(_, sample)| *sample != 0.0).collect();
+
+    assert_eq!(echoes, [(0, 0.5), (20, 0.5), (40, 0.5), (70, 0.5)]);
+}
+
+#[test]
+fn reverb_tails_are_deterministic() {
+    let render = || {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        let hall = mixer.add_bus("hall", BusId::MASTER);
+        mixer.add_effect(hall, Reverb::new(0.8, 0.3, 0.5));
+        mixer.play(&impulse(SAMPLE_RATE), PlaybackParams::new().bus(hall));
+        mix(&mut mixer, SAMPLE_RATE as usize)
+    };
+
+    let output = render();
+    let tail_start = rms(&output[4800..9600]);
+    let tail_end = rms(&output[86_400..91_200]);
+
+    assert!(tail_start > 1e-3, "expected a tail, got {}", tail_start);
+    assert!(tail_end < tail_start / 2.0, "expected the tail to decay");
+    assert_eq!(output, render());
+}
+
+#[test]
+fn compressors_and_limiters_tame_loud_input() {
+    let loud = Sound::from_samples(SAMPLE_RATE, 2, vec![1.0; 2 * 4800]);
+
+    // 0 dB is 12 dB over the threshold, a 2:1 ratio leaves it 6 dB over.
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let compressor = mixer.add_effect(BusId::MASTER, Compressor::new(-12.0, 2.0));
+    mixer.play(&loud, PlaybackParams::new().looping(true));
+
+    let output = mix(&mut mixer, 9600);
+    assert!((output[9599 * 2] - 0.5012).abs() < 1e-3, "got {}", output[9599 * 2]);
+    let reduction = mixer.effect_mut::<Compressor>(compressor).unwrap().gain_reduction_db();
+    assert!((reduction + 6.0).abs() < 0.01);
+
+    // Nothing gets past a limiter, not even the first sample.
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.add_effect(BusId::MASTER, Compressor::limiter(-6.0));
+    mixer.play(&loud, PlaybackParams::new().volume(4.0));
+
+    let output = mix(&mut mixer, 9600);
+    let limit = 10.0f32.powf(-6.0 / 20.0);
+    assert!(output.iter().all(|sample| sample.abs() <= limit + 1e-5));
+    assert!((output[0] - limit).abs() < 1e-5);
+}
@@ -0,0 +1,394 @@
+use glam::{vec2, vec3, Mat4};
+use simple_game::{
+    golden::GoldenTest,
+    graphics::{
+        text::{AxisAlign, StyledText, TextAlignment, TextSystem},
+        Camera2D, Camera3D, DebugDrawer, DepthTexture, FrameEncoder, FullscreenQuad,
+        GraphicsDevice, Image, ImageDrawer, LineDrawer, LineDrawer2d, LineVertex, LineVertex3,
+    },
+    input::Input,
+    GameApp,
+};
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 256;
+
+fn clear_pass<'a>(frame_encoder: &'a mut FrameEncoder) -> wgpu::RenderPass<'a> {
+    frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Golden test render pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &frame_encoder.backbuffer_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
+#[test]
+fn fullscreen_quad() {
+    let golden = GoldenTest::new("fullscreen_quad").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let fullscreen_quad =
+        FullscreenQuad::new(graphics_device.device(), graphics_device.surface_texture_format());
+
+    golden.run(&mut graphics_device, |_graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+        fullscreen_quad.render(&mut render_pass);
+    });
+}
+
+#[test]
+fn debug_drawer() {
+    let golden = GoldenTest::new("debug_drawer").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let mut debug_drawer = DebugDrawer::new(
+        graphics_device.device(),
+        graphics_device.surface_texture_format(),
+        WIDTH,
+        HEIGHT,
+    );
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+
+        let mut shape_recorder = debug_drawer.begin();
+        shape_recorder.draw_line(vec3(-8.0, -8.0, 0.0), vec3(8.0, 8.0, 0.0));
+        shape_recorder.draw_circle(vec3(0.0, 0.0, 0.0), 5.0, 0.0);
+        shape_recorder.draw_circle(vec3(4.0, -4.0, 0.0), 2.0, 0.5);
+        shape_recorder.end(&mut render_pass, graphics_device.queue());
+    });
+}
+
+#[test]
+fn line_drawer_2d() {
+    let golden = GoldenTest::new("line_drawer_2d").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let mut line_drawer = LineDrawer2d::new(
+        graphics_device.device(),
+        graphics_device.surface_texture_format(),
+        WIDTH,
+        HEIGHT,
+    );
+
+    let zig_zag: Vec<_> = (0..6)
+        .map(|i| {
+            let y = if i % 2 == 0 { 60.0 } else { 120.0 };
+            LineVertex::new(vec2(30.0 + i as f32 * 40.0, y), 4.0 + i as f32 * 3.0)
+        })
+        .collect();
+
+    let single_segment =
+        [LineVertex::new(vec2(40.0, 200.0), 20.0), LineVertex::new(vec2(220.0, 180.0), 8.0)];
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+
+        let mut line_recorder = line_drawer.begin();
+        line_recorder.draw_round_line_strip(&zig_zag);
+        line_recorder.draw_round_line_strip(&single_segment);
+        line_recorder.end(&mut render_pass, graphics_device.queue());
+    });
+}
+
+#[test]
+fn camera_2d() {
+    let golden = GoldenTest::new("camera_2d").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+    let device = graphics_device.device();
+    let format = graphics_device.surface_texture_format();
+
+    let mut debug_drawer = DebugDrawer::new(device, format, WIDTH, HEIGHT);
+    let mut line_drawer = LineDrawer2d::new(device, format, WIDTH, HEIGHT);
+
+    // Zoomed in on a square around the origin and turned a little. Both drawers share
+    // the camera, so the circle sits in the corner of the square.
+    let camera = Camera2D::new(WIDTH, HEIGHT)
+        .with_position(vec2(0.0, 0.0))
+        .with_view_height(30.0)
+        .with_rotation(0.3);
+    debug_drawer.set_camera(&camera);
+    line_drawer.set_camera(&camera);
+
+    let square: Vec<_> =
+        [(-10.0, -10.0), (10.0, -10.0), (10.0, 10.0), (-10.0, 10.0), (-10.0, -10.0)]
+            .iter()
+            .map(|&(x, y)| LineVertex::new(vec2(x, y), 1.0))
+            .collect();
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+
+        let mut line_recorder = line_drawer.begin();
+        line_recorder.draw_round_line_strip(&square);
+        line_recorder.end(&mut render_pass, graphics_device.queue());
+
+        let mut shape_recorder = debug_drawer.begin();
+        shape_recorder.draw_circle(vec3(10.0, 10.0, 0.0), 3.0, 0.0);
+        shape_recorder.draw_line(vec3(0.0, 0.0, 0.0), vec3(10.0, 0.0, 0.0));
+        shape_recorder.end(&mut render_pass, graphics_device.queue());
+    });
+}
+
+#[test]
+fn line_drawer_3d() {
+    let golden = GoldenTest::new("line_drawer_3d").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let depth_texture = DepthTexture::new(graphics_device.device(), WIDTH, HEIGHT);
+
+    let mut line_drawer = LineDrawer::new(
+        graphics_device.device(),
+        graphics_device.surface_texture_format(),
+        depth_texture.format(),
+        WIDTH,
+        HEIGHT,
+    );
+
+    let square = [
+        LineVertex3::new(vec3(-1.0, -1.0, 0.0), 6.0),
+        LineVertex3::new(vec3(1.0, -1.0, 0.0), 6.0),
+        LineVertex3::new(vec3(1.0, 1.0, 0.0), 6.0),
+        LineVertex3::new(vec3(-1.0, 1.0, 0.0), 6.0),
+        LineVertex3::new(vec3(-1.0, -1.0, 0.0), 6.0),
+    ];
+
+    let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_3, 1.0, 0.1, 100.0);
+    let view = Mat4::look_at_rh(vec3(1.5, 1.5, 3.0), vec3(0.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0));
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass =
+            frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Golden test render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame_encoder.backbuffer_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+        let mut line_recorder = line_drawer.begin();
+        line_recorder.draw_round_line_strip(&square);
+        line_recorder.end(&mut render_pass, graphics_device.queue(), proj * view, Mat4::IDENTITY);
+    });
+}
+
+#[test]
+fn camera_3d() {
+    let golden = GoldenTest::new("camera_3d").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+    let device = graphics_device.device();
+    let format = graphics_device.surface_texture_format();
+
+    let camera = Camera3D::perspective(WIDTH, HEIGHT, std::f32::consts::FRAC_PI_3)
+        .with_position(vec3(2.0, 1.5, 3.0))
+        .with_target(vec3(0.0, 0.0, 0.0))
+        .with_reverse_z(true);
+
+    let depth_texture = DepthTexture::new(device, WIDTH, HEIGHT);
+    let mut line_drawer = LineDrawer::new_with_depth_compare(
+        device,
+        format,
+        depth_texture.format(),
+        camera.depth_compare(),
+        WIDTH,
+        HEIGHT,
+    );
+
+    let mut debug_drawer = DebugDrawer::new(device, format, WIDTH, HEIGHT);
+    debug_drawer.set_camera(&camera);
+
+    // Two squares crossing each other, the nearer one hides parts of the other.
+    let square = |corner: fn(f32, f32) -> glam::Vec3| -> Vec<LineVertex3> {
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)]
+            .iter()
+            .map(|&(a, b)| LineVertex3::new(corner(a, b), 8.0))
+            .collect()
+    };
+    let facing_z = square(|a, b| vec3(a, b, 0.0));
+    let facing_x = square(|a, b| vec3(0.0, b, a));
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass =
+            frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Golden test render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame_encoder.backbuffer_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(camera.depth_clear_value()),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+        let mut line_recorder = line_drawer.begin();
+        line_recorder.draw_round_line_strip(&facing_z);
+        line_recorder.draw_round_line_strip(&facing_x);
+        line_recorder.end_with_camera(
+            &mut render_pass,
+            graphics_device.queue(),
+            &camera,
+            Mat4::IDENTITY,
+        );
+
+        drop(render_pass);
+
+        // The debug drawer doesn't use depth, its axes are drawn on top in a second pass.
+        let mut render_pass =
+            frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Golden test overlay pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame_encoder.backbuffer_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+        let mut shape_recorder = debug_drawer.begin();
+        shape_recorder.draw_line(vec3(0.0, 0.0, 0.0), vec3(1.5, 0.0, 0.0));
+        shape_recorder.draw_line(vec3(0.0, 0.0, 0.0), vec3(0.0, 1.5, 0.0));
+        shape_recorder.draw_line(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.5));
+        shape_recorder.end(&mut render_pass, graphics_device.queue());
+    });
+}
+
+#[test]
+#[cfg_attr(
+    debug_assertions,
+    ignore = "fontdue 0.4 calls NonZeroU16::new_unchecked(0) when loading fonts, which aborts in debug builds"
+)]
+fn text_system() {
+    let golden = GoldenTest::new("text_system").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let mut text_system: TextSystem = TextSystem::new(
+        graphics_device.device(),
+        graphics_device.surface_texture_format(),
+        None,
+        WIDTH,
+        HEIGHT,
+    );
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+
+        text_system.render_horizontal(
+            TextAlignment::new(AxisAlign::WindowCenter, AxisAlign::WindowCenter),
+            &[StyledText::default_styling("Hello "), StyledText::default_styling("0123")],
+            &mut render_pass,
+            graphics_device.queue(),
+        );
+    });
+}
+
+#[test]
+fn image_drawer() {
+    let golden = GoldenTest::new("image_drawer").size(WIDTH, HEIGHT);
+    let mut graphics_device = golden.graphics_device();
+
+    let mut image_drawer = ImageDrawer::new(
+        graphics_device.device(),
+        graphics_device.surface_texture_format(),
+        WIDTH,
+        HEIGHT,
+    );
+
+    let image = Image::from_png(
+        include_bytes!("../examples/resources/grass.png"),
+        graphics_device.device(),
+        graphics_device.queue(),
+    );
+
+    golden.run(&mut graphics_device, |graphics_device, frame_encoder| {
+        let mut render_pass = clear_pass(frame_encoder);
+
+        let mut image_recorder = image_drawer.begin();
+        image_recorder.draw_image(&image, vec2(0.0, 0.0));
+        image_recorder.end(&mut render_pass, graphics_device.queue());
+    });
+}
+
+struct SpinningLine {
+    debug_drawer: DebugDrawer,
+    angle: f32,
+}
+
+impl GameApp for SpinningLine {
+    fn init(graphics_device: &mut GraphicsDevice) -> Self {
+        let (width, height) = graphics_device.surface_dimensions();
+
+        Self {
+            debug_drawer: DebugDrawer::new(
+                graphics_device.device(),
+                graphics_device.surface_texture_format(),
+                width,
+                height,
+            ),
+            angle: 0.0,
+        }
+    }
+
+    fn tick(&mut self, dt: f32, _input: &Input) {
+        self.angle += dt;
+    }
+
+    fn render(&mut self, graphics_device: &mut GraphicsDevice, _alpha: f32) {
+        let Some(mut frame_encoder) = graphics_device.begin_frame().expect("Failed to begin frame")
+        else {
+            return;
+        };
+
+        {
+            let mut render_pass = clear_pass(&mut frame_encoder);
+
+            let end = 8.0 * vec3(self.angle.cos(), self.angle.sin(), 0.0);
+
+            let mut shape_recorder = self.debug_drawer.begin();
+            shape_recorder.draw_line(vec3(0.0, 0.0, 0.0), end);
+            shape_recorder.end(&mut render_pass, graphics_device.queue());
+        }
+
+        graphics_device.queue().submit(Some(frame_encoder.encoder.finish()));
+        frame_encoder.frame.present();
+    }
+}
+
+#[test]
+fn game_app() {
+    GoldenTest::new("game_app").size(WIDTH, HEIGHT).frames(30).run_app::<SpinningLine>();
+}
@@ -0,0 +1,121 @@
+use simple_game::graphics::{FullscreenQuad, GraphicsDevice};
+use std::path::PathBuf;
+
+const SHADER_NAME: &str = "fullscreen_quad.wgsl";
+
+/// The built-in fullscreen quad shader, filled with a solid color instead of its UVs.
+fn solid_color_shader(color: &str) -> String {
+    include_str!("../src/graphics/shaders/wgsl/fullscreen_quad.wgsl")
+        .replace("vec4<f32>(in.uv.x, in.uv.y, 1.0, 1.0)", &format!("vec4<f32>({})", color))
+}
+
+fn render_center_pixel(graphics_device: &mut GraphicsDevice, quad: &FullscreenQuad) -> Vec<u8> {
+    let mut frame_encoder = graphics_device
+        .begin_frame()
+        .expect("Failed to begin frame")
+        .expect("Headless devices always have a frame available");
+
+    {
+        let mut render_pass =
+            frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Hot reload test render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame_encoder.backbuffer_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+        quad.render(&mut render_pass);
+    }
+
+    graphics_device.queue().submit(Some(frame_encoder.encoder.finish()));
+
+    let texture = graphics_device.offscreen_texture().unwrap();
+    let pixels = graphics_device.read_texture_rgba(texture);
+    let center = ((texture.height() / 2 * texture.width() + texture.width() / 2) * 4) as usize;
+
+    pixels[center..center + 4].to_vec()
+}
+
+#[test]
+fn reloads_valid_shaders_and_keeps_the_last_good_pipeline() {
+    let shader_dir: PathBuf =
+        std::env::temp_dir().join(format!("simple-game-hot-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&shader_dir).unwrap();
+    let shader_path = shader_dir.join(SHADER_NAME);
+
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+    let mut quad = FullscreenQuad::new(graphics_device.device(), wgpu::TextureFormat::Bgra8Unorm);
+
+    // Shaders already in a user directory replace the built-in ones right away.
+    std::fs::write(&shader_path, solid_color_shader("1.0, 0.0, 0.0, 1.0")).unwrap();
+    graphics_device.watch_shader_dir(&shader_dir);
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [255, 0, 0, 255]);
+
+    // Invalid WGSL is rejected by naga before it reaches the drawers.
+    std::fs::write(&shader_path, "@fragment fn fs_main( -> {").unwrap();
+    assert!(!graphics_device.poll_shaders());
+    let watcher = graphics_device.shader_watcher().unwrap();
+    assert!(watcher.error(SHADER_NAME).is_some());
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [255, 0, 0, 255]);
+
+    // Valid WGSL which doesn't fit the pipeline fails when the pipeline is rebuilt.
+    std::fs::write(
+        &shader_path,
+        solid_color_shader("0.0, 1.0, 0.0, 1.0").replace("fs_main", "main"),
+    )
+    .unwrap();
+    assert!(graphics_device.poll_shaders());
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [255, 0, 0, 255]);
+
+    std::fs::write(&shader_path, solid_color_shader("0.0, 0.0, 1.0, 1.0")).unwrap();
+    assert!(graphics_device.poll_shaders());
+    assert!(graphics_device.shader_watcher().unwrap().error(SHADER_NAME).is_none());
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [0, 0, 255, 255]);
+
+    std::fs::remove_dir_all(&shader_dir).unwrap();
+}
+
+#[test]
+fn changing_an_include_reloads_the_shaders_which_include_it() {
+    let shader_dir: PathBuf =
+        std::env::temp_dir().join(format!("simple-game-hot-reload-include-{}", std::process::id()));
+    std::fs::create_dir_all(&shader_dir).unwrap();
+    let include_path = shader_dir.join("quad_color.wgsl");
+
+    std::fs::write(&include_path, "const QUAD_COLOR = vec4<f32>(1.0, 0.0, 0.0, 1.0);").unwrap();
+    std::fs::write(
+        shader_dir.join(SHADER_NAME),
+        solid_color_shader("1.0, 1.0, 1.0, 1.0")
+            .replace("vec4<f32>(1.0, 1.0, 1.0, 1.0)", "QUAD_COLOR")
+            .replace("struct VertexInput", "#include \"quad_color.wgsl\"\n\nstruct VertexInput"),
+    )
+    .unwrap();
+
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+    let mut quad = FullscreenQuad::new(graphics_device.device(), wgpu::TextureFormat::Bgra8Unorm);
+
+    graphics_device.watch_shader_dir(&shader_dir);
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [255, 0, 0, 255]);
+
+    std::fs::write(&include_path, "const QUAD_COLOR = vec4<f32>(0.0, 1.0, 0.0, 1.0);").unwrap();
+    assert!(graphics_device.poll_shaders());
+    quad.reload_shaders(&graphics_device);
+    assert_eq!(render_center_pixel(&mut graphics_device, &quad), [0, 255, 0, 255]);
+
+    std::fs::remove_dir_all(&shader_dir).unwrap();
+}
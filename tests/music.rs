@@ -0,0 +1,214 @@
+use simple_game::audio::{BusId, Mixer, MusicPlayer, MusicTrack, PlaybackParams, Sound};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const SAMPLE_RATE: u32 = 1000;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-4, "expected {}, got {}", expected, actual);
+}
+
+fn write_wav(name: &str, samples: &[f32]) -> PathBuf {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+
+    let path =
+        std::env::temp_dir().join(format!("simple-game-{}-{}.wav", name, std::process::id()));
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+
+    for &sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+
+    writer.finalize().unwrap();
+    path
+}
+
+/// Mixes `count` audible frames, skipping the silence while the stream thread catches up.
+fn audible_frames(mixer: &mut Mixer, count: usize) -> Vec<f32> {
+    let start = Instant::now();
+    let mut frames = Vec::new();
+
+    while frames.len() < count {
+        assert!(start.elapsed() < Duration::from_secs(10), "the stream stalled");
+
+        let mut output = [0.0; 2];
+        mixer.mix(&mut output);
+
+        if output[0] == 0.0 {
+            std::thread::sleep(Duration::from_millis(1));
+        } else {
+            frames.push(output[0]);
+        }
+    }
+
+    frames
+}
+
+fn wait_until_stopped(mixer: &mut Mixer) {
+    let start = Instant::now();
+
+    while mixer.voice_count() > 0 {
+        assert!(start.elapsed() < Duration::from_secs(10), "the stream never ended");
+        mixer.mix(&mut [0.0; 2]);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn remove(path: &Path) {
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn streams_loop_at_exact_frames() {
+    let ramp: Vec<f32> = (1..=100).map(|i| i as f32).collect();
+    let path = write_wav("loop", &ramp);
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+
+    // A 20 frame intro, then frames 20 to 59 forever.
+    mixer.play_stream(&MusicTrack::new(&path).loop_points(20, Some(60)), PlaybackParams::new());
+    let frames = audible_frames(&mut mixer, 140);
+
+    let expected: Vec<f32> = (1..=60).chain(21..=60).chain(21..=60).map(|i| i as f32).collect();
+    assert_eq!(frames, expected);
+
+    mixer.stop_all();
+
+    // Without loop points the whole file plays once.
+    mixer.play_stream(&MusicTrack::new(&path), PlaybackParams::new());
+    assert_eq!(audible_frames(&mut mixer, 100), ramp);
+    wait_until_stopped(&mut mixer);
+
+    // Missing files end the voice, the error is only printed.
+    mixer.play_stream(&MusicTrack::new(path.with_extension("missing")), PlaybackParams::new());
+    wait_until_stopped(&mut mixer);
+
+    remove(&path);
+}
+
+/// A mono Vorbis stream at 8 kHz, 8064 frames of noise in pages of three 128 frame
+/// packets.
+const OGG_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/stream.ogg");
+
+#[test]
+fn ogg_streams_loop_at_exact_frames() {
+    let file = std::fs::File::open(OGG_FIXTURE).unwrap();
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(file).unwrap();
+    let mut decoded = Vec::new();
+
+    while let Some(packet) = reader.read_dec_packet_itl().unwrap() {
+        decoded.extend(packet.into_iter().map(|sample: i16| sample as f32 / 32768.0));
+    }
+
+    assert_eq!(decoded.len(), 8064);
+    let mut mixer = Mixer::new(8000);
+
+    // Far into the file, and close enough to the start that seeking lands on the headers.
+    for (start, end) in [(5000, 7000), (100, 300)] {
+        mixer.play_stream(
+            &MusicTrack::new(OGG_FIXTURE).loop_points(start, Some(end)),
+            PlaybackParams::new(),
+        );
+
+        // `audible_frames` skips silence, so the few samples decoded as exactly zero
+        // are left out of the expected frames too.
+        let (start, end) = (start as usize, end as usize);
+        let expected: Vec<f32> = decoded[..end]
+            .iter()
+            .chain(&decoded[start..end])
+            .chain(&decoded[start..end])
+            .copied()
+            .filter(|&sample| sample != 0.0)
+            .collect();
+
+        assert_eq!(audible_frames(&mut mixer, expected.len()), expected);
+        mixer.stop_all();
+    }
+}
+
+#[test]
+fn music_crossfades_between_tracks() {
+    let first = write_wav("first", &[0.5; 100]);
+    let second = write_wav("second", &[0.25; 100]);
+
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let bus = mixer.add_bus("music", BusId::MASTER);
+    let mut music = MusicPlayer::new(bus);
+
+    let old = music.play(&mut mixer, &MusicTrack::new(&first).looping(), Duration::ZERO);
+    assert_eq!(mixer.volume(old), Some(1.0));
+    assert_eq!(audible_frames(&mut mixer, 10), [0.5; 10]);
+
+    // 100 ms are 100 frames.
+    let new =
+        music.play(&mut mixer, &MusicTrack::new(&second).looping(), Duration::from_millis(100));
+    assert_eq!(music.current(), Some(new));
+    assert_eq!(mixer.volume(new), Some(0.0));
+
+    // The crossfade waits for the new stream's first samples.
+    let start = Instant::now();
+
+    while mixer.is_buffering(new) {
+        assert!(start.elapsed() < Duration::from_secs(10), "the stream stalled");
+        mixer.mix(&mut [0.0; 2]);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(mixer.volume(old), Some(1.0));
+    assert_eq!(mixer.volume(new), Some(0.0));
+    music.update(&mut mixer);
+
+    mixer.mix(&mut [0.0; 50 * 2]);
+    assert_close(mixer.volume(old).unwrap(), 0.5);
+    assert_close(mixer.volume(new).unwrap(), 0.5);
+
+    mixer.mix(&mut [0.0; 50 * 2]);
+    assert!(!mixer.is_playing(old));
+    assert_eq!(mixer.volume(new), Some(1.0));
+
+    music.stop(&mut mixer, Duration::from_millis(10));
+    assert_eq!(music.current(), None);
+    mixer.mix(&mut [0.0; 10 * 2]);
+    assert_eq!(mixer.voice_count(), 0);
+
+    remove(&first);
+    remove(&second);
+}
+
+#[test]
+fn dialogue_ducks_the_music_bus() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    let music_bus = mixer.add_bus("music", BusId::MASTER);
+    let dialogue_bus = mixer.add_bus("dialogue", BusId::MASTER);
+    mixer.set_bus_volume(music_bus, 0.8);
+
+    let mut music =
+        MusicPlayer::new(music_bus).ducking(dialogue_bus, 0.25, Duration::from_millis(10));
+    music.update(&mut mixer);
+    assert!(!music.is_ducked());
+
+    let line = Sound::from_samples(SAMPLE_RATE, 1, vec![0.5; 50]);
+    mixer.play(&line, PlaybackParams::new().bus(dialogue_bus));
+    music.update(&mut mixer);
+    assert!(music.is_ducked());
+
+    mixer.mix(&mut [0.0; 5 * 2]);
+    assert_close(mixer.bus_volume(music_bus), 0.5);
+    mixer.mix(&mut [0.0; 5 * 2]);
+    assert_close(mixer.bus_volume(music_bus), 0.2);
+
+    // The line ends, the music comes back up.
+    mixer.mix(&mut [0.0; 40 * 2]);
+    music.update(&mut mixer);
+    assert!(!music.is_ducked());
+    assert_close(mixer.bus_target_volume(music_bus), 0.8);
+
+    mixer.mix(&mut [0.0; 10 * 2]);
+    assert_close(mixer.bus_volume(music_bus), 0.8);
+}
@@ -0,0 +1,40 @@
+use simple_game::pacing::FramePacer;
+use std::time::Duration;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn on_time_frames_keep_the_schedule() {
+    let mut pacer = FramePacer::new(ms(16)).with_wake_margin(ms(2));
+    let start = pacer.next_frame();
+
+    pacer.frame_started(start);
+    assert_eq!(pacer.next_frame(), start + ms(16));
+    assert_eq!(pacer.wake_time(), start + ms(14));
+
+    // Starting a little late doesn't push the schedule back.
+    pacer.frame_started(start + ms(20));
+    assert_eq!(pacer.next_frame(), start + ms(32));
+
+    pacer.frame_started(start + ms(32));
+    assert_eq!(pacer.next_frame(), start + ms(48));
+    assert_eq!(pacer.wake_time(), start + ms(46));
+}
+
+#[test]
+fn late_frames_reschedule_from_their_start() {
+    let mut pacer = FramePacer::new(ms(16));
+    let start = pacer.next_frame();
+
+    pacer.frame_started(start);
+
+    // More than a frame behind, the next frame is a whole frame later instead of a
+    // burst of frames catching up.
+    pacer.frame_started(start + ms(100));
+    assert_eq!(pacer.next_frame(), start + ms(116));
+
+    pacer.frame_started(start + ms(116));
+    assert_eq!(pacer.next_frame(), start + ms(132));
+}
@@ -0,0 +1,98 @@
+use simple_game::{
+    graphics::{GraphicsDevice, PreprocessError, WgslPreprocessor},
+    Error,
+};
+
+/// The non-empty lines of the processed source, trimmed.
+fn process(preprocessor: &WgslPreprocessor, source: &str) -> Vec<String> {
+    let processed = preprocessor.process(source).expect("Failed to preprocess");
+
+    processed.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect()
+}
+
+#[test]
+fn includes_are_pasted_in_once() {
+    let preprocessor = WgslPreprocessor::new()
+        .include("a.wgsl", "#include \"common.wgsl\"\nconst A = 1.0;")
+        .include("b.wgsl", "#include \"a.wgsl\"\nconst B = 2.0;");
+
+    let lines = process(&preprocessor, "#include \"a.wgsl\"\n#include \"b.wgsl\"\nconst C = 3.0;");
+
+    assert_eq!(lines.iter().filter(|line| line.starts_with("struct Globals")).count(), 1);
+    assert_eq!(lines.iter().filter(|line| line.starts_with("const A")).count(), 1);
+    assert_eq!(lines[lines.len() - 2..], ["const B = 2.0;", "const C = 3.0;"]);
+}
+
+#[test]
+fn defines_select_variants_and_inject_constants() {
+    let source = "\
+#ifndef INSTANCES
+#define INSTANCES 16
+#endif
+const MAX_INSTANCES: u32 = INSTANCES;
+#ifdef DEPTH
+const HAS_DEPTH = true;
+#else
+const HAS_DEPTH = false;
+#endif
+const NOT_INSTANCES_2 = 1e5;";
+
+    assert_eq!(
+        process(&WgslPreprocessor::new(), source),
+        [
+            "const MAX_INSTANCES: u32 = 16;",
+            "const HAS_DEPTH = false;",
+            "const NOT_INSTANCES_2 = 1e5;"
+        ]
+    );
+
+    let variant = WgslPreprocessor::new().define("INSTANCES", 40_000).flag("DEPTH");
+
+    assert_eq!(
+        process(&variant, source),
+        [
+            "const MAX_INSTANCES: u32 = 40000;",
+            "const HAS_DEPTH = true;",
+            "const NOT_INSTANCES_2 = 1e5;"
+        ]
+    );
+}
+
+#[test]
+fn errors_point_at_the_file_and_line() {
+    let preprocessor = WgslPreprocessor::new().include("broken.wgsl", "const A = 1.0;\n#endif");
+
+    assert_eq!(
+        preprocessor.process("// Comment\n#include \"broken.wgsl\""),
+        Err(PreprocessError {
+            file: Some("broken.wgsl".to_string()),
+            line: 2,
+            message: "#endif without #ifdef or #ifndef".to_string(),
+        })
+    );
+
+    assert_eq!(
+        preprocessor.process("#include \"missing.wgsl\"").unwrap_err().message,
+        "Unknown include 'missing.wgsl'"
+    );
+
+    assert_eq!(preprocessor.process("#ifdef DEPTH\n").unwrap_err().line, 1);
+}
+
+#[test]
+fn loading_a_shader_returns_preprocess_errors() {
+    let graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let result = GraphicsDevice::load_wgsl_shader_with(
+        graphics_device.device(),
+        "#include \"missing.wgsl\"\n",
+        &WgslPreprocessor::new(),
+    );
+
+    match result {
+        Err(Error::ShaderPreprocessError(err)) => assert_eq!(err.line, 1),
+        Err(err) => panic!("expected a preprocess error, got {}", err),
+        Ok(_) => panic!("expected a preprocess error"),
+    }
+}
@@ -0,0 +1,54 @@
+use simple_game::graphics::{FrameEncoder, GraphicsDevice};
+
+fn clear_pass(frame_encoder: &mut FrameEncoder) {
+    frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Profiler test render pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &frame_encoder.backbuffer_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+}
+
+#[test]
+fn profiler_reports_nested_scopes() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+    graphics_device.enable_profiler();
+
+    // GPU results are read back asynchronously, so give them a few frames to show up.
+    for _ in 0..10 {
+        let mut frame_encoder = graphics_device
+            .begin_frame()
+            .expect("Failed to begin frame")
+            .expect("Headless devices always have a frame available");
+
+        graphics_device.begin_profiler_scope(&mut frame_encoder, "frame");
+        graphics_device.begin_profiler_scope(&mut frame_encoder, "clear");
+        clear_pass(&mut frame_encoder);
+        graphics_device.end_profiler_scope(&mut frame_encoder);
+        graphics_device.end_profiler_scope(&mut frame_encoder);
+
+        graphics_device.queue().submit(Some(frame_encoder.encoder.finish()));
+        frame_encoder.frame.present();
+        graphics_device.device().poll(wgpu::Maintain::Wait);
+    }
+
+    let profiler = graphics_device.profiler().expect("Profiler should be enabled");
+    let results = profiler.results();
+
+    let scopes: Vec<_> = results.iter().map(|scope| (scope.name.as_str(), scope.depth)).collect();
+    assert_eq!(scopes, [("frame", 0), ("clear", 1)]);
+
+    for scope in results {
+        assert!(scope.cpu_ms >= 0.0);
+        assert_eq!(scope.gpu_ms.is_some(), profiler.has_gpu_timestamps());
+    }
+}
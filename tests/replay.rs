@@ -0,0 +1,105 @@
+use glam::{vec2, Vec2};
+use simple_game::{
+    graphics::GraphicsDevice,
+    input::{Button, Input, InputEvent, InputRecording},
+    timestep::FixedTimestep,
+    Error, GameApp,
+};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+/// Walks right while D is held and teleports to the cursor on click.
+struct Walker {
+    position: Vec2,
+}
+
+impl GameApp for Walker {
+    fn init(_graphics_device: &mut GraphicsDevice) -> Self {
+        Self { position: Vec2::ZERO }
+    }
+
+    fn tick(&mut self, dt: f32, input: &Input) {
+        if input.is_down(KeyCode::KeyD) {
+            self.position.x += 10.0 * dt;
+        }
+
+        if input.just_pressed(MouseButton::Left) {
+            if let Some(cursor_position) = input.cursor_position() {
+                self.position = cursor_position;
+            }
+        }
+    }
+
+    fn checksum(&self) -> Option<u64> {
+        Some((self.position.x.to_bits() as u64) << 32 | self.position.y.to_bits() as u64)
+    }
+
+    fn render(&mut self, _graphics_device: &mut GraphicsDevice, _alpha: f32) {}
+}
+
+fn graphics_device() -> GraphicsDevice {
+    pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device")
+}
+
+/// Records input for `Walker` the way the game loop does.
+fn record() -> InputRecording {
+    let mut recording = InputRecording::new(60);
+    let mut walker = Walker::init(&mut graphics_device());
+    let mut input = Input::new();
+    let dt = FixedTimestep::new(60).tick_dt().as_secs_f32();
+
+    for tick in 0..120 {
+        let events: &[InputEvent] = match tick {
+            10 => &[InputEvent::ButtonPressed(Button::Key(KeyCode::KeyD))],
+            40 => &[InputEvent::ButtonReleased(Button::Key(KeyCode::KeyD))],
+            60 => &[
+                InputEvent::CursorMoved(vec2(12.5, 30.25)),
+                InputEvent::ButtonPressed(Button::Mouse(MouseButton::Left)),
+            ],
+            61 => &[InputEvent::ButtonReleased(Button::Mouse(MouseButton::Left))],
+            _ => &[],
+        };
+
+        for event in events {
+            recording.record_event(*event);
+            input.apply_event(event);
+        }
+
+        walker.tick(dt, &input);
+        input.end_tick();
+        recording.end_tick(walker.checksum());
+    }
+
+    recording
+}
+
+#[test]
+fn replay_matches_recording() {
+    let recording = record();
+    let parsed: InputRecording =
+        recording.to_string().parse().expect("Failed to parse the recording");
+
+    assert_eq!(parsed, recording);
+
+    let walker = parsed.replay::<Walker>(&mut graphics_device()).expect("Replay diverged");
+    assert_eq!(walker.position, vec2(12.5, 30.25));
+}
+
+#[test]
+fn replay_detects_divergence() {
+    // Drop the click, so the walker never teleports.
+    let text: String = record()
+        .to_string()
+        .lines()
+        .filter(|line| !line.contains("Mouse:Left"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    let recording: InputRecording = text.parse().expect("Failed to parse the recording");
+
+    match recording.replay::<Walker>(&mut graphics_device()) {
+        Err(Error::ReplayDiverged { tick, .. }) => assert_eq!(tick, 60),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(_) => panic!("Replay should have diverged"),
+    }
+}
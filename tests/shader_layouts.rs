@@ -0,0 +1,66 @@
+use simple_game::{
+    field_layout,
+    graphics::{check_uniform_layout, check_vertex_layout, shader_layout_mismatches, ShaderLayout},
+};
+
+#[test]
+fn drawer_structs_match_their_shaders() {
+    let mismatches = shader_layout_mismatches();
+    assert!(mismatches.is_empty(), "Mismatched shader layouts:\n{}", mismatches.join("\n"));
+}
+
+#[test]
+fn shader_layouts_are_reflected() {
+    let glyph = ShaderLayout::find("glyph.wgsl").unwrap();
+    let color = glyph.vertex_input(4).unwrap();
+    assert_eq!((color.name, color.format), ("color", Some(wgpu::VertexFormat::Float32x4)));
+
+    // Included structs are part of the shaders which include them.
+    let globals = glyph.struct_layout("Globals").unwrap();
+    assert_eq!(globals.size, 64);
+
+    let lines = ShaderLayout::find("round_line_strip.wgsl").unwrap();
+    let globals = lines.struct_layout("Globals").unwrap();
+    assert_eq!(globals.size, 144);
+    assert_eq!(globals.members[2].offset, 128);
+}
+
+#[test]
+fn mismatched_structs_are_reported() {
+    // A vec3 is aligned to 16 bytes in WGSL, so the padding is missing here.
+    #[repr(C)]
+    struct Globals {
+        proj: [f32; 16],
+        transform: [f32; 16],
+        resolution: [f32; 3],
+    }
+
+    let mismatches = check_uniform_layout(
+        "round_line_strip.wgsl",
+        "Globals",
+        "Globals",
+        std::mem::size_of::<Globals>(),
+        &[
+            field_layout!(Globals, proj),
+            field_layout!(Globals, transform),
+            field_layout!(Globals, resolution),
+        ],
+    );
+    assert_eq!(mismatches.len(), 2, "{:?}", mismatches);
+
+    #[repr(C)]
+    struct Vertex {
+        pos: [f32; 2],
+        uv: [f32; 2],
+    }
+
+    let attributes = wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3];
+    let mismatches = check_vertex_layout(
+        "image.wgsl",
+        "Vertex",
+        std::mem::size_of::<Vertex>(),
+        &attributes,
+        &[field_layout!(Vertex, pos), field_layout!(Vertex, uv)],
+    );
+    assert_eq!(mismatches.len(), 2, "{:?}", mismatches);
+}
@@ -0,0 +1,116 @@
+use glam::{vec2, vec3, Mat4};
+use simple_game::audio::{Attenuation, Listener, Mixer, PlaybackParams, Sound};
+use std::f32::consts::FRAC_PI_8;
+
+const SAMPLE_RATE: u32 = 1000;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-4, "expected {}, got {}", expected, actual);
+}
+
+fn mix_frame(mixer: &mut Mixer) -> (f32, f32) {
+    let mut output = [0.0; 2];
+    mixer.mix(&mut output);
+    (output[0], output[1])
+}
+
+#[test]
+fn positioned_voices_are_panned_and_attenuated() {
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.set_listener(Listener::new(vec2(0.0, 0.0), 10.0));
+
+    let tone = Sound::from_samples(SAMPLE_RATE, 1, vec![1.0; 100]);
+    let linear = Attenuation::Linear { min_distance: 0.0, max_distance: 20.0 };
+    let voice = mixer.play(
+        &tone,
+        PlaybackParams::new().position(vec2(10.0, 0.0)).attenuation(linear).looping(true),
+    );
+
+    // Half way to silence, all the way to the right.
+    let (left, right) = mix_frame(&mut mixer);
+    assert_close(left, 0.0);
+    assert_close(right, 0.5);
+
+    mixer.set_position(voice, vec2(-5.0, 0.0));
+    let (left, right) = mix_frame(&mut mixer);
+    assert_close(left, 0.75 * FRAC_PI_8.cos());
+    assert_close(right, 0.75 * FRAC_PI_8.sin());
+
+    // Vertical offsets only attenuate.
+    mixer.stop_all();
+    let inverse = Attenuation::Inverse { min_distance: 1.0, rolloff: 1.0 };
+    mixer.play(&tone, PlaybackParams::new().position(vec2(0.0, 4.0)).attenuation(inverse));
+    let (left, right) = mix_frame(&mut mixer);
+    assert_close(left, 0.25 * std::f32::consts::FRAC_1_SQRT_2);
+    assert_close(left, right);
+
+    mixer.stop_all();
+    let custom = Attenuation::Custom(|distance| if distance < 3.0 { 1.0 } else { 0.0 });
+    let voice =
+        mixer.play(&tone, PlaybackParams::new().position(vec2(2.0, 0.0)).attenuation(custom));
+    assert!(mix_frame(&mut mixer).1 > 0.5);
+    mixer.set_position(voice, vec2(3.5, 0.0));
+    assert_eq!(mix_frame(&mut mixer), (0.0, 0.0));
+}
+
+#[test]
+fn listeners_follow_the_camera() {
+    let projection = Mat4::orthographic_rh(-8.0, 8.0, -5.0, 5.0, -1.0, 1.0);
+    let view = Mat4::look_at_rh(vec3(3.0, 2.0, 1.0), vec3(3.0, 2.0, 0.0), vec3(0.0, 1.0, 0.0));
+    let listener = Listener::from_camera_matrix(projection * view);
+
+    assert!(listener.position.abs_diff_eq(vec2(3.0, 2.0), 1e-5));
+    assert!(listener.right.abs_diff_eq(vec2(1.0, 0.0), 1e-5));
+    assert_close(listener.pan_width, 8.0);
+
+    // A voice at the right edge of the view is panned all the way.
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.set_listener(listener);
+
+    let tone = Sound::from_samples(SAMPLE_RATE, 1, vec![1.0; 100]);
+    let attenuation = Attenuation::Custom(|_| 1.0);
+    mixer.play(&tone, PlaybackParams::new().position(vec2(11.0, 2.0)).attenuation(attenuation));
+    let (left, right) = mix_frame(&mut mixer);
+    assert_close(left, 0.0);
+    assert_close(right, 1.0);
+}
+
+#[test]
+fn moving_voices_are_doppler_shifted() {
+    let ramp: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+    let ramp = Sound::from_samples(SAMPLE_RATE, 1, ramp);
+
+    let playback_speed = |velocity, doppler| {
+        let mut mixer = Mixer::new(SAMPLE_RATE);
+        mixer.set_speed_of_sound(100.0);
+
+        let params = PlaybackParams::new()
+            .position(vec2(10.0, 0.0))
+            .velocity(vec2(velocity, 0.0))
+            .attenuation(Attenuation::Custom(|_| 1.0))
+            .doppler(doppler);
+
+        mixer.play(&ramp, params);
+        mix_frame(&mut mixer);
+        mix_frame(&mut mixer).1 * 1000.0
+    };
+
+    // Approaching at half the speed of sound doubles the pitch.
+    assert_close(playback_speed(-50.0, 1.0), 2.0);
+    assert_close(playback_speed(50.0, 1.0), 100.0 / 150.0);
+    assert_close(playback_speed(-50.0, 0.0), 1.0);
+    assert_close(playback_speed(-25.0, 2.0), 2.0);
+
+    // A listener moving towards the voice raises its pitch as well.
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.set_speed_of_sound(100.0);
+    mixer.set_listener(Listener::new(vec2(0.0, 0.0), 10.0).velocity(vec2(50.0, 0.0)));
+    let params = PlaybackParams::new()
+        .position(vec2(10.0, 0.0))
+        .attenuation(Attenuation::Custom(|_| 1.0))
+        .doppler(1.0);
+
+    mixer.play(&ramp, params);
+    mix_frame(&mut mixer);
+    assert_close(mix_frame(&mut mixer).1 * 1000.0, 1.5);
+}
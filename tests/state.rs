@@ -0,0 +1,348 @@
+use simple_game::{
+    graphics::{FrameEncoder, GraphicsDevice},
+    input::{GamepadButton, Input, InputEvent, InputSink},
+    state::{GameState, InitialState, StateStack, Transition},
+    GameApp,
+};
+use std::{cell::RefCell, time::Duration};
+
+thread_local! {
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log(message: String) {
+    LOG.with(|log| log.borrow_mut().push(message));
+}
+
+fn take_log() -> Vec<String> {
+    LOG.with(|log| log.borrow_mut().drain(..).collect())
+}
+
+/// Logs its lifecycle and returns `transitions` from its ticks, one per tick.
+struct Logged {
+    name: &'static str,
+    overlay: bool,
+    transitions: Vec<Transition>,
+}
+
+impl Logged {
+    fn new(name: &'static str, overlay: bool, transitions: Vec<Transition>) -> Self {
+        Self { name, overlay, transitions }
+    }
+}
+
+impl GameState for Logged {
+    fn on_enter(&mut self) {
+        log(format!("{} on_enter", self.name));
+    }
+
+    fn on_exit(&mut self) {
+        log(format!("{} on_exit", self.name));
+    }
+
+    fn enter(&mut self, _graphics_device: &mut GraphicsDevice) {
+        log(format!("{} enter", self.name));
+    }
+
+    fn exit(&mut self, _graphics_device: &mut GraphicsDevice) {
+        log(format!("{} exit", self.name));
+    }
+
+    fn poll_input(&mut self, input: &mut InputSink) {
+        log(format!("{} poll_input", self.name));
+        input.gamepad_button(GamepadButton::South, true);
+    }
+
+    fn tick(&mut self, _dt: f32, _input: &Input) -> Transition {
+        log(format!("{} tick", self.name));
+
+        if self.transitions.is_empty() {
+            Transition::None
+        } else {
+            self.transitions.remove(0)
+        }
+    }
+
+    fn render(
+        &mut self,
+        _graphics_device: &GraphicsDevice,
+        _frame_encoder: &mut FrameEncoder,
+        _alpha: f32,
+        fade: f32,
+    ) {
+        log(format!("{} render {}", self.name, fade));
+    }
+
+    fn render_below(&self) -> bool {
+        self.overlay
+    }
+}
+
+impl InitialState for Logged {
+    fn init(_graphics_device: &mut GraphicsDevice) -> Self {
+        let pause = Logged::new("pause", true, vec![Transition::Pop]);
+        let game =
+            Logged::new("game", false, vec![Transition::Push(Box::new(pause)), Transition::Quit]);
+
+        Logged::new("title", false, vec![Transition::Switch(Box::new(game))])
+    }
+}
+
+/// Runs one tick and one render, like a frame of the game loop.
+fn frame<G: GameApp>(game_app: &mut G, graphics_device: &mut GraphicsDevice) {
+    game_app.tick(1.0 / 60.0, &Input::new());
+    game_app.render(graphics_device, 0.0);
+}
+
+#[test]
+fn push_pop_and_switch() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let mut stack = StateStack::<Logged>::init(&mut graphics_device);
+    assert_eq!(take_log(), ["title on_enter", "title enter"]);
+
+    frame(&mut stack, &mut graphics_device);
+    assert_eq!(
+        take_log(),
+        [
+            "title tick",
+            "title on_exit",
+            "game on_enter",
+            "title exit",
+            "game enter",
+            "game render 0"
+        ]
+    );
+
+    frame(&mut stack, &mut graphics_device);
+    assert_eq!(
+        take_log(),
+        ["game tick", "pause on_enter", "pause enter", "game render 0", "pause render 0"]
+    );
+    assert_eq!(stack.len(), 2);
+
+    frame(&mut stack, &mut graphics_device);
+    assert_eq!(take_log(), ["pause tick", "pause on_exit", "pause exit", "game render 0"]);
+
+    frame(&mut stack, &mut graphics_device);
+    assert_eq!(take_log(), ["game tick", "game on_exit", "game exit"]);
+    assert!(stack.should_exit());
+}
+
+struct Fading(Logged);
+
+impl GameState for Fading {
+    fn tick(&mut self, dt: f32, input: &Input) -> Transition {
+        self.0.tick(dt, input)
+    }
+
+    fn render(
+        &mut self,
+        graphics_device: &GraphicsDevice,
+        frame_encoder: &mut FrameEncoder,
+        alpha: f32,
+        fade: f32,
+    ) {
+        self.0.render(graphics_device, frame_encoder, alpha, fade);
+    }
+}
+
+impl InitialState for Fading {
+    fn init(_graphics_device: &mut GraphicsDevice) -> Self {
+        let next = Logged::new("next", false, vec![]);
+        Fading(Logged::new("first", false, vec![Transition::Switch(Box::new(next))]))
+    }
+
+    fn transition_duration() -> Duration {
+        Duration::from_millis(50)
+    }
+}
+
+#[test]
+fn transitions_fade_out_and_in() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let mut stack = StateStack::<Fading>::init(&mut graphics_device);
+    take_log();
+
+    let mut fades = Vec::new();
+
+    for _ in 0..8 {
+        stack.tick(0.02, &Input::new());
+        fades.push(stack.fade());
+        stack.render(&mut graphics_device, 0.0);
+    }
+
+    let expected = [0.0, 0.4, 0.8, 1.0, 0.6, 0.2, 0.0, 0.0];
+
+    for (fade, expected) in fades.iter().zip(expected) {
+        assert!((fade - expected).abs() < 1e-5, "fades {:?}, expected {:?}", fades, expected);
+    }
+
+    assert!(!stack.in_transition());
+    assert_eq!(take_log().iter().filter(|line| line.ends_with("tick")).count(), 5);
+}
+
+#[test]
+fn transitions_happen_at_the_same_tick_without_rendering() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let mut stack = StateStack::<Fading>::init(&mut graphics_device);
+    take_log();
+
+    // The fade out takes three ticks after the one requesting the switch, rendering or
+    // not, like in a replay.
+    for _ in 0..3 {
+        stack.tick(0.02, &Input::new());
+    }
+
+    assert_eq!(take_log(), ["first tick"]);
+    stack.tick(0.02, &Input::new());
+    assert_eq!(take_log(), ["next on_enter"]);
+    stack.tick(0.02, &Input::new());
+    assert_eq!(take_log(), ["next tick"]);
+
+    // GPU resources are only created once there's a frame to render.
+    stack.render(&mut graphics_device, 0.0);
+    assert_eq!(take_log(), ["next enter", "next render 0.6"]);
+
+    // States which never rendered aren't exited on the GPU either.
+    let mut stack = StateStack::<Logged>::init(&mut graphics_device);
+    take_log();
+
+    for _ in 0..4 {
+        stack.tick(1.0 / 60.0, &Input::new());
+    }
+
+    assert!(stack.should_exit());
+    stack.render(&mut graphics_device, 0.0);
+    assert_eq!(
+        take_log(),
+        [
+            "title tick",
+            "title on_exit",
+            "game on_enter",
+            "game tick",
+            "pause on_enter",
+            "pause tick",
+            "pause on_exit",
+            "game tick",
+            "game on_exit",
+            "title exit"
+        ]
+    );
+}
+
+#[test]
+fn suspending_recreates_gpu_resources_on_the_new_device() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let mut stack = StateStack::<Logged>::init(&mut graphics_device);
+    frame(&mut stack, &mut graphics_device);
+    frame(&mut stack, &mut graphics_device);
+    assert_eq!(stack.len(), 2);
+    take_log();
+
+    // The pause menu is popped without a render, it still exits on the old device.
+    stack.tick(1.0 / 60.0, &Input::new());
+    stack.suspended(&mut graphics_device);
+    assert_eq!(take_log(), ["pause tick", "pause on_exit", "pause exit", "game exit"]);
+
+    drop(graphics_device);
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    stack.resumed(&mut graphics_device);
+    assert_eq!(take_log(), ["game enter"]);
+
+    stack.render(&mut graphics_device, 0.0);
+    assert_eq!(take_log(), ["game render 0"]);
+}
+
+#[test]
+fn every_state_polls_input() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let mut stack = StateStack::<Logged>::init(&mut graphics_device);
+    frame(&mut stack, &mut graphics_device);
+    frame(&mut stack, &mut graphics_device);
+    take_log();
+
+    let mut input_sink = InputSink::new();
+    stack.poll_input(&mut input_sink);
+
+    assert_eq!(take_log(), ["game poll_input", "pause poll_input"]);
+    let events: Vec<_> = input_sink.drain().collect();
+    assert_eq!(events, [InputEvent::ButtonPressed(GamepadButton::South.into()); 2]);
+}
+
+/// Counts its ticks and pushes another counter on its first one.
+struct Counter {
+    ticks: u64,
+    push: bool,
+}
+
+impl GameState for Counter {
+    fn tick(&mut self, _dt: f32, _input: &Input) -> Transition {
+        self.ticks += 1;
+
+        if std::mem::take(&mut self.push) {
+            Transition::Push(Box::new(Counter { ticks: 0, push: false }))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn checksum(&self) -> Option<u64> {
+        Some(self.ticks)
+    }
+
+    fn render(
+        &mut self,
+        _graphics_device: &GraphicsDevice,
+        _frame_encoder: &mut FrameEncoder,
+        _alpha: f32,
+        _fade: f32,
+    ) {
+    }
+}
+
+impl InitialState for Counter {
+    fn init(_graphics_device: &mut GraphicsDevice) -> Self {
+        Counter { ticks: 0, push: true }
+    }
+}
+
+#[test]
+fn checksums_combine_every_state() {
+    let mut graphics_device = pollster::block_on(GraphicsDevice::new_headless(64, 64))
+        .expect("Failed to create a headless graphics device");
+
+    let stack = StateStack::<Logged>::init(&mut graphics_device);
+    assert_eq!(stack.checksum(), None);
+
+    let mut stack = StateStack::<Counter>::init(&mut graphics_device);
+    let mut checksums = vec![stack.checksum()];
+
+    // Ticking the bottom counter and pushing a new one, then ticking the new one.
+    for _ in 0..2 {
+        stack.tick(1.0 / 60.0, &Input::new());
+        checksums.push(stack.checksum());
+    }
+
+    assert_eq!(stack.len(), 2);
+    assert!(checksums.iter().all(Option::is_some));
+    assert_ne!(checksums[0], checksums[1]);
+    assert_ne!(checksums[1], checksums[2]);
+
+    // The same states give the same checksum.
+    let mut other = StateStack::<Counter>::init(&mut graphics_device);
+    other.tick(1.0 / 60.0, &Input::new());
+    other.tick(1.0 / 60.0, &Input::new());
+    assert_eq!(other.checksum(), checksums[2]);
+}
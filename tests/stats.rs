@@ -0,0 +1,82 @@
+use simple_game::stats::{FrameStats, FrameTiming, TimingSummary};
+use std::time::Duration;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn timing(frame_ms: u64, tick_ms: u64, render_ms: u64) -> FrameTiming {
+    FrameTiming {
+        frame_time: ms(frame_ms),
+        tick_time: ms(tick_ms),
+        render_time: ms(render_ms),
+        ticks: 1,
+    }
+}
+
+#[test]
+fn summaries_use_the_nearest_rank() {
+    assert_eq!(TimingSummary::new([]), TimingSummary::default());
+
+    // 1 to 100 ms, shuffled.
+    let summary = TimingSummary::new((1..=100).map(|i| ms(i * 37 % 101)));
+    assert_eq!(summary.min, ms(1));
+    assert_eq!(summary.max, ms(100));
+    assert_eq!(summary.mean, Duration::from_micros(50_500));
+    assert_eq!(summary.p50, ms(50));
+    assert_eq!(summary.p95, ms(95));
+    assert_eq!(summary.p99, ms(99));
+
+    let summary = TimingSummary::new([ms(10), ms(40), ms(20)]);
+    assert_eq!(summary.mean, ms(70) / 3);
+    assert_eq!(summary.p50, ms(20));
+    assert_eq!(summary.p95, ms(40));
+
+    let summary = TimingSummary::new([ms(7)]);
+    assert_eq!((summary.min, summary.p50, summary.p99, summary.max), (ms(7), ms(7), ms(7), ms(7)));
+}
+
+#[test]
+fn history_keeps_the_most_recent_frames() {
+    let mut stats = FrameStats::new(3);
+    assert_eq!(stats.fps(), 0.0);
+    assert!(stats.last().is_none());
+
+    for frame_ms in [10, 20, 30, 40, 50] {
+        stats.record(timing(frame_ms, 1, 2));
+    }
+
+    let frame_times: Vec<_> = stats.history().iter().map(|timing| timing.frame_time).collect();
+    assert_eq!(frame_times, [ms(30), ms(40), ms(50)]);
+    assert_eq!(stats.last(), Some(&timing(50, 1, 2)));
+    assert_eq!(stats.total_frames(), 5);
+
+    assert_eq!(stats.frame_time().min, ms(30));
+    assert_eq!(stats.frame_time().mean, ms(40));
+    assert!((stats.fps() - 25.0).abs() < 1e-9);
+    assert_eq!(stats.tick_time().max, ms(1));
+    assert_eq!(stats.render_time().mean, ms(2));
+
+    stats.clear();
+    assert!(stats.history().is_empty());
+    assert_eq!(stats.total_frames(), 0);
+}
+
+#[test]
+fn frames_over_budget_count_their_work_time() {
+    let mut stats = FrameStats::new(2);
+    stats.record(timing(100, 50, 50));
+    assert_eq!(stats.frames_over_budget(), 0);
+
+    // Waiting for the frame to start doesn't count, only ticking and rendering.
+    stats.set_budget(Some(ms(16)));
+    stats.record(timing(100, 5, 10));
+    stats.record(timing(16, 10, 7));
+    stats.record(timing(16, 8, 8));
+    assert_eq!(stats.frames_over_budget(), 1);
+    assert_eq!(stats.total_frames_over_budget(), 1);
+
+    stats.record(timing(16, 8, 9));
+    assert_eq!(stats.frames_over_budget(), 1);
+    assert_eq!(stats.total_frames_over_budget(), 2);
+}
@@ -0,0 +1,95 @@
+use simple_game::{
+    audio::{SfxPreset, SynthParams, Waveform},
+    Error,
+};
+
+const SAMPLE_RATE: u32 = 8000;
+
+const PRESETS: [SfxPreset; 7] = [
+    SfxPreset::Coin,
+    SfxPreset::Laser,
+    SfxPreset::Explosion,
+    SfxPreset::PowerUp,
+    SfxPreset::Hit,
+    SfxPreset::Jump,
+    SfxPreset::Blip,
+];
+
+#[test]
+fn sounds_follow_their_envelope_and_frequency() {
+    // A 1 kHz square wave at 8 kHz is high for 4 samples and low for 4.
+    let params = SynthParams {
+        frequency: 1000.0,
+        attack: 0.01,
+        sustain: 0.1,
+        decay: 0.1,
+        volume: 1.0,
+        ..SynthParams::default()
+    };
+
+    let samples = params.render(SAMPLE_RATE);
+    assert_eq!(samples.len(), 1680);
+    assert_eq!(samples[0], 0.0);
+
+    let sustained = &samples[80..96];
+    assert_eq!(sustained, [1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0].repeat(2));
+    assert!(samples[1600].abs() < 0.11);
+
+    // Sliding down below the minimum frequency ends the sound early.
+    let slide = SynthParams { frequency_slide: -20.0, min_frequency: 100.0, ..params.clone() };
+    let length = slide.render(SAMPLE_RATE).len();
+    assert!((1300..1360).contains(&length), "expected about 0.166 s, got {} samples", length);
+
+    let sound = SynthParams { waveform: Waveform::Sine, ..params }.to_sound(SAMPLE_RATE);
+    assert_eq!(
+        (sound.sample_rate(), sound.channels(), sound.frame_count()),
+        (SAMPLE_RATE, 1, 1680)
+    );
+}
+
+#[test]
+fn presets_vary_with_their_seed() {
+    for preset in PRESETS {
+        let sounds: Vec<Vec<f32>> =
+            (0..4).map(|seed| SynthParams::preset(preset, seed).render(SAMPLE_RATE)).collect();
+
+        for samples in &sounds {
+            assert!(!samples.is_empty(), "{:?} is empty", preset);
+            assert!(samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+            assert!(samples.iter().any(|sample| sample.abs() > 0.1), "{:?} is silent", preset);
+        }
+
+        assert_ne!(sounds[0], sounds[1], "{:?} doesn't vary", preset);
+        assert_eq!(sounds[2], SynthParams::preset(preset, 2).render(SAMPLE_RATE));
+    }
+}
+
+#[test]
+fn params_round_trip_through_text() {
+    for preset in PRESETS {
+        let params = SynthParams::preset(preset, 7);
+        assert_eq!(params.to_string().parse::<SynthParams>().unwrap(), params);
+    }
+
+    let path = std::env::temp_dir().join(format!("simple-game-synth-{}.txt", std::process::id()));
+    let params = SynthParams::preset(SfxPreset::Explosion, 3);
+    params.save(&path).unwrap();
+    assert_eq!(SynthParams::load(&path).unwrap(), params);
+    std::fs::remove_file(&path).unwrap();
+
+    // Missing parameters keep their defaults.
+    let params: SynthParams = "# coin\nwaveform = sine\n\nfrequency = 980.5\n".parse().unwrap();
+    assert_eq!(
+        params,
+        SynthParams { waveform: Waveform::Sine, frequency: 980.5, ..SynthParams::default() }
+    );
+
+    for (text, expected_line) in
+        [("waveform = organ", 1), ("decay = 0.1\nwobble = 2", 2), ("decay = soon", 1), ("decay", 1)]
+    {
+        match text.parse::<SynthParams>() {
+            Err(Error::SynthParseError { line, .. }) => assert_eq!(line, expected_line),
+            other => panic!("expected a parse error for {:?}, got {:?}", text, other),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use simple_game::timestep::FixedTimestep;
+use std::time::Duration;
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn frame_time_accumulates_into_whole_ticks() {
+    let mut timestep = FixedTimestep::new(100);
+    assert_eq!(timestep.tick_dt(), ms(10));
+
+    assert_eq!(timestep.advance(ms(4)), 0);
+    assert_eq!(timestep.advance(ms(4)), 0);
+    assert_eq!(timestep.advance(ms(4)), 1);
+    assert_eq!(timestep.advance(ms(35)), 3);
+
+    // 47 ms in total, the leftover 7 ms carry over to the next frame.
+    assert_eq!(timestep.advance(ms(3)), 1);
+    assert_eq!(timestep.advance(Duration::ZERO), 0);
+}
+
+#[test]
+fn long_frames_are_clamped() {
+    let mut timestep = FixedTimestep::new(100);
+    assert_eq!(timestep.advance(Duration::from_secs(10)), 25);
+
+    let mut timestep = FixedTimestep::new(100).with_max_frame_time(ms(50));
+    assert_eq!(timestep.advance(Duration::from_secs(10)), 5);
+    assert_eq!(timestep.advance(ms(49)), 4);
+}
+
+#[test]
+fn alpha_is_the_progress_towards_the_next_tick() {
+    let mut timestep = FixedTimestep::new(100);
+    assert_eq!(timestep.alpha(), 0.0);
+
+    timestep.advance(ms(5));
+    assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+
+    timestep.advance(ms(7));
+    assert!((timestep.alpha() - 0.2).abs() < 1e-6);
+
+    timestep.advance(ms(8));
+    assert_eq!(timestep.alpha(), 0.0);
+}